@@ -1,5 +1,10 @@
+use crate::error::{AppError, Result};
+use crate::models::{DevicePreset, ResponseFormat};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -20,18 +25,77 @@ pub struct Config {
     #[serde(default = "default_max_timeout")]
     pub max_timeout: u64,
 
+    /// Longest origin `Retry-After`, in seconds, waited out before one more
+    /// try. Longer ones fail the load with the hint passed on; 0 never waits.
+    #[serde(default = "default_origin_retry_after_max")]
+    pub origin_retry_after_max: u64,
+
     #[serde(default = "default_cache_ttl")]
     pub cache_ttl: u64,
 
+    /// Per-format overrides of `cache_ttl`, in seconds.
+    #[serde(default)]
+    pub cache_ttl_markdown: Option<u64>,
+
+    #[serde(default)]
+    pub cache_ttl_html: Option<u64>,
+
+    #[serde(default)]
+    pub cache_ttl_text: Option<u64>,
+
+    /// Covers both `screenshot` and `pageshot`.
+    #[serde(default)]
+    pub cache_ttl_screenshot: Option<u64>,
+
+    /// Keep cache entries gzipped, decompressing them on every read.
+    #[serde(default)]
+    pub cache_compress: bool,
+
+    #[serde(default = "default_idempotency_ttl")]
+    pub idempotency_ttl: u64,
+
     #[serde(default = "default_max_requests_per_page")]
     pub max_requests_per_page: usize,
 
     #[serde(default = "default_max_domains_per_page")]
     pub max_domains_per_page: usize,
 
+    #[serde(default = "default_max_batch_urls")]
+    pub max_batch_urls: usize,
+
+    /// Pages batch-style requests may hold at once; 0 means half the pool.
+    #[serde(default)]
+    pub batch_max_pages: usize,
+
     #[serde(default = "default_screenshot_dir")]
     pub screenshot_dir: PathBuf,
 
+    #[serde(default = "default_screenshot_naming")]
+    pub screenshot_naming: String,
+
+    #[serde(default = "default_screenshot_backend")]
+    pub screenshot_backend: String,
+
+    pub screenshot_s3_bucket: Option<String>,
+
+    #[serde(default = "default_screenshot_s3_region")]
+    pub screenshot_s3_region: String,
+
+    pub screenshot_s3_endpoint: Option<String>,
+
+    pub screenshot_s3_access_key_id: Option<String>,
+
+    pub screenshot_s3_secret_access_key: Option<String>,
+
+    pub screenshot_s3_public_url: Option<String>,
+
+    #[serde(default = "default_screenshot_s3_url_expiry")]
+    pub screenshot_s3_url_expiry: u64,
+
+    /// Tallest full-page capture in CSS pixels; taller pages keep their top.
+    #[serde(default = "default_screenshot_max_height")]
+    pub screenshot_max_height: u32,
+
     #[serde(default = "default_user_agent")]
     pub default_user_agent: String,
 
@@ -40,102 +104,490 @@ pub struct Config {
 
     #[serde(default = "default_user_agent_rotation")]
     pub user_agent_rotation: String,
+
+    /// `x-device` presets added to or replacing the built-in ones, keyed by
+    /// lowercase name.
+    #[serde(default)]
+    pub device_presets: HashMap<String, DevicePreset>,
+
+    #[serde(default)]
+    pub browser_lazy_init: bool,
+
+    #[serde(default)]
+    pub browser_context_isolation: bool,
+
+    #[serde(default)]
+    pub warm_context_domains: Vec<String>,
+
+    /// Domains that skip the per-domain rate limit and queue for a page at
+    /// high priority.
+    #[serde(default)]
+    pub priority_domains: Vec<String>,
+
+    #[serde(default)]
+    pub stealth_mode: bool,
+
+    #[serde(default = "default_true")]
+    pub disable_web_security: bool,
+
+    #[serde(default = "default_true")]
+    pub ignore_cert_errors: bool,
+
+    pub alt_text_service_url: Option<String>,
+
+    #[serde(default)]
+    pub allow_cdp_passthrough: bool,
+
+    #[serde(default)]
+    pub max_concurrent_per_key: usize,
+
+    /// Response formats requests authenticated with `api_key` may use.
+    /// Empty allows every format.
+    #[serde(default)]
+    pub api_key_allowed_formats: Vec<String>,
+
+    /// Answer blocked URLs with an empty `blocked` response instead of an
+    /// error.
+    #[serde(default)]
+    pub blocked_url_soft_fail: bool,
+
+    /// CSS selector list whose presence marks a page as paywalled.
+    #[serde(default = "default_paywall_selectors")]
+    pub paywall_selectors: String,
+
+    /// Content returned when extraction leaves next to no text. `{url}` and
+    /// `{title}` are filled in.
+    pub empty_content_template: Option<String>,
+
+    /// Phrases that mark a short page as a soft 404.
+    #[serde(default = "default_soft_404_phrases")]
+    pub soft_404_phrases: Vec<String>,
 }
 
 fn default_api_port() -> u16 { 14786 }
+fn default_true() -> bool { true }
 fn default_chrome_path() -> String { "/usr/bin/chromium".to_string() }
 fn default_browser_pool_size() -> usize { 10 }
 fn default_request_timeout() -> u64 { 30 }
 fn default_max_timeout() -> u64 { 180 }
+fn default_origin_retry_after_max() -> u64 { 5 }
 fn default_cache_ttl() -> u64 { 3600 }
+fn default_idempotency_ttl() -> u64 { 600 }
 fn default_max_requests_per_page() -> usize { 2000 }
 fn default_max_domains_per_page() -> usize { 200 }
+fn default_max_batch_urls() -> usize { 100 }
 fn default_screenshot_dir() -> PathBuf { PathBuf::from("/app/screenshots") }
+fn default_screenshot_naming() -> String { "uuid".to_string() }
+fn default_screenshot_backend() -> String { "local".to_string() }
+fn default_screenshot_s3_region() -> String { "us-east-1".to_string() }
+fn default_screenshot_s3_url_expiry() -> u64 { 3600 }
+/// Chrome's maximum texture size; taller captures fail or come back blank.
+fn default_screenshot_max_height() -> u32 { 16384 }
 fn default_user_agent() -> String {
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
 }
 fn default_user_agent_rotation() -> String { "off".to_string() }
+fn default_soft_404_phrases() -> Vec<String> {
+    [
+        "page not found",
+        "404 not found",
+        "error 404",
+        "404 error",
+        "page does not exist",
+        "page doesn't exist",
+        "page could not be found",
+        "page you requested could not be found",
+        "nothing was found at this location",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+fn default_paywall_selectors() -> String {
+    ".paywall, #paywall, [data-paywall], .subscription-required, .subscriber-only, \
+     .premium-content, .meteredContent, .tp-modal, .piano-offer".to_string()
+}
+
+/// Parse an optional raw env value: unset falls back to the default, while a
+/// value that is set but fails to parse is a configuration error.
+fn parse_value<T>(name: &str, raw: Option<String>, default: fn() -> T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match raw.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) {
+        None => Ok(default()),
+        Some(value) => value.parse().map_err(|e| {
+            AppError::ConfigError(format!(
+                "Invalid value for {}: '{}' ({})",
+                name, value, e
+            ))
+        }),
+    }
+}
 
-fn load_user_agent_pool() -> Vec<String> {
-    let from_file = std::env::var("USER_AGENT_POOL_FILE")
+fn parse_flag(name: &str, raw: Option<String>, default: bool) -> Result<bool> {
+    match raw.map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()) {
+        None => Ok(default),
+        Some(value) => match value.as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            _ => Err(AppError::ConfigError(format!(
+                "Invalid value for {}: '{}' (expected true or false)",
+                name, value
+            ))),
+        },
+    }
+}
+
+fn env_flag(name: &str, default: bool) -> Result<bool> {
+    parse_flag(name, std::env::var(name).ok(), default)
+}
+
+/// Trimmed value of `name`, or `None` when unset or blank.
+fn env_string(name: &str) -> Option<String> {
+    std::env::var(name)
         .ok()
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
-        .and_then(|path| std::fs::read_to_string(&path).ok());
+}
 
-    let raw = from_file.or_else(|| {
-        std::env::var("USER_AGENT_POOL")
-            .ok()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-    });
+/// Like `env_value`, but unset stays `None` instead of taking a default.
+fn env_optional<T>(name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    env_string(name)
+        .map(|value| {
+            value.parse().map_err(|e| {
+                AppError::ConfigError(format!("Invalid value for {}: '{}' ({})", name, value, e))
+            })
+        })
+        .transpose()
+}
 
-    raw.map(|text| parse_user_agent_pool(&text))
-        .unwrap_or_default()
+fn env_value<T>(name: &str, default: fn() -> T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    parse_value(name, std::env::var(name).ok(), default)
+}
+
+fn load_user_agent_pool() -> Result<Vec<String>> {
+    if let Some(path) = env_string("USER_AGENT_POOL_FILE") {
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            return parse_user_agent_pool("USER_AGENT_POOL_FILE", &text);
+        }
+    }
+
+    env_string("USER_AGENT_POOL")
+        .map(|text| parse_user_agent_pool("USER_AGENT_POOL", &text))
+        .transpose()
+        .map(Option::unwrap_or_default)
 }
 
-fn parse_user_agent_pool(text: &str) -> Vec<String> {
-    text.split(|c| c == '\n' || c == '|')
+fn parse_user_agent_pool(name: &str, text: &str) -> Result<Vec<String>> {
+    // A JSON array avoids any ambiguity for UAs that contain `|`. Anything
+    // that opens like one has to parse as one, rather than being split into
+    // fragments of broken JSON.
+    if text.trim_start().starts_with('[') {
+        let list: Vec<String> = serde_json::from_str(text).map_err(|e| {
+            AppError::ConfigError(format!("Invalid value for {}: expected a JSON array of strings ({})", name, e))
+        })?;
+        return Ok(list.into_iter()
+            .map(|ua| ua.trim().to_string())
+            .filter(|ua| !ua.is_empty())
+            .collect());
+    }
+
+    Ok(text.split(['\n', '|'])
         .map(str::trim)
         .filter(|line| !line.is_empty() && !line.starts_with('#'))
         .map(str::to_string)
+        .collect())
+}
+
+/// JSON object of device name to preset, e.g.
+/// `{"galaxy-s22": {"user_agent": "...", "width": 360, "height": 780}}`.
+fn parse_device_presets(name: &str, text: &str) -> Result<HashMap<String, DevicePreset>> {
+    let presets: HashMap<String, DevicePreset> = serde_json::from_str(text).map_err(|e| {
+        AppError::ConfigError(format!("Invalid value for {}: expected a JSON object of presets ({})", name, e))
+    })?;
+    Ok(presets.into_iter()
+        .map(|(device, preset)| (device.trim().to_lowercase(), preset))
+        .collect())
+}
+
+fn env_device_presets(name: &str) -> Result<HashMap<String, DevicePreset>> {
+    env_string(name)
+        .map(|text| parse_device_presets(name, &text))
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+fn parse_domain_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|d| d.trim().trim_start_matches("*.").to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+fn parse_phrase_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
         .collect()
 }
 
+fn env_domain_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|v| parse_domain_list(&v))
+        .unwrap_or_default()
+}
+
 impl Config {
-    pub fn from_env() -> anyhow::Result<Self> {
+    pub fn from_env() -> Result<Self> {
         dotenvy::dotenv().ok();
 
         let config = Config {
-            api_port: std::env::var("API_PORT")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_api_port),
+            api_port: env_value("API_PORT", default_api_port)?,
             api_key: std::env::var("API_KEY").ok(),
             chrome_path: std::env::var("CHROME_PATH")
                 .unwrap_or_else(|_| default_chrome_path()),
-            browser_pool_size: std::env::var("BROWSER_POOL_SIZE")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_browser_pool_size),
-            request_timeout: std::env::var("REQUEST_TIMEOUT")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_request_timeout),
-            max_timeout: std::env::var("MAX_TIMEOUT")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_max_timeout),
-            cache_ttl: std::env::var("CACHE_TTL")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_cache_ttl),
-            max_requests_per_page: std::env::var("MAX_REQUESTS_PER_PAGE")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_max_requests_per_page),
-            max_domains_per_page: std::env::var("MAX_DOMAINS_PER_PAGE")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or_else(default_max_domains_per_page),
+            browser_pool_size: env_value("BROWSER_POOL_SIZE", default_browser_pool_size)?,
+            request_timeout: env_value("REQUEST_TIMEOUT", default_request_timeout)?,
+            max_timeout: env_value("MAX_TIMEOUT", default_max_timeout)?,
+            origin_retry_after_max: env_value("ORIGIN_RETRY_AFTER_MAX", default_origin_retry_after_max)?,
+            cache_ttl: env_value("CACHE_TTL", default_cache_ttl)?,
+            cache_ttl_markdown: env_optional("CACHE_TTL_MARKDOWN")?,
+            cache_ttl_html: env_optional("CACHE_TTL_HTML")?,
+            cache_ttl_text: env_optional("CACHE_TTL_TEXT")?,
+            cache_ttl_screenshot: env_optional("CACHE_TTL_SCREENSHOT")?,
+            cache_compress: env_flag("CACHE_COMPRESS", false)?,
+            idempotency_ttl: env_value("IDEMPOTENCY_TTL", default_idempotency_ttl)?,
+            max_requests_per_page: env_value("MAX_REQUESTS_PER_PAGE", default_max_requests_per_page)?,
+            max_domains_per_page: env_value("MAX_DOMAINS_PER_PAGE", default_max_domains_per_page)?,
+            max_batch_urls: env_value("MAX_BATCH_URLS", default_max_batch_urls)?,
+            batch_max_pages: env_value("BATCH_MAX_PAGES", || 0)?,
             screenshot_dir: std::env::var("SCREENSHOT_DIR")
                 .map(PathBuf::from)
                 .unwrap_or_else(|_| default_screenshot_dir()),
+            screenshot_naming: std::env::var("SCREENSHOT_NAMING")
+                .ok()
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(default_screenshot_naming),
+            screenshot_backend: std::env::var("SCREENSHOT_BACKEND")
+                .ok()
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(default_screenshot_backend),
+            screenshot_s3_bucket: env_string("SCREENSHOT_S3_BUCKET"),
+            screenshot_s3_region: env_string("SCREENSHOT_S3_REGION")
+                .unwrap_or_else(default_screenshot_s3_region),
+            screenshot_s3_endpoint: env_string("SCREENSHOT_S3_ENDPOINT"),
+            screenshot_s3_access_key_id: env_string("SCREENSHOT_S3_ACCESS_KEY_ID")
+                .or_else(|| env_string("AWS_ACCESS_KEY_ID")),
+            screenshot_s3_secret_access_key: env_string("SCREENSHOT_S3_SECRET_ACCESS_KEY")
+                .or_else(|| env_string("AWS_SECRET_ACCESS_KEY")),
+            screenshot_s3_public_url: env_string("SCREENSHOT_S3_PUBLIC_URL"),
+            screenshot_s3_url_expiry: env_value("SCREENSHOT_S3_URL_EXPIRY", default_screenshot_s3_url_expiry)?,
+            screenshot_max_height: env_value("SCREENSHOT_MAX_HEIGHT", default_screenshot_max_height)?,
             default_user_agent: std::env::var("DEFAULT_USER_AGENT")
                 .ok()
                 .map(|s| s.trim().to_string())
                 .filter(|s| !s.is_empty())
                 .unwrap_or_else(default_user_agent),
-            user_agent_pool: load_user_agent_pool(),
+            user_agent_pool: load_user_agent_pool()?,
             user_agent_rotation: std::env::var("USER_AGENT_ROTATION")
                 .ok()
                 .map(|s| s.trim().to_lowercase())
                 .filter(|s| !s.is_empty())
                 .unwrap_or_else(default_user_agent_rotation),
+            device_presets: env_device_presets("DEVICE_PRESETS")?,
+            browser_lazy_init: env_flag("BROWSER_LAZY_INIT", false)?,
+            browser_context_isolation: env_flag("BROWSER_CONTEXT_ISOLATION", false)?,
+            warm_context_domains: env_domain_list("WARM_CONTEXT_DOMAINS"),
+            priority_domains: env_domain_list("PRIORITY_DOMAINS"),
+            stealth_mode: env_flag("STEALTH_MODE", false)?,
+            disable_web_security: env_flag("DISABLE_WEB_SECURITY", true)?,
+            ignore_cert_errors: env_flag("IGNORE_CERT_ERRORS", true)?,
+            alt_text_service_url: std::env::var("ALT_TEXT_SERVICE_URL")
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            allow_cdp_passthrough: env_flag("ALLOW_CDP_PASSTHROUGH", false)?,
+            max_concurrent_per_key: env_value("MAX_CONCURRENT_PER_KEY", || 0)?,
+            api_key_allowed_formats: env_string("API_KEY_ALLOWED_FORMATS")
+                .map(|v| parse_phrase_list(&v))
+                .unwrap_or_default(),
+            blocked_url_soft_fail: env_flag("BLOCKED_URL_SOFT_FAIL", false)?,
+            paywall_selectors: env_string("PAYWALL_SELECTORS").unwrap_or_else(default_paywall_selectors),
+            empty_content_template: env_string("EMPTY_CONTENT_TEMPLATE"),
+            soft_404_phrases: env_string("SOFT_404_PHRASES")
+                .map(|v| parse_phrase_list(&v))
+                .unwrap_or_else(default_soft_404_phrases),
         };
 
+        config.validate()?;
+
         Ok(config)
     }
+
+    /// `BATCH_MAX_PAGES`, defaulting to half the pool rounded up and never
+    /// more than the whole pool.
+    pub fn batch_page_limit(&self) -> usize {
+        match self.batch_max_pages {
+            0 => self.browser_pool_size.div_ceil(2),
+            limit => limit.min(self.browser_pool_size),
+        }
+    }
+
+    /// Cache lifetime in seconds for responses in `format`: its
+    /// `CACHE_TTL_<FORMAT>` when set, otherwise `CACHE_TTL`.
+    pub fn cache_ttl_for(&self, format: ResponseFormat) -> u64 {
+        let specific = match format {
+            ResponseFormat::Default | ResponseFormat::Markdown => self.cache_ttl_markdown,
+            ResponseFormat::Html => self.cache_ttl_html,
+            ResponseFormat::Text => self.cache_ttl_text,
+            ResponseFormat::Screenshot | ResponseFormat::Pageshot => self.cache_ttl_screenshot,
+        };
+        specific.unwrap_or(self.cache_ttl)
+    }
+
+    /// Whether a request may ask for `format`. Only requests carrying
+    /// `API_KEY` are restricted, so without a key every format is allowed.
+    pub fn format_allowed(&self, format: &str) -> bool {
+        self.api_key.is_none()
+            || self.api_key_allowed_formats.is_empty()
+            || self.api_key_allowed_formats.iter().any(|f| f == format)
+    }
+
+    /// The `x-device` preset called `name`: one from `DEVICE_PRESETS` first,
+    /// then a built-in one.
+    pub fn device_preset(&self, name: &str) -> Option<DevicePreset> {
+        self.device_presets.get(&name.trim().to_lowercase())
+            .cloned()
+            .or_else(|| DevicePreset::builtin(name))
+    }
+
+    /// Seconds a request may run: its own `x-timeout` or `REQUEST_TIMEOUT`,
+    /// never more than `MAX_TIMEOUT`.
+    pub fn effective_timeout(&self, requested: Option<u64>) -> u64 {
+        requested.unwrap_or(self.request_timeout).min(self.max_timeout)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.api_port == 0 {
+            return Err(AppError::ConfigError(
+                "API_PORT must be between 1 and 65535".to_string(),
+            ));
+        }
+
+        if self.browser_pool_size == 0 {
+            return Err(AppError::ConfigError(
+                "BROWSER_POOL_SIZE must be at least 1".to_string(),
+            ));
+        }
+
+        if self.request_timeout == 0 {
+            return Err(AppError::ConfigError(
+                "REQUEST_TIMEOUT must be at least 1 second".to_string(),
+            ));
+        }
+
+        if self.max_timeout < self.request_timeout {
+            return Err(AppError::ConfigError(format!(
+                "MAX_TIMEOUT ({}) must not be lower than REQUEST_TIMEOUT ({})",
+                self.max_timeout, self.request_timeout
+            )));
+        }
+
+        if self.max_requests_per_page == 0 {
+            return Err(AppError::ConfigError(
+                "MAX_REQUESTS_PER_PAGE must be at least 1".to_string(),
+            ));
+        }
+
+        if self.max_domains_per_page == 0 {
+            return Err(AppError::ConfigError(
+                "MAX_DOMAINS_PER_PAGE must be at least 1".to_string(),
+            ));
+        }
+
+        if self.screenshot_max_height == 0 {
+            return Err(AppError::ConfigError(
+                "SCREENSHOT_MAX_HEIGHT must be at least 1".to_string(),
+            ));
+        }
+
+        let invalid_device = self.device_presets.iter()
+            .find(|(_, p)| p.width == 0 || p.height == 0 || p.device_scale_factor <= 0.0);
+        if let Some((name, _)) = invalid_device {
+            return Err(AppError::ConfigError(format!(
+                "DEVICE_PRESETS entry '{}' needs a non-zero width, height and device_scale_factor",
+                name
+            )));
+        }
+
+        if !matches!(self.screenshot_naming.as_str(), "uuid" | "hash") {
+            return Err(AppError::ConfigError(format!(
+                "SCREENSHOT_NAMING must be uuid or hash, got {}",
+                self.screenshot_naming
+            )));
+        }
+
+        match self.screenshot_backend.as_str() {
+            "local" => {}
+            "s3" => {
+                if self.screenshot_s3_bucket.is_none()
+                    || self.screenshot_s3_access_key_id.is_none()
+                    || self.screenshot_s3_secret_access_key.is_none()
+                {
+                    return Err(AppError::ConfigError(
+                        "SCREENSHOT_BACKEND=s3 needs SCREENSHOT_S3_BUCKET and access key credentials".to_string(),
+                    ));
+                }
+                if let Some(endpoint) = &self.screenshot_s3_endpoint {
+                    url::Url::parse(endpoint).map_err(|e| {
+                        AppError::ConfigError(format!("SCREENSHOT_S3_ENDPOINT is not a valid URL: {}", e))
+                    })?;
+                }
+            }
+            other => {
+                return Err(AppError::ConfigError(format!(
+                    "SCREENSHOT_BACKEND must be local or s3, got {}",
+                    other
+                )));
+            }
+        }
+
+        if self.max_batch_urls == 0 {
+            return Err(AppError::ConfigError(
+                "MAX_BATCH_URLS must be at least 1".to_string(),
+            ));
+        }
+
+        const FORMATS: &[&str] = &["default", "markdown", "html", "text", "screenshot", "pageshot"];
+        if let Some(unknown) = self.api_key_allowed_formats.iter().find(|f| !FORMATS.contains(&f.as_str())) {
+            return Err(AppError::ConfigError(format!(
+                "API_KEY_ALLOWED_FORMATS has unknown format '{}' (expected {})",
+                unknown,
+                FORMATS.join(", ")
+            )));
+        }
+
+        if scraper::Selector::parse(&self.paywall_selectors).is_err() {
+            return Err(AppError::ConfigError(format!(
+                "PAYWALL_SELECTORS is not a valid CSS selector list: {}",
+                self.paywall_selectors
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -147,62 +599,223 @@ impl Default for Config {
             browser_pool_size: default_browser_pool_size(),
             request_timeout: default_request_timeout(),
             max_timeout: default_max_timeout(),
+            origin_retry_after_max: default_origin_retry_after_max(),
             cache_ttl: default_cache_ttl(),
+            cache_ttl_markdown: None,
+            cache_ttl_html: None,
+            cache_ttl_text: None,
+            cache_ttl_screenshot: None,
+            cache_compress: false,
+            idempotency_ttl: default_idempotency_ttl(),
             max_requests_per_page: default_max_requests_per_page(),
             max_domains_per_page: default_max_domains_per_page(),
+            max_batch_urls: default_max_batch_urls(),
+            batch_max_pages: 0,
             screenshot_dir: default_screenshot_dir(),
+            screenshot_naming: default_screenshot_naming(),
+            screenshot_backend: default_screenshot_backend(),
+            screenshot_s3_bucket: None,
+            screenshot_s3_region: default_screenshot_s3_region(),
+            screenshot_s3_endpoint: None,
+            screenshot_s3_access_key_id: None,
+            screenshot_s3_secret_access_key: None,
+            screenshot_s3_public_url: None,
+            screenshot_s3_url_expiry: default_screenshot_s3_url_expiry(),
+            screenshot_max_height: default_screenshot_max_height(),
             default_user_agent: default_user_agent(),
             user_agent_pool: Vec::new(),
             user_agent_rotation: default_user_agent_rotation(),
+            device_presets: HashMap::new(),
+            browser_lazy_init: false,
+            browser_context_isolation: false,
+            warm_context_domains: Vec::new(),
+            priority_domains: Vec::new(),
+            stealth_mode: false,
+            disable_web_security: true,
+            ignore_cert_errors: true,
+            alt_text_service_url: None,
+            allow_cdp_passthrough: false,
+            max_concurrent_per_key: 0,
+            api_key_allowed_formats: Vec::new(),
+            blocked_url_soft_fail: false,
+            paywall_selectors: default_paywall_selectors(),
+            empty_content_template: None,
+            soft_404_phrases: default_soft_404_phrases(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_user_agent_pool;
+    use super::*;
 
     #[test]
     fn splits_on_pipe() {
-        let pool = parse_user_agent_pool("UA1|UA2|UA3");
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", "UA1|UA2|UA3").unwrap();
         assert_eq!(pool, vec!["UA1", "UA2", "UA3"]);
     }
 
     #[test]
     fn splits_on_newline() {
-        let pool = parse_user_agent_pool("UA1\nUA2\nUA3");
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", "UA1\nUA2\nUA3").unwrap();
         assert_eq!(pool, vec!["UA1", "UA2", "UA3"]);
     }
 
     #[test]
     fn splits_on_both_separators_mixed() {
-        let pool = parse_user_agent_pool("UA1|UA2\nUA3|UA4");
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", "UA1|UA2\nUA3|UA4").unwrap();
         assert_eq!(pool, vec!["UA1", "UA2", "UA3", "UA4"]);
     }
 
     #[test]
     fn skips_comment_lines() {
-        let pool = parse_user_agent_pool("UA1\n# comment\nUA2\n#another");
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", "UA1\n# comment\nUA2\n#another").unwrap();
         assert_eq!(pool, vec!["UA1", "UA2"]);
     }
 
     #[test]
     fn skips_empty_lines_and_trims_whitespace() {
-        let pool = parse_user_agent_pool("  UA1  \n\n   \n  UA2\t\n");
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", "  UA1  \n\n   \n  UA2\t\n").unwrap();
         assert_eq!(pool, vec!["UA1", "UA2"]);
     }
 
+    #[test]
+    fn parses_json_list() {
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", r#"["UA1", " UA|2 ", ""]"#).unwrap();
+        assert_eq!(pool, vec!["UA1", "UA|2"]);
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let err = parse_user_agent_pool("USER_AGENT_POOL", "[not json|UA2").unwrap_err();
+        assert!(err.to_string().contains("USER_AGENT_POOL"));
+    }
+
     #[test]
     fn empty_input_returns_empty_pool() {
-        assert!(parse_user_agent_pool("").is_empty());
-        assert!(parse_user_agent_pool("   \n\n   ").is_empty());
-        assert!(parse_user_agent_pool("# only a comment").is_empty());
+        assert!(parse_user_agent_pool("USER_AGENT_POOL", "").unwrap().is_empty());
+        assert!(parse_user_agent_pool("USER_AGENT_POOL", "   \n\n   ").unwrap().is_empty());
+        assert!(parse_user_agent_pool("USER_AGENT_POOL", "# only a comment").unwrap().is_empty());
     }
 
     #[test]
     fn preserves_user_agent_content_with_internal_spaces() {
         let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/120.0.0.0";
-        let pool = parse_user_agent_pool(ua);
+        let pool = parse_user_agent_pool("USER_AGENT_POOL", ua).unwrap();
         assert_eq!(pool, vec![ua]);
     }
+
+    #[test]
+    fn unset_value_uses_default() {
+        assert_eq!(parse_value("API_PORT", None, default_api_port).unwrap(), 14786);
+        assert_eq!(
+            parse_value("API_PORT", Some("   ".to_string()), default_api_port).unwrap(),
+            14786
+        );
+    }
+
+    #[test]
+    fn set_value_is_parsed() {
+        let size = parse_value("BROWSER_POOL_SIZE", Some(" 4 ".to_string()), default_browser_pool_size);
+        assert_eq!(size.unwrap(), 4);
+    }
+
+    #[test]
+    fn set_but_invalid_value_is_an_error() {
+        let err = parse_value("BROWSER_POOL_SIZE", Some("ten".to_string()), default_browser_pool_size)
+            .unwrap_err();
+        assert!(matches!(err, AppError::ConfigError(_)));
+        assert!(err.to_string().contains("BROWSER_POOL_SIZE"));
+        assert!(err.to_string().contains("'ten'"));
+
+        let err = parse_value("API_PORT", Some("70000".to_string()), default_api_port).unwrap_err();
+        assert!(err.to_string().contains("API_PORT"));
+    }
+
+    #[test]
+    fn batch_page_limit_defaults_to_half_the_pool() {
+        assert_eq!(Config { browser_pool_size: 5, ..Config::default() }.batch_page_limit(), 3);
+        assert_eq!(Config { browser_pool_size: 1, ..Config::default() }.batch_page_limit(), 1);
+        assert_eq!(Config { browser_pool_size: 4, batch_max_pages: 8, ..Config::default() }.batch_page_limit(), 4);
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn out_of_range_values_fail_validation() {
+        let config = Config { browser_pool_size: 0, ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("BROWSER_POOL_SIZE"));
+
+        let config = Config { api_port: 0, ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("API_PORT"));
+
+        let config = Config { request_timeout: 60, max_timeout: 30, ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("MAX_TIMEOUT"));
+
+        let config = Config { screenshot_backend: "s3".to_string(), ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("SCREENSHOT_S3_BUCKET"));
+
+        let config = Config { screenshot_backend: "gcs".to_string(), ..Config::default() };
+        assert!(config.validate().is_err());
+
+        let config = Config { screenshot_naming: "sequential".to_string(), ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("SCREENSHOT_NAMING"));
+
+        let config = Config { api_key_allowed_formats: vec!["pdf".to_string()], ..Config::default() };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("API_KEY_ALLOWED_FORMATS"));
+    }
+
+    #[test]
+    fn flag_values_are_parsed() {
+        assert!(parse_flag("BROWSER_LAZY_INIT", None, true).unwrap());
+        assert!(parse_flag("BROWSER_LAZY_INIT", Some("1".to_string()), false).unwrap());
+        assert!(parse_flag("BROWSER_LAZY_INIT", Some("TRUE".to_string()), false).unwrap());
+        assert!(!parse_flag("BROWSER_LAZY_INIT", Some("off".to_string()), true).unwrap());
+        assert!(parse_flag("BROWSER_LAZY_INIT", Some("maybe".to_string()), false).is_err());
+    }
+
+    #[test]
+    fn domain_list_is_split_and_normalized() {
+        assert_eq!(
+            parse_domain_list(" Example.com, *.docs.rs ,,news.ycombinator.com"),
+            vec!["example.com", "docs.rs", "news.ycombinator.com"]
+        );
+        assert!(parse_domain_list("").is_empty());
+    }
+
+    #[test]
+    fn device_presets_extend_and_override_builtins() {
+        let iphone = Config::default().device_preset("iPhone-13").unwrap();
+        assert!(iphone.user_agent.contains("iPhone OS 15_0"));
+        assert_eq!((iphone.width, iphone.height), (390, 844));
+        assert_eq!(iphone.device_scale_factor, 3.0);
+        assert!(iphone.mobile);
+        assert!(Config::default().device_preset("nokia-3310").is_none());
+
+        let presets = parse_device_presets(
+            "DEVICE_PRESETS",
+            r#"{"Kiosk": {"user_agent": "KioskBrowser/1.0", "width": 1080, "height": 1920},
+                "ipad": {"user_agent": "CustomPad/2.0", "width": 768, "height": 1024, "device_scale_factor": 2, "mobile": true}}"#,
+        ).unwrap();
+        let config = Config { device_presets: presets, ..Config::default() };
+        let kiosk = config.device_preset("kiosk").unwrap();
+        assert_eq!((kiosk.width, kiosk.height, kiosk.device_scale_factor, kiosk.mobile), (1080, 1920, 1.0, false));
+        assert_eq!(config.device_preset("ipad").unwrap().user_agent, "CustomPad/2.0");
+        assert!(config.device_preset("pixel-7").is_some());
+        assert!(config.validate().is_ok());
+
+        assert!(parse_device_presets("DEVICE_PRESETS", "[1, 2]").is_err());
+        let mut zero = config.clone();
+        zero.device_presets.get_mut("kiosk").unwrap().width = 0;
+        assert!(zero.validate().unwrap_err().to_string().contains("kiosk"));
+    }
 }
@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -20,6 +20,9 @@ pub enum AppError {
     #[error("Blocked URL: {0}")]
     BlockedUrl(String),
 
+    #[error("Response format not allowed for this API key: {0}")]
+    FormatNotAllowed(String),
+
     #[error("Request timeout after {0} seconds")]
     Timeout(u64),
 
@@ -38,15 +41,40 @@ pub enum AppError {
     #[error("Screenshot error: {0}")]
     ScreenshotError(String),
 
-    #[error("Rate limit exceeded for domain: {0}")]
-    RateLimitExceeded(String),
+    /// Our own per-domain limit, or the origin's 429/503. `retry_after` is
+    /// in seconds and is sent back as a `Retry-After` header.
+    #[error("Rate limit exceeded for domain: {domain}")]
+    RateLimitExceeded { domain: String, retry_after: Option<u64> },
 
     #[error("Circuit breaker open for domain: {0}")]
     CircuitBreakerOpen(String),
 
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+
     #[error("Too many domains requested: {0}")]
     TooManyDomains(usize),
 
+    #[error("Too many URLs in one request: {count} (limit is {limit})")]
+    TooManyUrls { count: usize, limit: usize },
+
+    #[error("Response body exceeded limit of {0} bytes")]
+    ContentTooLarge(u64),
+
+    #[error("Page JS heap exceeded limit of {0} MB")]
+    HeapLimitExceeded(u64),
+
+    #[error("Anti-bot challenge did not clear for: {0}")]
+    ChallengeNotCleared(String),
+
+    /// The origin answered a conditional navigation with 304. Only raised
+    /// when a cached copy was there to revalidate.
+    #[error("Origin reported no change for: {0}")]
+    NotModified(String),
+
+    #[error("Page looks like a not-found page: {0}")]
+    SoftNotFound(String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -57,6 +85,38 @@ pub enum AppError {
     IoError(#[from] std::io::Error),
 }
 
+impl AppError {
+    /// Stable machine-readable identifier for the error variant.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized => "unauthorized",
+            AppError::InvalidApiKey => "invalid_api_key",
+            AppError::InvalidUrl(_) => "invalid_url",
+            AppError::BlockedUrl(_) => "blocked_url",
+            AppError::FormatNotAllowed(_) => "format_not_allowed",
+            AppError::Timeout(_) => "timeout",
+            AppError::BrowserError(_) => "browser_error",
+            AppError::ScrapingError(_) => "scraping_error",
+            AppError::ExtractionError(_) => "extraction_error",
+            AppError::MarkdownError(_) => "markdown_error",
+            AppError::ScreenshotError(_) => "screenshot_error",
+            AppError::RateLimitExceeded { .. } => "rate_limited",
+            AppError::CircuitBreakerOpen(_) => "circuit_open",
+            AppError::InvalidOption(_) => "invalid_option",
+            AppError::TooManyDomains(_) => "too_many_domains",
+            AppError::TooManyUrls { .. } => "too_many_urls",
+            AppError::ContentTooLarge(_) => "content_too_large",
+            AppError::HeapLimitExceeded(_) => "heap_limit_exceeded",
+            AppError::ChallengeNotCleared(_) => "challenge_not_cleared",
+            AppError::NotModified(_) => "not_modified",
+            AppError::SoftNotFound(_) => "soft_404",
+            AppError::Internal(_) => "internal_error",
+            AppError::ConfigError(_) => "config_error",
+            AppError::IoError(_) => "io_error",
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match &self {
@@ -64,27 +124,112 @@ impl IntoResponse for AppError {
             AppError::InvalidApiKey => (StatusCode::UNAUTHORIZED, self.to_string()),
             AppError::InvalidUrl(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::BlockedUrl(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::FormatNotAllowed(_) => (StatusCode::FORBIDDEN, self.to_string()),
             AppError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
             AppError::BrowserError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::ScrapingError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::ExtractionError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::MarkdownError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::ScreenshotError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            AppError::RateLimitExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::RateLimitExceeded { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
             AppError::CircuitBreakerOpen(_) => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
+            AppError::InvalidOption(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::TooManyDomains(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::TooManyUrls { .. } => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::ContentTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
+            AppError::HeapLimitExceeded(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
+            AppError::ChallengeNotCleared(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
+            AppError::NotModified(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
+            AppError::SoftNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::ConfigError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::IoError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
+            "error_code": self.error_code(),
             "code": status.as_u16()
-        }));
+        });
 
-        (status, body).into_response()
+        let retry_after = match &self {
+            AppError::RateLimitExceeded { retry_after, .. } => *retry_after,
+            _ => None,
+        };
+        let Some(retry_after) = retry_after else {
+            return (status, Json(body)).into_response();
+        };
+        body["retry_after"] = retry_after.into();
+        (status, [(header::RETRY_AFTER, retry_after.to_string())], Json(body)).into_response()
     }
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_per_variant() {
+        let cases = [
+            (AppError::Unauthorized, "unauthorized"),
+            (AppError::InvalidApiKey, "invalid_api_key"),
+            (AppError::InvalidUrl("x".into()), "invalid_url"),
+            (AppError::BlockedUrl("x".into()), "blocked_url"),
+            (AppError::FormatNotAllowed("x".into()), "format_not_allowed"),
+            (AppError::Timeout(30), "timeout"),
+            (AppError::BrowserError("x".into()), "browser_error"),
+            (AppError::ScrapingError("x".into()), "scraping_error"),
+            (AppError::ExtractionError("x".into()), "extraction_error"),
+            (AppError::MarkdownError("x".into()), "markdown_error"),
+            (AppError::ScreenshotError("x".into()), "screenshot_error"),
+            (AppError::RateLimitExceeded { domain: "x".into(), retry_after: None }, "rate_limited"),
+            (AppError::CircuitBreakerOpen("x".into()), "circuit_open"),
+            (AppError::InvalidOption("x".into()), "invalid_option"),
+            (AppError::TooManyDomains(3), "too_many_domains"),
+            (AppError::TooManyUrls { count: 5, limit: 4 }, "too_many_urls"),
+            (AppError::ContentTooLarge(10), "content_too_large"),
+            (AppError::HeapLimitExceeded(256), "heap_limit_exceeded"),
+            (AppError::ChallengeNotCleared("x".into()), "challenge_not_cleared"),
+            (AppError::NotModified("x".into()), "not_modified"),
+            (AppError::SoftNotFound("x".into()), "soft_404"),
+            (AppError::Internal("x".into()), "internal_error"),
+            (AppError::ConfigError("x".into()), "config_error"),
+            (AppError::IoError(std::io::Error::other("x")), "io_error"),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(err.error_code(), code, "wrong code for {:?}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_body_includes_code_and_message() {
+        let response = AppError::BlockedUrl("Access to 10.0.0.1 is not allowed".into()).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(body["error_code"], "blocked_url");
+        assert_eq!(body["code"], 403);
+        assert_eq!(body["error"], "Blocked URL: Access to 10.0.0.1 is not allowed");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_carries_retry_after() {
+        let response = AppError::RateLimitExceeded { domain: "example.com".into(), retry_after: Some(30) }
+            .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["retry_after"], 30);
+
+        let response = AppError::RateLimitExceeded { domain: "example.com".into(), retry_after: None }
+            .into_response();
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+}
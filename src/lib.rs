@@ -0,0 +1,17 @@
+//! Page loading and conversion services behind the web loader HTTP API.
+//!
+//! The binary wires these into axum routes; they can also be used directly.
+//! Build [`CrawlerOptions`] with [`LoaderOptionsBuilder`], get a page from a
+//! [`BrowserPool`] and hand the captured HTML to a [`ConverterService`].
+
+pub mod config;
+pub mod error;
+pub mod models;
+pub mod services;
+
+pub use config::Config;
+pub use error::{AppError, Result};
+pub use models::{CrawlerOptions, LoadResponse, LoaderOptionsBuilder, ResponseFormat};
+pub use services::{
+    BrowserPool, CacheService, ConverterService, ProgressReporter, ScreenshotService, SecurityService,
+};
@@ -1,13 +1,9 @@
-mod config;
-mod error;
 mod middleware;
-mod models;
 mod routes;
-mod services;
 
 use axum::{
     middleware as axum_middleware,
-    routing::{get, post},
+    routing::{delete, get, post},
     Extension, Router,
 };
 use std::net::SocketAddr;
@@ -21,11 +17,18 @@ use tower_http::{
 use tracing::info;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+use web_loader_engine::{config, error, models, services};
+
 use config::Config;
-use middleware::{auth_middleware, AuthLayer};
-use routes::{batch_load_handler, health_handler, load_handler, openwebui_handler};
+use middleware::{auth_middleware, trace_context_middleware, AuthLayer};
+use routes::{
+    batch_load_handler, browser_status_handler, cache_clear_handler, cache_stats_handler, crawl_handler,
+    deep_health_handler, health_handler, in_flight_requests_handler, load_handler, load_query_handler,
+    openwebui_handler, recycle_browser_handler, validate_handler, ws_load_handler,
+};
 use services::{
-    BrowserPool, CacheService, ConverterService, ScreenshotService, SecurityService,
+    BrowserPool, CacheService, ConverterService, IdempotencyService, InFlightRegistry, ScreenshotService,
+    SecurityService,
 };
 
 #[derive(Clone)]
@@ -36,6 +39,8 @@ pub struct AppState {
     pub cache: Arc<CacheService>,
     pub security: Arc<SecurityService>,
     pub screenshot_service: Arc<ScreenshotService>,
+    pub idempotency: Arc<IdempotencyService>,
+    pub in_flight: Arc<InFlightRegistry>,
 }
 
 #[tokio::main]
@@ -64,14 +69,16 @@ async fn main() -> anyhow::Result<()> {
     info!("Initializing services...");
 
     let browser_pool = Arc::new(BrowserPool::new(config.clone()).await?);
-    info!("Browser pool initialized");
+    info!("Browser pool initialized (lazy: {})", config.browser_lazy_init);
 
     let converter = Arc::new(ConverterService::new(config.clone()));
     info!("Converter service initialized");
 
-    let cache = Arc::new(CacheService::new(config.cache_ttl));
+    let cache = Arc::new(CacheService::with_compression(config.cache_ttl, config.cache_compress));
     info!("Cache service initialized");
 
+    let idempotency = Arc::new(IdempotencyService::new(config.idempotency_ttl));
+
     let security = Arc::new(SecurityService::new(config.clone()));
     info!("Security service initialized");
 
@@ -86,15 +93,29 @@ async fn main() -> anyhow::Result<()> {
         cache,
         security,
         screenshot_service,
+        idempotency,
+        in_flight: Arc::new(InFlightRegistry::new()),
     };
 
-    let auth_layer = Arc::new(AuthLayer::new(config.api_key.clone()));
+    let auth_layer = Arc::new(AuthLayer::new(
+        config.api_key.clone(),
+        config.max_concurrent_per_key,
+    ));
 
     let app = Router::new()
         .route("/health", get(health_handler))
-        .route("/load", post(load_handler))
+        .route("/health/deep", get(deep_health_handler))
+        .route("/load", get(load_query_handler).post(load_handler))
         .route("/load/batch", post(batch_load_handler))
+        .route("/load/crawl", post(crawl_handler))
+        .route("/ws/load", get(ws_load_handler))
+        .route("/validate", post(validate_handler))
         .route("/", post(openwebui_handler))
+        .route("/cache/stats", get(cache_stats_handler))
+        .route("/cache", delete(cache_clear_handler))
+        .route("/admin/recycle-browser", post(recycle_browser_handler))
+        .route("/admin/requests", get(in_flight_requests_handler))
+        .route("/status/browser", get(browser_status_handler))
         .nest_service("/screenshots", ServeDir::new(&config.screenshot_dir))
         .with_state(state)
         .layer(axum_middleware::from_fn(auth_middleware))
@@ -105,6 +126,7 @@ async fn main() -> anyhow::Result<()> {
                 .allow_methods(Any)
                 .allow_headers(Any),
         )
+        .layer(axum_middleware::from_fn(trace_context_middleware))
         .layer(TraceLayer::new_for_http());
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.api_port));
@@ -5,18 +5,73 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use dashmap::DashMap;
 use serde_json::json;
 use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::warn;
 
+/// The API key's concurrency slot for one request, exposed as a request
+/// extension. The slot is returned when the last clone is dropped, so a
+/// handler whose work outlives its response (a WebSocket upgrade) keeps it
+/// by moving a clone into that work.
+#[derive(Clone)]
+pub struct KeySlot {
+    _permit: Arc<OwnedSemaphorePermit>,
+}
+
 #[derive(Clone)]
 pub struct AuthLayer {
     api_key: Option<String>,
+    /// Simultaneous requests allowed per API key; 0 means unlimited.
+    max_concurrent_per_key: usize,
+    in_flight: Arc<DashMap<String, Arc<Semaphore>>>,
 }
 
 impl AuthLayer {
-    pub fn new(api_key: Option<String>) -> Self {
-        Self { api_key }
+    pub fn new(api_key: Option<String>, max_concurrent_per_key: usize) -> Self {
+        Self {
+            api_key,
+            max_concurrent_per_key,
+            in_flight: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Runs the request while holding one of the key's concurrency slots,
+    /// rejecting it with 429 when they are all taken. The slot is released
+    /// once the handler has produced its response, unless the handler kept
+    /// the request's `KeySlot`.
+    async fn run_limited(&self, key: &str, mut request: Request, next: Next) -> Response {
+        if self.max_concurrent_per_key == 0 {
+            return next.run(request).await;
+        }
+
+        let semaphore = self.in_flight
+            .entry(key.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrent_per_key)))
+            .clone();
+
+        let Ok(permit) = semaphore.try_acquire_owned() else {
+            warn!("Concurrent request limit reached for API key");
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({
+                    "error": format!(
+                        "Too many concurrent requests (limit {})",
+                        self.max_concurrent_per_key
+                    ),
+                    "error_code": "concurrency_limited",
+                    "code": 429
+                })),
+            )
+                .into_response();
+        };
+
+        let slot = KeySlot { _permit: Arc::new(permit) };
+        request.extensions_mut().insert(slot.clone());
+        let response = next.run(request).await;
+        drop(slot);
+        response
     }
 }
 
@@ -25,7 +80,7 @@ pub async fn auth_middleware(
     request: Request,
     next: Next,
 ) -> Response {
-    if request.uri().path() == "/health" {
+    if matches!(request.uri().path(), "/health" | "/health/deep") {
         return next.run(request).await;
     }
 
@@ -45,13 +100,15 @@ pub async fn auth_middleware(
                 .unwrap_or(header_value);
 
             if provided_key == expected_key {
-                next.run(request).await
+                let key = provided_key.to_string();
+                auth.run_limited(&key, request, next).await
             } else {
                 warn!("Invalid API key provided");
                 (
                     StatusCode::UNAUTHORIZED,
                     Json(json!({
                         "error": "Invalid API key",
+                        "error_code": "invalid_api_key",
                         "code": 401
                     })),
                 )
@@ -64,6 +121,7 @@ pub async fn auth_middleware(
                 StatusCode::UNAUTHORIZED,
                 Json(json!({
                     "error": "Authorization header required",
+                    "error_code": "unauthorized",
                     "code": 401
                 })),
             )
@@ -71,3 +129,77 @@ pub async fn auth_middleware(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Extension, Router};
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    fn app(limit: usize, release: Arc<Notify>) -> Router {
+        let auth = Arc::new(AuthLayer::new(Some("secret".to_string()), limit));
+        Router::new()
+            .route(
+                "/load",
+                get(move || {
+                    let release = release.clone();
+                    async move {
+                        release.notified().await;
+                        "done"
+                    }
+                }),
+            )
+            .layer(middleware::from_fn(auth_middleware))
+            .layer(Extension(auth))
+    }
+
+    fn request() -> Request {
+        Request::builder()
+            .uri("/load")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_over_key_limit_are_rejected() {
+        let release = Arc::new(Notify::new());
+        let app = app(2, release.clone());
+
+        let in_flight: Vec<_> = (0..2)
+            .map(|_| tokio::spawn(app.clone().oneshot(request())))
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let rejected = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        release.notify_waiters();
+        for handle in in_flight {
+            assert_eq!(handle.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+
+        // Slots are returned once the earlier responses complete.
+        let again = tokio::spawn(app.clone().oneshot(request()));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        release.notify_waiters();
+        assert_eq!(again.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_zero_limit_is_unlimited() {
+        let release = Arc::new(Notify::new());
+        let app = app(0, release.clone());
+
+        let in_flight: Vec<_> = (0..5)
+            .map(|_| tokio::spawn(app.clone().oneshot(request())))
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        release.notify_waiters();
+
+        for handle in in_flight {
+            assert_eq!(handle.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+    }
+}
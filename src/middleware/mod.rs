@@ -1,3 +1,5 @@
 pub mod auth;
+pub mod trace;
 
-pub use auth::{auth_middleware, AuthLayer};
+pub use auth::{auth_middleware, AuthLayer, KeySlot};
+pub use trace::trace_context_middleware;
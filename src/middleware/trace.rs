@@ -0,0 +1,272 @@
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info_span, Instrument, Span};
+
+/// Error bodies are small JSON objects; anything larger, or of unknown
+/// length, is passed through untouched rather than buffered.
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// W3C trace context (`traceparent` / `tracestate`) for one request.
+/// `parent_id` is the caller's span, `span_id` the one this service reports
+/// back in its own `traceparent`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_id: Option<String>,
+    pub span_id: String,
+    pub flags: String,
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Continues the caller's trace when `traceparent` is valid, otherwise
+    /// starts a new one. `tracestate` is only kept alongside a valid parent,
+    /// as the spec requires.
+    pub fn from_headers(traceparent: Option<&str>, tracestate: Option<&str>) -> Self {
+        match traceparent.and_then(parse_traceparent) {
+            Some((trace_id, parent_id, flags)) => Self {
+                trace_id,
+                parent_id: Some(parent_id),
+                span_id: random_hex(16),
+                flags,
+                tracestate: tracestate
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string),
+            },
+            None => Self {
+                trace_id: random_hex(32),
+                parent_id: None,
+                span_id: random_hex(16),
+                flags: "01".to_string(),
+                tracestate: None,
+            },
+        }
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, self.flags)
+    }
+
+    fn span(&self) -> Span {
+        info_span!(
+            "request",
+            trace_id = %self.trace_id,
+            span_id = %self.span_id,
+            parent_id = self.parent_id.as_deref(),
+            tracestate = self.tracestate.as_deref(),
+        )
+    }
+}
+
+/// Parses `version-traceid-parentid-flags`. Versions other than `00` may
+/// append fields, so only the first four are read; `ff` and all-zero ids
+/// are invalid.
+fn parse_traceparent(raw: &str) -> Option<(String, String, String)> {
+    let raw = raw.trim();
+    let mut parts = raw.split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+
+    let is_hex = |s: &str, len: usize| {
+        s.len() == len && s.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    };
+    let all_zero = |s: &str| s.bytes().all(|b| b == b'0');
+
+    if !is_hex(version, 2) || version == "ff" || (version == "00" && parts.next().is_some()) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || all_zero(trace_id) || !is_hex(parent_id, 16) || all_zero(parent_id) {
+        return None;
+    }
+    if !is_hex(flags, 2) {
+        return None;
+    }
+
+    Some((trace_id.to_string(), parent_id.to_string(), flags.to_string()))
+}
+
+fn random_hex(len: usize) -> String {
+    let mut hex = uuid::Uuid::new_v4().simple().to_string();
+    hex.truncate(len);
+    hex
+}
+
+/// Runs the request inside a span carrying its trace context, echoes a
+/// `traceparent` for this hop and adds `trace_id` to JSON error bodies.
+pub async fn trace_context_middleware(mut request: Request, next: Next) -> Response {
+    let context = {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+        TraceContext::from_headers(header("traceparent"), header("tracestate"))
+    };
+    request.extensions_mut().insert(context.clone());
+
+    let span = context.span();
+    let mut response = next.run(request).instrument(span).await;
+
+    if let Ok(value) = HeaderValue::from_str(&context.traceparent()) {
+        response.headers_mut().insert("traceparent", value);
+    }
+    if let Some(ref state) = context.tracestate {
+        if let Ok(value) = HeaderValue::from_str(state) {
+            response.headers_mut().insert("tracestate", value);
+        }
+    }
+
+    if response.status().is_client_error() || response.status().is_server_error() {
+        response = with_trace_id(response, &context.trace_id).await;
+    }
+    response
+}
+
+async fn with_trace_id(response: Response, trace_id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let fits = response.body().size_hint().upper().is_some_and(|len| len <= MAX_ERROR_BODY_BYTES as u64);
+    if !fits {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert("trace_id".to_string(), trace_id.into());
+            serde_json::to_vec(&object).map(Body::from).unwrap_or_else(|_| Body::from(bytes))
+        }
+        _ => Body::from(bytes),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+    use axum::{middleware, routing::get, Router};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    /// Records the fields of every new span.
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Visit for SpanFields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.lock().push((field.name().to_string(), format!("{:?}", value)));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.lock().push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanFields {
+        fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, _: &tracing::span::Id, _: Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/fail", get(|| async { AppError::InvalidOption("bad".to_string()) }))
+            .route("/fail-large", get(|| async {
+                AppError::InvalidOption("x".repeat(MAX_ERROR_BODY_BYTES * 2))
+            }))
+            .layer(middleware::from_fn(trace_context_middleware))
+    }
+
+    fn request(uri: &str, traceparent: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(traceparent) = traceparent {
+            builder = builder.header("traceparent", traceparent).header("tracestate", "vendor=1");
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_incoming_traceparent_is_recorded_on_span() {
+        let fields = SpanFields::default();
+        let _guard = tracing_subscriber::registry().with(fields.clone()).set_default();
+
+        let response = app().oneshot(request("/ok", Some(PARENT))).await.unwrap();
+
+        let recorded = fields.0.lock().clone();
+        let field = |name: &str| recorded.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+        assert_eq!(field("trace_id"), Some("4bf92f3577b34da6a3ce929d0e0e4736"));
+        assert_eq!(field("parent_id"), Some("00f067aa0ba902b7"));
+        assert_eq!(field("tracestate"), Some("vendor=1"));
+
+        let echoed = response.headers()["traceparent"].to_str().unwrap();
+        assert!(echoed.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!echoed.contains("00f067aa0ba902b7"));
+    }
+
+    #[tokio::test]
+    async fn test_error_body_carries_trace_id() {
+        let response = app().oneshot(request("/fail", Some(PARENT))).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(json["error_code"], "invalid_option");
+
+        let response = app().oneshot(request("/fail", None)).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["trace_id"].as_str().unwrap().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_error_body_passes_through() {
+        let response = app().oneshot(request("/fail-large", Some(PARENT))).await.unwrap();
+        assert!(response.headers().contains_key("traceparent"));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error_code"], "invalid_option");
+        assert!(json.get("trace_id").is_none());
+        assert!(body.len() > MAX_ERROR_BODY_BYTES);
+    }
+
+    #[test]
+    fn test_invalid_traceparent_starts_new_trace() {
+        for raw in [
+            "garbage",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            let context = TraceContext::from_headers(Some(raw), Some("vendor=1"));
+            assert_eq!(context.parent_id, None, "{}", raw);
+            assert_eq!(context.tracestate, None);
+            assert_ne!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        }
+
+        let future = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra";
+        assert!(TraceContext::from_headers(Some(future), None).parent_id.is_some());
+    }
+}
@@ -0,0 +1,359 @@
+use super::{
+    CdpCommand, CookieInput, CrawlerOptions, LineEndings, MarkdownBullet, MarkdownEmphasis, PostBody, Priority, ResponseFormat, ScreenshotClip,
+    TargetMode, WaitMode,
+};
+
+/// Fluent construction of [`CrawlerOptions`] for code that embeds the crate
+/// instead of going through the HTTP headers. Every setter maps to the field
+/// of the same name; anything left unset keeps the same default as a request
+/// without the corresponding header. Values are not validated here.
+///
+/// ```
+/// use web_loader_engine::{LoaderOptionsBuilder, ResponseFormat};
+///
+/// let options = LoaderOptionsBuilder::new("https://example.com/article")
+///     .respond_with(ResponseFormat::Markdown)
+///     .target_selector("article")
+///     .wait_for_selectors(["#comments", ".footer"])
+///     .timeout(20)
+///     .with_links_summary(true)
+///     .build();
+///
+/// assert_eq!(options.url, "https://example.com/article");
+/// assert_eq!(options.target_selector.as_deref(), Some("article"));
+/// assert_eq!(options.wait_for_selectors.len(), 2);
+/// assert_eq!(options.timeout, Some(20));
+/// assert!(options.with_links_summary);
+/// assert!(!options.no_cache);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoaderOptionsBuilder {
+    options: CrawlerOptions,
+}
+
+impl LoaderOptionsBuilder {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            options: CrawlerOptions::new(url.into()),
+        }
+    }
+
+    pub fn build(self) -> CrawlerOptions {
+        self.options
+    }
+
+    pub fn respond_with(mut self, value: ResponseFormat) -> Self {
+        self.options.respond_with = value;
+        self
+    }
+
+    pub fn capture(mut self, value: ResponseFormat) -> Self {
+        self.options.capture = Some(value);
+        self
+    }
+
+    pub fn screenshot_clip(mut self, value: ScreenshotClip) -> Self {
+        self.options.screenshot_clip = Some(value);
+        self
+    }
+
+    pub fn screenshot_wait_assets(mut self, value: bool) -> Self {
+        self.options.screenshot_wait_assets = value;
+        self
+    }
+
+    pub fn wait_for_selector(mut self, value: impl Into<String>) -> Self {
+        self.options.wait_for_selector = Some(value.into());
+        self
+    }
+
+    pub fn wait_for_selectors<I, S>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.wait_for_selectors = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn wait_mode(mut self, value: WaitMode) -> Self {
+        self.options.wait_mode = value;
+        self
+    }
+
+    pub fn target_mode(mut self, value: TargetMode) -> Self {
+        self.options.target_mode = value;
+        self
+    }
+
+    pub fn target_selector(mut self, value: impl Into<String>) -> Self {
+        self.options.target_selector = Some(value.into());
+        self
+    }
+
+    pub fn remove_selector(mut self, value: impl Into<String>) -> Self {
+        self.options.remove_selector = Some(value.into());
+        self
+    }
+
+    pub fn timeout(mut self, value: u64) -> Self {
+        self.options.timeout = Some(value);
+        self
+    }
+
+    pub fn cookies(mut self, value: impl Into<String>) -> Self {
+        self.options.cookies = Some(value.into());
+        self
+    }
+
+    pub fn cookies_json(mut self, value: Vec<CookieInput>) -> Self {
+        self.options.cookies_json = value;
+        self
+    }
+
+    pub fn referer(mut self, value: impl Into<String>) -> Self {
+        self.options.referer = Some(value.into());
+        self
+    }
+
+    pub fn proxy_url(mut self, value: impl Into<String>) -> Self {
+        self.options.proxy_url = Some(value.into());
+        self
+    }
+
+    pub fn user_agent(mut self, value: impl Into<String>) -> Self {
+        self.options.user_agent = Some(value.into());
+        self
+    }
+
+    pub fn device(mut self, value: impl Into<String>) -> Self {
+        self.options.device = Some(value.into());
+        self
+    }
+
+    pub fn with_iframe(mut self, value: bool) -> Self {
+        self.options.with_iframe = value;
+        self
+    }
+
+    pub fn with_shadow_dom(mut self, value: bool) -> Self {
+        self.options.with_shadow_dom = value;
+        self
+    }
+
+    pub fn no_cache(mut self, value: bool) -> Self {
+        self.options.no_cache = value;
+        self
+    }
+
+    pub fn cache_tolerance(mut self, value: u64) -> Self {
+        self.options.cache_tolerance = Some(value);
+        self
+    }
+
+    pub fn cache_key(mut self, value: impl Into<String>) -> Self {
+        self.options.cache_key = Some(value.into());
+        self
+    }
+
+    pub fn with_images_summary(mut self, value: bool) -> Self {
+        self.options.with_images_summary = value;
+        self
+    }
+
+    pub fn with_links_summary(mut self, value: bool) -> Self {
+        self.options.with_links_summary = value;
+        self
+    }
+
+    pub fn with_text(mut self, value: bool) -> Self {
+        self.options.with_text = value;
+        self
+    }
+
+    pub fn with_generated_alt(mut self, value: bool) -> Self {
+        self.options.with_generated_alt = value;
+        self
+    }
+
+    pub fn keep_img_data_url(mut self, value: bool) -> Self {
+        self.options.keep_img_data_url = value;
+        self
+    }
+
+    pub fn max_data_url_bytes(mut self, value: usize) -> Self {
+        self.options.max_data_url_bytes = Some(value);
+        self
+    }
+
+    pub fn max_bytes(mut self, value: u64) -> Self {
+        self.options.max_bytes = Some(value);
+        self
+    }
+
+    pub fn max_heap_mb(mut self, value: u64) -> Self {
+        self.options.max_heap_mb = Some(value);
+        self
+    }
+
+    pub fn links_summary_limit(mut self, value: usize) -> Self {
+        self.options.links_summary_limit = Some(value);
+        self
+    }
+
+    pub fn images_summary_limit(mut self, value: usize) -> Self {
+        self.options.images_summary_limit = Some(value);
+        self
+    }
+
+    pub fn respect_meta_robots(mut self, value: bool) -> Self {
+        self.options.respect_meta_robots = value;
+        self
+    }
+
+    pub fn soft_404_strict(mut self, value: bool) -> Self {
+        self.options.soft_404_strict = value;
+        self
+    }
+
+    pub fn min_content_length(mut self, value: usize) -> Self {
+        self.options.min_content_length = Some(value);
+        self
+    }
+
+    pub fn empty_template(mut self, value: impl Into<String>) -> Self {
+        self.options.empty_template = Some(value.into());
+        self
+    }
+
+    pub fn raw_extract(mut self, value: bool) -> Self {
+        self.options.raw_extract = value;
+        self
+    }
+
+    pub fn wait_for_challenge(mut self, value: bool) -> Self {
+        self.options.wait_for_challenge = value;
+        self
+    }
+
+    pub fn no_settle(mut self, value: bool) -> Self {
+        self.options.no_settle = value;
+        self
+    }
+
+    pub fn settle_ms(mut self, value: u64) -> Self {
+        self.options.settle_ms = Some(value);
+        self
+    }
+
+    pub fn follow_meta_refresh(mut self, value: bool) -> Self {
+        self.options.follow_meta_refresh = value;
+        self
+    }
+
+    pub fn follow_js_redirect(mut self, value: bool) -> Self {
+        self.options.follow_js_redirect = value;
+        self
+    }
+
+    pub fn post_body(mut self, value: PostBody) -> Self {
+        self.options.post_body = Some(value);
+        self
+    }
+
+    pub fn stealth(mut self, value: bool) -> Self {
+        self.options.stealth = Some(value);
+        self
+    }
+
+    pub fn cdp_commands(mut self, value: Vec<CdpCommand>) -> Self {
+        self.options.cdp_commands = value;
+        self
+    }
+
+    pub fn priority(mut self, value: Priority) -> Self {
+        self.options.priority = value;
+        self
+    }
+
+    pub fn debug(mut self, value: bool) -> Self {
+        self.options.debug = value;
+        self
+    }
+
+    pub fn with_diff(mut self, value: bool) -> Self {
+        self.options.with_diff = value;
+        self
+    }
+
+    pub fn raw_body(mut self, value: bool) -> Self {
+        self.options.raw_body = value;
+        self
+    }
+
+    pub fn with_structured_data(mut self, value: bool) -> Self {
+        self.options.with_structured_data = value;
+        self
+    }
+
+    pub fn with_feeds(mut self, value: bool) -> Self {
+        self.options.with_feeds = value;
+        self
+    }
+
+    pub fn normalize_headings(mut self, value: bool) -> Self {
+        self.options.normalize_headings = value;
+        self
+    }
+
+    pub fn strip_hidden(mut self, value: bool) -> Self {
+        self.options.strip_hidden = value;
+        self
+    }
+
+    pub fn sanitize_html(mut self, value: bool) -> Self {
+        self.options.sanitize_html = value;
+        self
+    }
+
+    pub fn html_source(mut self, value: bool) -> Self {
+        self.options.html_source = value;
+        self
+    }
+
+    pub fn max_paragraphs(mut self, value: usize) -> Self {
+        self.options.max_paragraphs = Some(value);
+        self
+    }
+
+    pub fn deadline_ms(mut self, value: u64) -> Self {
+        self.options.deadline_ms = Some(value);
+        self
+    }
+
+    pub fn chunk_size(mut self, value: usize) -> Self {
+        self.options.chunk_size = Some(value);
+        self
+    }
+
+    pub fn line_endings(mut self, value: LineEndings) -> Self {
+        self.options.line_endings = value;
+        self
+    }
+
+    pub fn markdown_bullet(mut self, value: MarkdownBullet) -> Self {
+        self.options.markdown_bullet = value;
+        self
+    }
+
+    pub fn markdown_emphasis(mut self, value: MarkdownEmphasis) -> Self {
+        self.options.markdown_emphasis = value;
+        self
+    }
+}
+
+impl CrawlerOptions {
+    pub fn builder(url: impl Into<String>) -> LoaderOptionsBuilder {
+        LoaderOptionsBuilder::new(url)
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// What `x-device` expands into: the UA string plus the metrics Chrome
+/// DevTools uses to emulate the device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DevicePreset {
+    pub user_agent: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_scale_factor")]
+    pub device_scale_factor: f64,
+    #[serde(default)]
+    pub mobile: bool,
+}
+
+fn default_scale_factor() -> f64 { 1.0 }
+
+/// Built-in presets, matching the DevTools device list.
+const BUILTIN_DEVICES: &[(&str, &str, u32, u32, f64)] = &[
+    (
+        "iphone-13",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 15_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1",
+        390, 844, 3.0,
+    ),
+    (
+        "pixel-7",
+        "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        412, 915, 2.625,
+    ),
+    (
+        "ipad",
+        "Mozilla/5.0 (iPad; CPU OS 15_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1",
+        820, 1180, 2.0,
+    ),
+];
+
+impl DevicePreset {
+    /// The built-in preset called `name`, ignoring case.
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_DEVICES.iter()
+            .find(|(preset, ..)| preset.eq_ignore_ascii_case(name.trim()))
+            .map(|&(_, user_agent, width, height, device_scale_factor)| Self {
+                user_agent: user_agent.to_string(),
+                width,
+                height,
+                device_scale_factor,
+                mobile: true,
+            })
+    }
+}
@@ -1,7 +1,11 @@
+pub mod builder;
+pub mod device;
 pub mod request;
 pub mod response;
 pub mod snapshot;
 
+pub use builder::LoaderOptionsBuilder;
+pub use device::DevicePreset;
 pub use request::*;
 pub use response::*;
 pub use snapshot::*;
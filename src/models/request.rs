@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::OriginValidators;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseFormat {
+    #[default]
     Default,
     Markdown,
     Html,
@@ -11,12 +14,6 @@ pub enum ResponseFormat {
     Pageshot,
 }
 
-impl Default for ResponseFormat {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 impl ResponseFormat {
     pub fn from_header(value: &str) -> Self {
         match value.to_lowercase().as_str() {
@@ -28,27 +25,426 @@ impl ResponseFormat {
             _ => Self::Default,
         }
     }
+
+    pub fn is_screenshot(&self) -> bool {
+        matches!(self, Self::Screenshot | Self::Pageshot)
+    }
+
+    /// Name as written in `x-respond-with`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Text => "text",
+            Self::Screenshot => "screenshot",
+            Self::Pageshot => "pageshot",
+        }
+    }
+
+    /// Parse a comma-separated `x-respond-with` list into the content format
+    /// and an optional screenshot captured during the same navigation. A
+    /// list with only a screenshot format keeps the screenshot-only response.
+    pub fn from_header_list(value: &str) -> (Self, Option<Self>) {
+        let formats: Vec<Self> = value.split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(Self::from_header)
+            .collect();
+
+        let content = formats.iter().copied().find(|f| !f.is_screenshot());
+        let capture = formats.iter().copied().find(Self::is_screenshot);
+
+        match (content, capture) {
+            (Some(content), capture) => (content, capture),
+            (None, Some(capture)) => (capture, None),
+            (None, None) => (Self::Default, None),
+        }
+    }
+
+    /// Map a standard `Accept` header to a format, picking the supported
+    /// media type with the highest quality. Wildcards and unknown types
+    /// yield `None` so the caller can fall back to the default.
+    pub fn from_accept(value: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+
+        for entry in value.split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default().to_lowercase();
+            let quality = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let format = match media_type.as_str() {
+                "text/markdown" | "text/x-markdown" => Self::Markdown,
+                "text/html" => Self::Html,
+                "text/plain" => Self::Text,
+                "application/json" => Self::Default,
+                "image/png" => Self::Screenshot,
+                _ => continue,
+            };
+
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((format, quality));
+            }
+        }
+
+        best.map(|(format, _)| format)
+    }
 }
 
-#[derive(Debug, Clone, Default)]
+/// Serializes for the `x-debug` echo, so fields that can carry credentials
+/// are redacted on the way out.
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CrawlerOptions {
     pub url: String,
     pub respond_with: ResponseFormat,
+    /// Screenshot or pageshot taken alongside the content format.
+    pub capture: Option<ResponseFormat>,
+    pub screenshot_clip: Option<ScreenshotClip>,
+    /// Wait for web fonts and images to finish loading before capturing.
+    pub screenshot_wait_assets: bool,
     pub wait_for_selector: Option<String>,
+    pub wait_for_selectors: Vec<String>,
+    pub wait_mode: WaitMode,
     pub target_selector: Option<String>,
+    pub target_mode: TargetMode,
     pub remove_selector: Option<String>,
     pub timeout: Option<u64>,
+    #[serde(serialize_with = "redact_present")]
     pub cookies: Option<String>,
+    #[serde(serialize_with = "redact_cookie_values")]
+    pub cookies_json: Vec<CookieInput>,
+    /// Sent as `Referer` on the top-level navigation only.
+    pub referer: Option<String>,
+    #[serde(serialize_with = "redact_url_credentials")]
     pub proxy_url: Option<String>,
     pub user_agent: Option<String>,
+    /// Named device preset supplying the UA, viewport, scale factor and
+    /// mobile flag. An explicit `user_agent` still wins.
+    pub device: Option<String>,
     pub with_iframe: bool,
     pub with_shadow_dom: bool,
     pub no_cache: bool,
     pub cache_tolerance: Option<u64>,
+    /// Client-chosen cache key used in place of the one derived from the URL
+    /// and options, so equivalent URLs can share an entry.
+    pub cache_key: Option<String>,
     pub with_images_summary: bool,
     pub with_links_summary: bool,
+    pub with_text: bool,
     pub with_generated_alt: bool,
     pub keep_img_data_url: bool,
+    pub max_data_url_bytes: Option<usize>,
+    pub max_bytes: Option<u64>,
+    /// Abort once the page's JS heap grows past this many megabytes while
+    /// it settles.
+    pub max_heap_mb: Option<u64>,
+    pub links_summary_limit: Option<usize>,
+    pub images_summary_limit: Option<usize>,
+    pub respect_meta_robots: bool,
+    /// Fail with `soft_404` instead of returning a page flagged as one.
+    pub soft_404_strict: bool,
+    /// Fail with `extraction_error` when the extracted text is shorter than
+    /// this many characters.
+    pub min_content_length: Option<usize>,
+    /// Content to return instead when extraction leaves next to no text,
+    /// with `{url}` and `{title}` filled in. Overrides `EMPTY_CONTENT_TEMPLATE`.
+    pub empty_template: Option<String>,
+    /// Convert the targeted HTML as is, without cleanup or readability.
+    pub raw_extract: bool,
+    /// Wait for anti-bot interstitials to clear before capturing.
+    pub wait_for_challenge: bool,
+    /// Read the DOM as soon as the document fires `DOMContentLoaded`,
+    /// without waiting for the `load` event or the settle delay.
+    pub no_settle: bool,
+    /// Fixed settle pause in milliseconds instead of the timeout-scaled one.
+    pub settle_ms: Option<u64>,
+    /// Follow `<meta http-equiv="refresh">` redirects before capturing.
+    pub follow_meta_refresh: bool,
+    /// Wait for navigations scripts start while the page settles, such as a
+    /// delayed `window.location` assignment, and capture where they land.
+    pub follow_js_redirect: bool,
+    pub post_body: Option<PostBody>,
+    /// Per-request override of `STEALTH_MODE`.
+    pub stealth: Option<bool>,
+    pub cdp_commands: Vec<CdpCommand>,
+    pub priority: Priority,
+    /// Echo the effective options in the response.
+    pub debug: bool,
+    /// Load fresh and compare with the cached version.
+    pub with_diff: bool,
+    /// Answer `POST /load` with the bare content and its media type.
+    pub raw_body: bool,
+    /// Include structured page metadata such as OpenGraph tags.
+    pub with_structured_data: bool,
+    /// Include the RSS and Atom feeds the page links to.
+    pub with_feeds: bool,
+    /// Shift markdown headings so the shallowest becomes `#`.
+    pub normalize_headings: bool,
+    /// Remove elements that are not rendered before extraction.
+    pub strip_hidden: bool,
+    /// Return `html` content cleaned and reduced to an allowlist of safe
+    /// tags and attributes.
+    pub sanitize_html: bool,
+    /// Return `html` content from the document as the server sent it,
+    /// before scripts ran, instead of the rendered DOM.
+    pub html_source: bool,
+    /// Keep only the first N paragraphs of the markdown and plain text.
+    pub max_paragraphs: Option<usize>,
+    /// Upper bound in milliseconds for the whole load, conversion included.
+    pub deadline_ms: Option<u64>,
+    /// Also return the content split into pieces of at most this many
+    /// characters.
+    pub chunk_size: Option<usize>,
+    /// Line endings of the returned `content` and `text`.
+    pub line_endings: LineEndings,
+    /// Marker every markdown bullet list item is rewritten to use.
+    pub markdown_bullet: MarkdownBullet,
+    /// Delimiter every markdown emphasis and strong span is rewritten to use.
+    pub markdown_emphasis: MarkdownEmphasis,
+    /// Validators of an expired cache entry, sent so the origin can answer
+    /// 304 instead of the full document. Set by the cache, not by headers.
+    #[serde(skip)]
+    pub conditional: Option<OriginValidators>,
+}
+
+const REDACTED: &str = "[redacted]";
+
+fn redact_body<S: serde::Serializer>(_: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+fn redact_present<S: serde::Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|_| REDACTED).serialize(serializer)
+}
+
+fn redact_cookie_values<S: serde::Serializer>(cookies: &[CookieInput], serializer: S) -> Result<S::Ok, S::Error> {
+    let redacted: Vec<CookieInput> = cookies.iter()
+        .map(|c| CookieInput { value: REDACTED.to_string(), ..c.clone() })
+        .collect();
+    redacted.serialize(serializer)
+}
+
+/// `raw` with any user name and password swapped for a placeholder, or the
+/// placeholder alone when it does not parse as a URL.
+pub fn redacted_url(raw: &str) -> String {
+    match url::Url::parse(raw) {
+        Ok(mut url) => {
+            if !url.username().is_empty() || url.password().is_some() {
+                let _ = url.set_username("redacted");
+                let _ = url.set_password(None);
+            }
+            url.to_string()
+        }
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+fn redact_url_credentials<S: serde::Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_deref().map(redacted_url).serialize(serializer)
+}
+
+/// Cookie from `x-set-cookie-json`. Attributes left out fall back to
+/// browser defaults; the domain defaults to the requested page's host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieInput {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub secure: Option<bool>,
+    #[serde(default, alias = "http_only")]
+    pub http_only: Option<bool>,
+    #[serde(default, alias = "same_site")]
+    pub same_site: Option<String>,
+    /// Expiry as seconds since the Unix epoch.
+    #[serde(default)]
+    pub expires: Option<f64>,
+}
+
+/// Raw CDP command from `x-cdp-commands`, run on the page after navigation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CdpCommand {
+    pub method: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+/// Order in which queued requests get a browser page when the pool is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "high" => Self::High,
+            "low" => Self::Low,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// How `wait_for_selectors` is satisfied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitMode {
+    /// Proceed once any listed selector matches.
+    #[default]
+    Any,
+    /// Proceed once every listed selector matches.
+    All,
+}
+
+/// How a comma list in `target_selector` is applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetMode {
+    /// Fallback chain: the first selector that matches anything wins.
+    #[default]
+    First,
+    /// Every selector's matches, concatenated in document order.
+    All,
+}
+
+impl TargetMode {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "all" => Self::All,
+            _ => Self::First,
+        }
+    }
+}
+
+/// Line endings the returned content is normalized to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEndings {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "crlf" => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    /// Rewrites every `\r\n`, lone `\r` and `\n` in `text` to this ending.
+    pub fn apply(&self, text: &str) -> String {
+        let lf = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self {
+            Self::Lf => lf,
+            Self::Crlf => lf.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Bullet marker of unordered markdown list items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkdownBullet {
+    #[default]
+    Dash,
+    Asterisk,
+    Plus,
+}
+
+impl MarkdownBullet {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "*" | "asterisk" => Self::Asterisk,
+            "+" | "plus" => Self::Plus,
+            _ => Self::Dash,
+        }
+    }
+
+    pub fn marker(&self) -> char {
+        match self {
+            Self::Dash => '-',
+            Self::Asterisk => '*',
+            Self::Plus => '+',
+        }
+    }
+}
+
+/// Delimiter of markdown emphasis; strong text doubles it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkdownEmphasis {
+    #[default]
+    Asterisk,
+    Underscore,
+}
+
+impl MarkdownEmphasis {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "_" | "underscore" => Self::Underscore,
+            _ => Self::Asterisk,
+        }
+    }
+
+    pub fn marker(&self) -> char {
+        match self {
+            Self::Asterisk => '*',
+            Self::Underscore => '_',
+        }
+    }
+}
+
+impl WaitMode {
+    pub fn from_header(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "all" => Self::All,
+            _ => Self::Any,
+        }
+    }
+
+    pub fn is_satisfied(&self, matches: &[bool]) -> bool {
+        match self {
+            Self::Any => matches.iter().any(|m| *m),
+            Self::All => matches.iter().all(|m| *m),
+        }
+    }
+}
+
+/// Region captured by `x-screenshot-clip`, in CSS pixels from the top-left
+/// corner of the document.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ScreenshotClip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl std::fmt::Display for ScreenshotClip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Body sent when the navigation is issued as a POST instead of a GET.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PostBody {
+    #[serde(serialize_with = "redact_body")]
+    pub body: String,
+    pub content_type: String,
 }
 
 impl CrawlerOptions {
@@ -75,14 +471,156 @@ pub struct LoadRequestOptions {
     pub timeout: Option<u64>,
 }
 
+impl LoadRequestOptions {
+    /// These options with any field left unset taken from `fallback`.
+    pub fn or(&self, fallback: &LoadRequestOptions) -> LoadRequestOptions {
+        LoadRequestOptions {
+            wait_for_selector: self.wait_for_selector.clone().or_else(|| fallback.wait_for_selector.clone()),
+            target_selector: self.target_selector.clone().or_else(|| fallback.target_selector.clone()),
+            remove_selector: self.remove_selector.clone().or_else(|| fallback.remove_selector.clone()),
+            timeout: self.timeout.or(fallback.timeout),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchRefreshMode {
+    /// Load every URL before responding.
+    #[default]
+    Blocking,
+    /// Answer warm URLs from the cache immediately and refresh them in the
+    /// background; only cold URLs are loaded before responding.
+    Background,
+}
+
+/// One entry of `BatchLoadRequest::urls`: a bare URL, or a URL with options
+/// of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchUrl {
+    Plain(String),
+    WithOptions {
+        url: String,
+        #[serde(default)]
+        options: LoadRequestOptions,
+    },
+}
+
+impl BatchUrl {
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Plain(url) | Self::WithOptions { url, .. } => url,
+        }
+    }
+
+    /// Options for this URL: its own, with fields it leaves unset taken from
+    /// the batch-wide `shared` options.
+    pub fn options(&self, shared: &LoadRequestOptions) -> LoadRequestOptions {
+        match self {
+            Self::Plain(_) => shared.clone(),
+            Self::WithOptions { options, .. } => options.or(shared),
+        }
+    }
+}
+
+impl From<String> for BatchUrl {
+    fn from(url: String) -> Self {
+        Self::Plain(url)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchLoadRequest {
-    pub urls: Vec<String>,
+    pub urls: Vec<BatchUrl>,
+    #[serde(default)]
+    pub options: LoadRequestOptions,
+    #[serde(default)]
+    pub refresh: BatchRefreshMode,
+}
+
+/// Body of `POST /load/crawl`: the seed page plus the internal pages it links
+/// to, one level deep.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlRequest {
+    pub url: String,
+    /// Linked pages to load besides the seed. Defaults to 10 and is capped by
+    /// `MAX_BATCH_URLS`.
+    #[serde(default)]
+    pub max_pages: Option<usize>,
+    /// Only follow links whose path starts with this prefix.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
     #[serde(default)]
     pub options: LoadRequestOptions,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateRequest {
+    pub url: String,
+    /// The other URLs of a batch `url` would be sent with; the batch-size and
+    /// domain-count checks run over `url` plus these.
+    #[serde(default)]
+    pub urls: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenWebUIRequest {
     pub urls: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_header() {
+        assert_eq!(Priority::from_header(" High "), Priority::High);
+        assert_eq!(Priority::from_header("low"), Priority::Low);
+        assert_eq!(Priority::from_header("urgent"), Priority::Normal);
+    }
+
+    #[test]
+    fn test_accept_header_mapping() {
+        assert_eq!(ResponseFormat::from_accept("text/markdown"), Some(ResponseFormat::Markdown));
+        assert_eq!(ResponseFormat::from_accept("text/html, */*;q=0.8"), Some(ResponseFormat::Html));
+        assert_eq!(ResponseFormat::from_accept("text/html;q=0.5, text/plain"), Some(ResponseFormat::Text));
+        assert_eq!(ResponseFormat::from_accept("application/json"), Some(ResponseFormat::Default));
+        assert_eq!(ResponseFormat::from_accept("text/markdown;q=0, text/html;q=0.1"), Some(ResponseFormat::Html));
+        assert_eq!(ResponseFormat::from_accept("*/*"), None);
+        assert_eq!(ResponseFormat::from_accept("application/xml"), None);
+    }
+
+    #[test]
+    fn test_wait_mode() {
+        assert_eq!(WaitMode::from_header("ALL"), WaitMode::All);
+        assert_eq!(WaitMode::from_header("whatever"), WaitMode::Any);
+
+        assert!(WaitMode::Any.is_satisfied(&[false, true]));
+        assert!(!WaitMode::Any.is_satisfied(&[false, false]));
+        assert!(WaitMode::All.is_satisfied(&[true, true]));
+        assert!(!WaitMode::All.is_satisfied(&[true, false]));
+    }
+
+    #[test]
+    fn test_single_format_header() {
+        assert_eq!(ResponseFormat::from_header_list("html"), (ResponseFormat::Html, None));
+        assert_eq!(
+            ResponseFormat::from_header_list("pageshot"),
+            (ResponseFormat::Pageshot, None)
+        );
+    }
+
+    #[test]
+    fn test_format_list_splits_content_and_capture() {
+        assert_eq!(
+            ResponseFormat::from_header_list("markdown, pageshot"),
+            (ResponseFormat::Markdown, Some(ResponseFormat::Pageshot))
+        );
+        assert_eq!(
+            ResponseFormat::from_header_list("screenshot,text"),
+            (ResponseFormat::Text, Some(ResponseFormat::Screenshot))
+        );
+        assert_eq!(ResponseFormat::from_header_list(" , "), (ResponseFormat::Default, None));
+    }
+}
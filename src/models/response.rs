@@ -1,19 +1,118 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::{ExtractionStrategy, Priority, ResponseFormat};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoadResponse {
     pub url: String,
     pub title: Option<String>,
     pub content: String,
+    /// `content` split on paragraph and sentence boundaries, present with
+    /// `x-chunk-size`. The chunks concatenate back to `content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub published_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub images: Option<Vec<ImageInfo>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub links: Option<Vec<LinkInfo>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub screenshot_url: Option<String>,
+    /// Plain text of the extracted content, present with `x-with-text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Non-fatal issues hit while producing the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
     pub metadata: ResponseMetadata,
+    /// OpenGraph and Twitter card tags, present with `x-with-structured-data`
+    /// when the page has any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og: Option<OpenGraph>,
+    /// RSS and Atom feeds the page advertises, present with `x-with-feeds`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feeds: Vec<String>,
+    /// Every URL visited, from the requested one to the page the content
+    /// came from, when `x-follow-meta-refresh` or `x-follow-js-redirect`
+    /// followed at least one hop.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<String>,
+    /// The page looks like a teaser behind a paywall, so `content` is likely
+    /// incomplete.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paywalled: bool,
+    /// The origin answered 200 but the page reads like a "not found" page.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub soft_404: bool,
+    /// Effective options the request ran with, present with `x-debug`.
+    /// Cookies, proxy credentials and POST bodies are redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_options: Option<serde_json::Value>,
+    /// Validators the origin sent with the document, kept with the cache
+    /// entry to revalidate it once it expires. Never serialized.
+    #[serde(skip)]
+    pub origin_validators: Option<OriginValidators>,
+}
+
+/// `ETag` and `Last-Modified` of a loaded document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OriginValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl OriginValidators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// `If-None-Match` / `If-Modified-Since` headers that ask the origin to
+    /// answer 304 when the document has not changed.
+    pub fn conditional_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &self.etag {
+            headers.push(("If-None-Match", etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push(("If-Modified-Since", last_modified.clone()));
+        }
+        headers
+    }
+}
+
+/// Preview metadata from `og:*` and `twitter:*` meta tags. Image URLs are
+/// absolute.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenGraph {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub og_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_alt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter_card: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter_site: Option<String>,
+}
+
+impl OpenGraph {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +124,8 @@ pub struct ImageInfo {
     pub width: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub height: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub data_url_dropped: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,12 +133,37 @@ pub struct LinkInfo {
     pub href: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    /// Lowercased `rel` tokens of the anchor, e.g. `nofollow sponsored`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rel: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseMetadata {
     pub processing_time_ms: u64,
     pub cached: bool,
+    /// Comparison with the previously cached version, present with
+    /// `x-with-diff` when there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<ContentDiff>,
+    /// The URL was refused by the blocklist and `BLOCKED_URL_SOFT_FAIL`
+    /// turned the error into this empty response.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blocked: bool,
+    /// How a `default` format page was converted, chosen from its content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction: Option<ExtractionStrategy>,
+}
+
+/// Line-based comparison of `content` against an earlier load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentDiff {
+    pub changed: bool,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// The first changed lines, prefixed with `+ ` or `- `.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +179,43 @@ pub struct BatchLoadResult {
     pub response: Option<LoadResponse>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    pub from_cache: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_time_ms: Option<u64>,
+}
+
+impl BatchLoadResult {
+    /// Timing and cache status are copied from the response metadata.
+    pub fn success(url: String, response: LoadResponse) -> Self {
+        Self {
+            url,
+            from_cache: response.metadata.cached,
+            processing_time_ms: Some(response.metadata.processing_time_ms),
+            response: Some(response),
+            error: None,
+        }
+    }
+
+    pub fn failure(url: String, error: String) -> Self {
+        Self {
+            url,
+            response: None,
+            error: Some(error),
+            from_cache: false,
+            processing_time_ms: None,
+        }
+    }
+}
+
+/// Frame sent over the `/ws/load` socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LoadEvent {
+    Navigating,
+    Settling,
+    Extracting,
+    Done { result: Box<LoadResponse> },
+    Error { error: String, error_code: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +231,114 @@ pub struct BrowserPoolStatus {
     pub total: usize,
     pub healthy: bool,
     pub recreation_count: u64,
+    /// Requests currently waiting for a free page.
+    pub queued: usize,
+    pub acquire_wait: AcquireWaitStats,
+}
+
+/// Time requests spent waiting for a pool slot since startup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcquireWaitStats {
+    pub count: u64,
+    pub total_ms: u64,
+    pub buckets: Vec<WaitBucket>,
+}
+
+/// Waits of at most `le_ms` milliseconds that were longer than the previous
+/// bucket's bound. The last bucket has no bound.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaitBucket {
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycleBrowserResponse {
+    pub status: String,
+    pub recreation_count: u64,
+}
+
+/// Why the pool dropped its browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecreationReason {
+    /// The `about:blank` check before handing out a page failed.
+    HealthCheckFailed,
+    /// The CDP connection broke while opening or loading a page.
+    ConnectionError,
+    /// `POST /admin/recycle-browser`.
+    ManualRecycle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecreationEvent {
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub reason: RecreationReason,
+}
+
+/// Body of `GET /status/browser`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserStatusResponse {
+    pub healthy: bool,
+    pub recreation_count: u64,
+    /// Latest browser invalidations, oldest first.
+    pub recent_recreations: Vec<RecreationEvent>,
+    /// Load times of each `WARM_CONTEXT_DOMAINS` entry loaded so far.
+    pub warm_contexts: Vec<WarmContextLatency>,
+}
+
+/// How long loads of one warm domain took, in a context opened for the
+/// load (`cold`) and in one an earlier load already used (`warm`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarmContextLatency {
+    pub domain: String,
+    pub cold_loads: u64,
+    pub cold_avg_ms: Option<u64>,
+    pub warm_loads: u64,
+    pub warm_avg_ms: Option<u64>,
+}
+
+/// A request listed by `GET /admin/requests`.
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightRequest {
+    pub id: u64,
+    /// Requested URL with credentials redacted.
+    pub url: String,
+    pub format: ResponseFormat,
+    pub priority: Priority,
+    pub elapsed_ms: u64,
+    /// Whether the request holds a browser page or is still queued for one.
+    pub page_acquired: bool,
+}
+
+/// Body of `GET /admin/requests`.
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightRequestsResponse {
+    /// Requests being loaded right now, longest-running first.
+    pub requests: Vec<InFlightRequest>,
+    /// Requests since startup that ran while the list was full.
+    pub untracked: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateResponse {
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub normalized_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStatsResponse {
+    pub size: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheClearResponse {
+    pub removed: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,3 +353,37 @@ pub struct OpenWebUIMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 }
+
+/// A bare response for `url` with `content` and nothing else, for tests that
+/// only need something to store or return.
+#[cfg(test)]
+pub(crate) fn test_response(url: &str, content: &str) -> LoadResponse {
+    LoadResponse {
+        url: url.to_string(),
+        title: None,
+        content: content.to_string(),
+        chunks: None,
+        published_time: None,
+        byline: None,
+        excerpt: None,
+        images: None,
+        links: None,
+        screenshot_url: None,
+        text: None,
+        warnings: Vec::new(),
+        metadata: ResponseMetadata {
+            processing_time_ms: 0,
+            cached: false,
+            diff: None,
+            blocked: false,
+            extraction: None,
+        },
+        og: None,
+        feeds: Vec::new(),
+        redirect_chain: Vec::new(),
+        paywalled: false,
+        soft_404: false,
+        applied_options: None,
+        origin_validators: None,
+    }
+}
@@ -1,14 +1,39 @@
 use serde::{Deserialize, Serialize};
 
+use super::OpenGraph;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageSnapshot {
     pub url: String,
     pub html: String,
     pub title: Option<String>,
     pub published_time: Option<String>,
+    /// Author from meta tags or a byline element.
+    pub byline: Option<String>,
+    /// `<meta name="description">`, falling back to `og:description`.
+    pub description: Option<String>,
     pub images: Vec<ImageData>,
     pub links: Vec<LinkData>,
     pub has_pdf: bool,
+    pub robots: MetaRobots,
+    pub open_graph: OpenGraph,
+    /// Absolute URLs of the RSS and Atom feeds the page links to.
+    pub feeds: Vec<String>,
+    /// A paywall marker or a short teaser ending in a subscribe prompt.
+    pub paywalled: bool,
+    /// Short page reading like a not-found page despite a 200.
+    pub soft_404: bool,
+    /// How a `Default` format request converts the page, picked from the
+    /// whole page; `None` for other formats and with `x-raw-extract`.
+    pub extraction: Option<ExtractionStrategy>,
+    pub warnings: Vec<String>,
+}
+
+/// Directives from `<meta name="robots">`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetaRobots {
+    pub noindex: bool,
+    pub nofollow: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,12 +43,16 @@ pub struct ImageData {
     pub width: Option<u32>,
     pub height: Option<u32>,
     pub data_url: Option<String>,
+    /// The inline `data:` URL was over `x-max-data-url-bytes` and was dropped.
+    pub data_url_dropped: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LinkData {
     pub href: String,
     pub text: Option<String>,
+    /// Lowercased `rel` tokens of the anchor, e.g. `nofollow noopener`.
+    pub rel: Option<String>,
     pub is_internal: bool,
 }
 
@@ -34,8 +63,13 @@ pub struct ExtractedContent {
     pub content: String,
     pub text_content: String,
     pub published_time: Option<String>,
+    pub byline: Option<String>,
+    /// Short summary: the page description or else the first paragraph.
+    pub excerpt: Option<String>,
     pub images: Vec<ImageData>,
     pub links: Vec<LinkData>,
+    /// Set when readability failed and the raw HTML was used instead.
+    pub fallback_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -46,8 +80,36 @@ pub struct ComplexityMetrics {
     pub has_math: bool,
     pub is_non_english: bool,
     pub total_elements: usize,
+    /// Non-whitespace characters of visible body text.
+    pub text_chars: usize,
+    /// The part of `text_chars` inside links.
+    pub link_text_chars: usize,
+    /// The part of `text_chars` inside tables.
+    pub table_text_chars: usize,
+    /// Paragraphs long enough to be prose rather than a caption or teaser.
+    pub paragraph_count: usize,
 }
 
+/// How a `Default` format request was converted, picked from the page's
+/// [`ComplexityMetrics`] and reported in `metadata.extraction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionStrategy {
+    /// Readability markdown, as for `markdown`.
+    Article,
+    /// Readability markdown with tables kept as HTML.
+    Tables,
+    /// The whole page as markdown, as with `x-raw-extract`.
+    Listing,
+}
+
+/// Share of the text in tables from which a page counts as tabular.
+const TABLE_HEAVY_SHARE: f32 = 0.5;
+/// Share of the text in links from which a page counts as a listing.
+const LISTING_LINK_SHARE: f32 = 0.5;
+/// Listings have fewer prose paragraphs than this.
+const LISTING_MAX_PARAGRAPHS: usize = 3;
+
 impl ComplexityMetrics {
     pub fn calculate_score(&self) -> f32 {
         let mut score: f32 = 0.0;
@@ -80,4 +142,23 @@ impl ComplexityMetrics {
 
         score.min(1.0)
     }
+
+    /// Tables when most of the text is in tables, listing when most of it
+    /// is link text with little prose around it, article otherwise.
+    pub fn extraction_strategy(&self) -> ExtractionStrategy {
+        if self.text_chars == 0 {
+            return ExtractionStrategy::Article;
+        }
+        let share = |chars: usize| chars as f32 / self.text_chars as f32;
+
+        if self.table_count > 0 && share(self.table_text_chars) >= TABLE_HEAVY_SHARE {
+            ExtractionStrategy::Tables
+        } else if share(self.link_text_chars) >= LISTING_LINK_SHARE
+            && self.paragraph_count < LISTING_MAX_PARAGRAPHS
+        {
+            ExtractionStrategy::Listing
+        } else {
+            ExtractionStrategy::Article
+        }
+    }
 }
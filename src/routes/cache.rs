@@ -0,0 +1,48 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::models::{CacheClearResponse, CacheStatsResponse};
+use crate::services::CacheService;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct CacheClearQuery {
+    pub url: Option<String>,
+    /// An `x-cache-key` value; drops the entries stored under it.
+    pub key: Option<String>,
+}
+
+pub async fn cache_stats_handler(
+    State(state): State<AppState>,
+) -> Json<CacheStatsResponse> {
+    Json(state.cache.stats())
+}
+
+pub async fn cache_clear_handler(
+    State(state): State<AppState>,
+    Query(query): Query<CacheClearQuery>,
+) -> Json<CacheClearResponse> {
+    let removed = match (query.url, query.key) {
+        (Some(url), _) => {
+            let removed = state.cache.invalidate_url(&url);
+            info!("Invalidated {} cache entries for {}", removed, url);
+            removed
+        }
+        (None, Some(key)) => {
+            let removed = state.cache.invalidate_url(&CacheService::client_key(&key));
+            info!("Invalidated {} cache entries for cache key {}", removed, key);
+            removed
+        }
+        (None, None) => {
+            let removed = state.cache.clear();
+            info!("Cleared cache ({} entries)", removed);
+            removed
+        }
+    };
+
+    Json(CacheClearResponse { removed })
+}
@@ -0,0 +1,225 @@
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    Json,
+};
+use futures::future::join_all;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Instant;
+use tracing::info;
+use url::Url;
+
+use crate::error::AppError;
+use crate::models::{
+    BatchLoadResponse, BatchLoadResult, CrawlRequest, CrawlerOptions, LinkInfo, LoadResponse,
+};
+use crate::routes::loader::{fan_out_priority, load_with_options, parse_options};
+use crate::services::ProgressReporter;
+use crate::AppState;
+
+const DEFAULT_MAX_PAGES: usize = 10;
+
+/// `POST /load/crawl` - loads the seed URL, then up to `max_pages` of the
+/// same-host pages it links to. Results come back in the batch shape with the
+/// seed first; linked pages go through the same rate limits, circuit breaker
+/// and page permits as any other load.
+#[axum::debug_handler]
+pub async fn crawl_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<CrawlRequest>,
+) -> Result<Json<BatchLoadResponse>, AppError> {
+    let load = |state: AppState, options: CrawlerOptions| async move {
+        load_with_options(&state, &options, &ProgressReporter::disabled(), Instant::now()).await
+    };
+    crawl(state, &headers, request, load).await.map(Json)
+}
+
+async fn crawl<L, F>(
+    state: AppState,
+    headers: &HeaderMap,
+    request: CrawlRequest,
+    load: L,
+) -> Result<BatchLoadResponse, AppError>
+where
+    L: Fn(AppState, CrawlerOptions) -> F,
+    F: Future<Output = Result<LoadResponse, AppError>>,
+{
+    let start = Instant::now();
+    let max_pages = request.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    state.security.check_batch_size(max_pages)?;
+    info!("Processing crawl request for {} (up to {} linked pages)", request.url, max_pages);
+
+    let seed_options = parse_options(headers, &request.url, &request.options)?;
+    let seed_url = Url::parse(&seed_options.url)
+        .map_err(|e| AppError::InvalidUrl(format!("Invalid URL format: {}", e)))?;
+    let wants_links = seed_options.with_links_summary;
+    let mut link_options = seed_options.clone();
+    link_options.with_links_summary = true;
+    link_options.links_summary_limit = None;
+
+    let mut seed = load(state.clone(), link_options).await?;
+
+    let links = seed.links.take().unwrap_or_default();
+    let targets = crawl_targets(&seed_url, &links, request.path_prefix.as_deref(), max_pages);
+    if wants_links {
+        seed.links = Some(links);
+    }
+
+    let domains: Vec<String> = targets.iter()
+        .map(|u| u.host_str().unwrap_or("").to_string())
+        .collect();
+    state.security.check_domain_count(&domains)?;
+
+    let futures: Vec<_> = targets.into_iter().map(|target| {
+        let mut options = seed_options.clone();
+        options.url = target.to_string();
+        // Linked pages are fan-out work, like batch URLs.
+        options.priority = fan_out_priority(&state, headers, &options);
+        let url = options.url.clone();
+        let pending = load(state.clone(), options);
+        let state = state.clone();
+
+        async move {
+            let _slot = state.browser_pool.admit_batch().await;
+            match pending.await {
+                Ok(response) => BatchLoadResult::success(url, response),
+                Err(e) => BatchLoadResult::failure(url, e.to_string()),
+            }
+        }
+    }).collect();
+
+    let mut results = vec![BatchLoadResult::success(request.url.clone(), seed)];
+    results.extend(join_all(futures).await);
+
+    let total_time = start.elapsed().as_millis() as u64;
+    info!("Crawl of {} loaded {} pages in {}ms", request.url, results.len(), total_time);
+
+    Ok(BatchLoadResponse {
+        results,
+        total_processing_time_ms: total_time,
+    })
+}
+
+/// Same-host http(s) links from the seed in page order, without fragments or
+/// duplicates and never the seed itself.
+fn crawl_targets(
+    seed: &Url,
+    links: &[LinkInfo],
+    path_prefix: Option<&str>,
+    max_pages: usize,
+) -> Vec<Url> {
+    let mut seen = HashSet::new();
+    let mut seed_key = seed.clone();
+    seed_key.set_fragment(None);
+    seen.insert(seed_key.to_string());
+
+    links.iter()
+        .filter_map(|link| seed.join(&link.href).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter(|url| url.host_str() == seed.host_str() && url.port_or_known_default() == seed.port_or_known_default())
+        .filter(|url| path_prefix.is_none_or(|prefix| url.path().starts_with(prefix)))
+        .filter_map(|mut url| {
+            url.set_fragment(None);
+            seen.insert(url.to_string()).then_some(url)
+        })
+        .take(max_pages)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::routes::loader::process_url;
+    use crate::routes::loader::tests::{state_with, test_config};
+
+    fn link(href: &str) -> LinkInfo {
+        LinkInfo { href: href.to_string(), text: None, rel: None }
+    }
+
+    #[test]
+    fn test_crawl_targets_keep_internal_links_once() {
+        let seed = Url::parse("https://example.com/docs/").unwrap();
+        let links = vec![
+            link("/docs/a"),
+            link("https://example.com/docs/a#intro"),
+            link("https://other.com/docs/b"),
+            link("/blog/post"),
+            link("mailto:hi@example.com"),
+            link("#top"),
+            link("b"),
+        ];
+
+        let all: Vec<String> = crawl_targets(&seed, &links, None, 10).iter().map(Url::to_string).collect();
+        assert_eq!(all, vec![
+            "https://example.com/docs/a",
+            "https://example.com/blog/post",
+            "https://example.com/docs/b",
+        ]);
+
+        let docs = crawl_targets(&seed, &links, Some("/docs/"), 10);
+        assert_eq!(docs.len(), 2);
+        assert_eq!(crawl_targets(&seed, &links, None, 1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_rejects_max_pages_over_batch_limit() {
+        let state = state_with(Config { max_batch_urls: 5, ..test_config() }).await;
+        let request = CrawlRequest {
+            url: "https://example.com/".to_string(),
+            max_pages: Some(6),
+            path_prefix: None,
+            options: Default::default(),
+        };
+
+        let err = crawl_handler(State(state), HeaderMap::new(), Json(request)).await.unwrap_err();
+        assert_eq!(err.error_code(), "too_many_urls");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_crawl_loads_seed_and_linked_pages() {
+        use axum::{response::Html, routing::get, Router};
+
+        let app = Router::new()
+            .route("/", get(|| async {
+                Html(r#"<html><body><h1>Seed</h1><a href="/one">One</a><a href="/two">Two</a>
+                    <a href="https://example.org/">Elsewhere</a></body></html>"#)
+            }))
+            .route("/one", get(|| async { Html("<html><body><p>Page one</p></body></html>") }))
+            .route("/two", get(|| async { Html("<html><body><p>Page two</p></body></html>") }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let state = state_with(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..test_config()
+        })
+        .await;
+
+        let request = CrawlRequest {
+            url: format!("http://{}/", addr),
+            max_pages: None,
+            path_prefix: None,
+            options: Default::default(),
+        };
+        // The fixture is on localhost, which `validate_url` refuses, so load
+        // without the security checks.
+        let load = |state: AppState, options: CrawlerOptions| async move {
+            process_url(&state, &options, &ProgressReporter::disabled()).await
+        };
+        let crawl = crawl(state, &HeaderMap::new(), request, load).await.unwrap();
+
+        assert_eq!(crawl.results.len(), 3);
+        assert!(crawl.results[0].response.as_ref().unwrap().content.contains("Seed"));
+        assert!(crawl.results[0].response.as_ref().unwrap().links.is_none());
+        let bodies: Vec<&str> = crawl.results[1..].iter()
+            .map(|r| r.response.as_ref().unwrap().content.as_str())
+            .collect();
+        assert!(bodies.iter().any(|b| b.contains("Page one")));
+        assert!(bodies.iter().any(|b| b.contains("Page two")));
+    }
+}
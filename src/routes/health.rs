@@ -1,20 +1,64 @@
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use crate::models::{BrowserPoolStatus, HealthResponse};
 use crate::AppState;
 
-pub async fn health_handler(
-    State(state): State<AppState>,
-) -> Json<HealthResponse> {
-    let browser_status = BrowserPoolStatus {
+fn pool_status(state: &AppState) -> BrowserPoolStatus {
+    BrowserPoolStatus {
         available: state.browser_pool.available_slots(),
         total: state.browser_pool.total_slots(),
         healthy: state.browser_pool.is_healthy(),
         recreation_count: state.browser_pool.recreation_count(),
-    };
+        queued: state.browser_pool.queued_requests(),
+        acquire_wait: state.browser_pool.acquire_wait_stats(),
+    }
+}
 
+pub async fn health_handler(
+    State(state): State<AppState>,
+) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
-        browser_pool: browser_status,
+        browser_pool: pool_status(&state),
     })
 }
+
+/// Readiness probe: returns 503 unless the browser can open a page.
+pub async fn deep_health_handler(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let healthy = state.browser_pool.deep_health_check().await;
+    let (status, label) = if healthy {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+    };
+
+    (
+        status,
+        Json(HealthResponse {
+            status: label.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            browser_pool: pool_status(&state),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecreationReason;
+    use crate::routes::loader::tests::test_state;
+
+    #[tokio::test]
+    async fn test_deep_health_reports_invalidated_browser() {
+        let state = test_state().await;
+        state.browser_pool.invalidate_browser(RecreationReason::HealthCheckFailed).await;
+
+        let (status, Json(body)) = deep_health_handler(State(state)).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.status, "unhealthy");
+        assert!(!body.browser_pool.healthy);
+    }
+}
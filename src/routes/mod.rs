@@ -1,5 +1,15 @@
+pub mod admin;
+pub mod cache;
+pub mod crawl;
 pub mod health;
 pub mod loader;
+pub mod validate;
+pub mod ws;
 
-pub use health::health_handler;
-pub use loader::{load_handler, batch_load_handler, openwebui_handler};
+pub use admin::{browser_status_handler, in_flight_requests_handler, recycle_browser_handler};
+pub use cache::{cache_clear_handler, cache_stats_handler};
+pub use crawl::crawl_handler;
+pub use health::{deep_health_handler, health_handler};
+pub use loader::{load_handler, load_query_handler, batch_load_handler, openwebui_handler};
+pub use validate::validate_handler;
+pub use ws::ws_load_handler;
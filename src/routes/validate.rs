@@ -0,0 +1,131 @@
+use axum::{extract::State, Json};
+
+use crate::error::AppError;
+use crate::models::{ValidateRequest, ValidateResponse};
+use crate::services::SecurityService;
+use crate::AppState;
+
+/// Dry run of the URL checks `POST /load` performs, without loading anything.
+///
+/// Runs the per-URL scheme, host and circuit-breaker checks, plus the batch
+/// size and domain-count checks over `url` and any `urls` sent with it. Meta
+/// robots directives live in the page itself, so they are only applied by a
+/// real load.
+pub async fn validate_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ValidateRequest>,
+) -> Json<ValidateResponse> {
+    let outcome = state.security.validate_url(&request.url).and_then(|url| {
+        let domain = SecurityService::extract_domain(&url);
+        state.security.check_circuit_breaker(&domain)?;
+
+        if !request.urls.is_empty() {
+            state.security.check_batch_size(request.urls.len() + 1)?;
+            let domains: Vec<String> = std::iter::once(domain)
+                .chain(request.urls.iter()
+                    .filter_map(|u| url::Url::parse(u).ok())
+                    .map(|u| u.host_str().unwrap_or("").to_string()))
+                .collect();
+            state.security.check_domain_count(&domains)?;
+        }
+
+        Ok::<_, AppError>(url)
+    });
+
+    let response = match outcome {
+        Ok(url) => ValidateResponse {
+            allowed: true,
+            reason: None,
+            normalized_url: url.to_string(),
+        },
+        Err(e) => ValidateResponse {
+            allowed: false,
+            reason: Some(e.to_string()),
+            normalized_url: url::Url::parse(&request.url)
+                .map(|u| u.to_string())
+                .unwrap_or(request.url),
+        },
+    };
+
+    Json(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::loader::tests::{state_with, test_config, test_state};
+
+    async fn validate(state: &AppState, url: &str) -> ValidateResponse {
+        let request = ValidateRequest { url: url.to_string(), urls: Vec::new() };
+        validate_handler(State(state.clone()), Json(request)).await.0
+    }
+
+    #[tokio::test]
+    async fn test_allowed_url_is_normalized() {
+        let state = test_state().await;
+        let response = validate(&state, "HTTPS://Example.COM").await;
+
+        assert!(response.allowed);
+        assert!(response.reason.is_none());
+        assert_eq!(response.normalized_url, "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_rejection_reasons() {
+        let state = test_state().await;
+
+        let cases = [
+            ("not a url", "Invalid URL format"),
+            ("ftp://example.com/file", "Invalid scheme"),
+            ("http://localhost:8080/", "Access to localhost is not allowed"),
+            ("http://192.168.1.10/", "localhost/internal IPs"),
+            ("http://[fc00::1]/", "localhost/internal IPs"),
+            ("http://intranet/", "valid TLD"),
+        ];
+
+        for (url, expected) in cases {
+            let response = validate(&state, url).await;
+            assert!(!response.allowed, "{} should be rejected", url);
+            let reason = response.reason.unwrap();
+            assert!(reason.contains(expected), "{}: unexpected reason {:?}", url, reason);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_breaker_is_reported() {
+        let state = test_state().await;
+        for _ in 0..5 {
+            state.security.record_failure("flaky.example.com");
+        }
+
+        let response = validate(&state, "https://flaky.example.com/page").await;
+        assert!(!response.allowed);
+        assert!(response.reason.unwrap().contains("Circuit breaker open"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_checks_run_over_the_url_list() {
+        let mut config = test_config();
+        config.max_domains_per_page = 2;
+        config.max_batch_urls = 3;
+        let state = state_with(config).await;
+
+        let check = |urls: &[&str]| {
+            let request = ValidateRequest {
+                url: "https://a.example.com/".to_string(),
+                urls: urls.iter().map(|u| u.to_string()).collect(),
+            };
+            validate_handler(State(state.clone()), Json(request))
+        };
+
+        assert!(check(&["https://b.example.com/"]).await.0.allowed);
+
+        let response = check(&["https://b.example.com/", "https://c.example.com/"]).await.0;
+        assert!(!response.allowed);
+        assert!(response.reason.unwrap().contains("domains"));
+
+        let response = check(&["https://a.example.com/1", "https://a.example.com/2", "https://a.example.com/3"]).await.0;
+        assert!(!response.allowed);
+        assert!(response.reason.unwrap().contains("URLs"));
+    }
+}
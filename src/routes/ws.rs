@@ -0,0 +1,209 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    Extension,
+    http::HeaderMap,
+    response::Response,
+};
+use std::future::Future;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use crate::error::AppError;
+use crate::middleware::KeySlot;
+use crate::models::{LoadEvent, LoadRequest, LoadResponse};
+use crate::routes::loader::load_single;
+use crate::services::ProgressReporter;
+use crate::AppState;
+
+/// `GET /ws/load` - the client sends a `LoadRequest` as the first text frame and
+/// receives `navigating`, `settling` and `extracting` progress frames followed by
+/// a final `done` or `error` frame. Request headers from the upgrade apply as
+/// options, the same as on `POST /load`.
+pub async fn ws_load_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    slot: Option<Extension<KeySlot>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    // The load runs after the 101 response, so the key's concurrency slot
+    // has to live as long as the socket does.
+    ws.on_upgrade(move |socket| async move {
+        let _slot = slot;
+        handle_load_socket(socket, state, headers).await
+    })
+}
+
+async fn handle_load_socket(mut socket: WebSocket, state: AppState, headers: HeaderMap) {
+    let request = match socket.recv().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<LoadRequest>(&text),
+        other => {
+            debug!("WebSocket closed before a load request was received: {:?}", other);
+            return;
+        }
+    };
+
+    let request = match request {
+        Ok(request) => request,
+        Err(e) => {
+            let event = LoadEvent::Error {
+                error: format!("Invalid load request: {}", e),
+                error_code: "invalid_request".to_string(),
+            };
+            send_event(&mut socket, &event).await;
+            return;
+        }
+    };
+
+    info!("Processing WebSocket load request for URL: {}", request.url);
+
+    stream_load(&mut socket, |progress| async move {
+        load_single(&state, &headers, &request, &progress).await
+    })
+    .await;
+    let _ = socket.close().await;
+}
+
+/// Run `load`, forwarding the progress events it emits as frames while it
+/// runs, then send the final `done` or `error` frame.
+async fn stream_load<F, Fut>(socket: &mut WebSocket, load: F)
+where
+    F: FnOnce(ProgressReporter) -> Fut,
+    Fut: Future<Output = Result<LoadResponse, AppError>>,
+{
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let load = load(ProgressReporter::new(sender));
+
+    let forward = async {
+        while let Some(event) = events.recv().await {
+            send_event(socket, &event).await;
+        }
+    };
+
+    let (result, _) = tokio::join!(load, forward);
+
+    let event = match result {
+        Ok(response) => LoadEvent::Done { result: Box::new(response) },
+        Err(e) => LoadEvent::Error {
+            error: e.to_string(),
+            error_code: e.error_code().to_string(),
+        },
+    };
+    send_event(socket, &event).await;
+}
+
+async fn send_event(socket: &mut WebSocket, event: &LoadEvent) {
+    match serde_json::to_string(event) {
+        Ok(frame) => {
+            if let Err(e) = socket.send(Message::Text(frame)).await {
+                debug!("Failed to send WebSocket frame: {}", e);
+            }
+        }
+        Err(e) => debug!("Failed to serialize WebSocket frame: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::loader::tests::{response, test_state};
+    use axum::{routing::get, Router};
+    use std::time::Duration;
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite;
+
+    #[tokio::test]
+    async fn test_socket_streams_final_frame_for_one_url() {
+        let app = Router::new()
+            .route("/ws/load", get(ws_load_handler))
+            .with_state(test_state().await);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/load", addr))
+            .await
+            .unwrap();
+        client
+            .send(tungstenite::Message::Text(r#"{"url": "http://127.0.0.1/admin"}"#.into()))
+            .await
+            .unwrap();
+
+        let frame = client.next().await.unwrap().unwrap();
+        let event: serde_json::Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+
+        assert_eq!(event["event"], "error");
+        assert_eq!(event["error_code"], "blocked_url");
+    }
+
+    #[tokio::test]
+    async fn test_open_socket_holds_the_key_slot() {
+        use crate::middleware::{auth_middleware, AuthLayer};
+        use tungstenite::client::IntoClientRequest;
+
+        let auth = std::sync::Arc::new(AuthLayer::new(Some("secret".to_string()), 1));
+        let app = Router::new()
+            .route("/ws/load", get(ws_load_handler))
+            .with_state(test_state().await)
+            .layer(axum::middleware::from_fn(auth_middleware))
+            .layer(Extension(auth));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let connect = || {
+            let mut request = format!("ws://{}/ws/load", addr).into_client_request().unwrap();
+            request.headers_mut().insert("authorization", "Bearer secret".parse().unwrap());
+            tokio_tungstenite::connect_async(request)
+        };
+
+        // The first socket is upgraded but hasn't sent its request yet.
+        let (first, _) = connect().await.unwrap();
+        match connect().await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 429),
+            other => panic!("second socket was not rejected: {:?}", other.map(|_| ())),
+        }
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(connect().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_socket_streams_progress_frames_before_the_result() {
+        let app = Router::new().route(
+            "/ws/stub",
+            get(|ws: WebSocketUpgrade| async move {
+                ws.on_upgrade(|mut socket| async move {
+                    stream_load(&mut socket, |progress| async move {
+                        for event in [LoadEvent::Navigating, LoadEvent::Settling, LoadEvent::Extracting] {
+                            progress.emit(event);
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                        }
+                        Ok(response("https://example.com/", "Loaded"))
+                    })
+                    .await;
+                    let _ = socket.close().await;
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/stub", addr))
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(Ok(tungstenite::Message::Text(frame))) = client.next().await {
+            events.push(serde_json::from_str::<serde_json::Value>(&frame).unwrap());
+        }
+
+        let names: Vec<_> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+        assert_eq!(names, ["navigating", "settling", "extracting", "done"]);
+        assert_eq!(events[3]["result"]["content"], "Loaded");
+    }
+}
@@ -0,0 +1,66 @@
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+const ALT_TEXT_TIMEOUT_SECS: u64 = 10;
+
+/// Produces a description for an image that has no `alt` attribute.
+#[async_trait]
+pub trait AltTextGenerator: Send + Sync {
+    async fn generate(&self, image_url: &str, page_url: &str) -> Result<String>;
+}
+
+#[derive(Serialize)]
+struct AltTextRequest<'a> {
+    image_url: &'a str,
+    page_url: &'a str,
+}
+
+#[derive(Deserialize)]
+struct AltTextResponse {
+    alt: String,
+}
+
+/// Posts `{"image_url", "page_url"}` to `ALT_TEXT_SERVICE_URL` and expects
+/// `{"alt": "..."}` back.
+pub struct HttpAltTextGenerator {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl HttpAltTextGenerator {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let endpoint = config.alt_text_service_url.clone()?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(ALT_TEXT_TIMEOUT_SECS))
+            .build()
+            .ok()?;
+
+        info!("Alt text generation enabled via {}", endpoint);
+
+        Some(Self { client, endpoint })
+    }
+}
+
+#[async_trait]
+impl AltTextGenerator for HttpAltTextGenerator {
+    async fn generate(&self, image_url: &str, page_url: &str) -> Result<String> {
+        let response = self.client
+            .post(&self.endpoint)
+            .json(&AltTextRequest { image_url, page_url })
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::ExtractionError(format!("Alt text service failed: {}", e)))?;
+
+        let body: AltTextResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExtractionError(format!("Invalid alt text response: {}", e)))?;
+
+        Ok(body.alt.trim().to_string())
+    }
+}
@@ -1,54 +1,690 @@
 use crate::config::Config;
 use crate::error::{AppError, Result};
-use crate::models::CrawlerOptions;
+use crate::models::{
+    CdpCommand, CookieInput, CrawlerOptions, DevicePreset, LoadEvent, OriginValidators, PostBody, Priority,
+    RecreationEvent, RecreationReason, ResponseFormat, WaitMode, WarmContextLatency,
+};
+use crate::models::AcquireWaitStats;
+use crate::services::permits::{BatchAdmission, BatchSlot, PagePermit, PriorityPermits, WaitHistogram};
+use crate::services::progress::ProgressReporter;
+use crate::services::scraper::ScraperService;
+use crate::services::security::SecurityService;
 use crate::services::user_agent::UserAgentService;
 use chromiumoxide::browser::{Browser, BrowserConfig};
-use chromiumoxide::cdp::browser_protocol::network::{CookieParam, SetCookiesParams};
-use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use chromiumoxide::cdp::browser_protocol::network::{
+    CookieParam, CookieSameSite, EventDataReceived, EventResponseReceived, GetResponseBodyParams, RequestId,
+    ResourceType, SetCookiesParams, TimeSinceEpoch,
+};
+use base64::Engine;
+use chromiumoxide::cdp::browser_protocol::fetch;
+use chromiumoxide::cdp::browser_protocol::page::{
+    AddScriptToEvaluateOnNewDocumentParams, CaptureScreenshotFormat, EventDomContentEventFired,
+    EventFrameNavigated, NavigateParams, ReferrerPolicy, StopLoadingParams, Viewport,
+};
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetTouchEmulationEnabledParams,
+};
+use chromiumoxide::cdp::browser_protocol::performance;
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams};
 use chromiumoxide::page::ScreenshotParams;
 use chromiumoxide::Page;
 use futures::StreamExt;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, RwLock, Semaphore};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
 const MAX_RETRIES: u32 = 3;
+/// Page-level failures retried on the same browser before `get_page` gives
+/// up on it and recreates the whole browser.
+const MAX_PAGE_RETRIES: u32 = 1;
 const RETRY_DELAY_MS: u64 = 500;
 const HEALTH_CHECK_TIMEOUT_MS: u64 = 5000;
+const SELECTOR_POLL_INTERVAL_MS: u64 = 100;
+/// Pause after the ready state before reading the DOM, so late scripts can
+/// render: a thirtieth of the effective timeout (one second at the default
+/// 30s), clamped to the bounds below. `x-settle-ms` fixes it and
+/// `x-no-settle` skips it.
+const SETTLE_TIMEOUT_DIVISOR: u32 = 30;
+const MIN_SETTLE_MS: u64 = 250;
+const MAX_SETTLE_MS: u64 = 3000;
+/// Meta-refresh and JavaScript redirect hops followed before the load fails.
+const MAX_REDIRECT_HOPS: usize = 5;
+/// `/health/deep` reuses a result this fresh instead of opening another page.
+const DEEP_HEALTH_CACHE_SECS: u64 = 5;
+/// Browser invalidations remembered for `GET /status/browser`.
+const RECREATION_HISTORY: usize = 20;
+
+/// Init script for stealth mode. Runs before any page script and hides the
+/// most common automation tells left after `AutomationControlled` is disabled.
+const STEALTH_SCRIPT: &str = r#"
+Object.defineProperty(Navigator.prototype, 'webdriver', { get: () => undefined });
+Object.defineProperty(Navigator.prototype, 'languages', { get: () => ['en-US', 'en'] });
+Object.defineProperty(Navigator.prototype, 'plugins', {
+    get: () => [
+        { name: 'PDF Viewer', filename: 'internal-pdf-viewer', description: 'Portable Document Format' },
+        { name: 'Chrome PDF Viewer', filename: 'internal-pdf-viewer', description: 'Portable Document Format' },
+        { name: 'Chromium PDF Viewer', filename: 'internal-pdf-viewer', description: 'Portable Document Format' },
+    ],
+});
+if (!window.chrome) {
+    window.chrome = { runtime: {}, loadTimes: () => ({}), csi: () => ({}) };
+}
+"#;
+
+/// Removes body elements the browser does not render (computed `display:none`
+/// or `visibility:hidden`, or the `hidden` attribute) before the DOM is read.
+/// One pass over the tree: a hidden subtree is dropped from its top, and a
+/// `visibility:hidden` element with a descendant that sets it back to
+/// `visible` keeps that descendant, losing only its own text. Returns how
+/// many subtrees were dropped.
+const STRIP_HIDDEN_SCRIPT: &str = r#"
+(() => {
+    let removed = 0;
+    const visit = (el) => {
+        const style = window.getComputedStyle(el);
+        if (el.hidden || style.display === 'none') return false;
+        const selfVisible = style.visibility !== 'hidden';
+        const children = Array.from(el.children, (child) => [child, visit(child)]);
+        const shown = selfVisible || children.some(([, visible]) => visible);
+        if (shown) {
+            for (const [child, visible] of children) {
+                if (!visible) { child.remove(); removed++; }
+            }
+            if (!selfVisible) {
+                Array.from(el.childNodes)
+                    .filter((node) => node.nodeType === Node.TEXT_NODE)
+                    .forEach((node) => node.remove());
+            }
+        }
+        return shown;
+    };
+    if (document.body && !visit(document.body)) {
+        removed += document.body.children.length;
+        document.body.replaceChildren();
+    }
+    return removed;
+})()
+"#;
+
+/// CDP method prefixes `x-cdp-commands` may call. Limited to domains that only
+/// change how the current page renders or runs; anything that can navigate,
+/// run script, reach the network or touch the browser/filesystem is excluded.
+const CDP_PASSTHROUGH_ALLOWLIST: &[&str] = &[
+    "Emulation.",
+    "Animation.",
+    "Performance.",
+    "Overlay.",
+    "Page.setBypassCSP",
+];
+
+/// Browser window size, in pixels. Screenshot filenames in hash mode include it.
+pub const VIEWPORT: (u32, u32) = (1920, 1080);
+
+/// Resolves once web fonts are loaded and reports whether every image that
+/// ends up in the capture has finished loading or failed. `%FULL_PAGE%` is
+/// replaced with whether images below the fold count too.
+const ASSETS_READY_SCRIPT: &str = r#"
+(async () => {
+    if (document.fonts && document.fonts.ready) {
+        await document.fonts.ready;
+    }
+    const fullPage = %FULL_PAGE%;
+    const inCapture = (img) => {
+        if (fullPage) return true;
+        const rect = img.getBoundingClientRect();
+        return rect.bottom > 0 && rect.right > 0
+            && rect.top < window.innerHeight && rect.left < window.innerWidth;
+    };
+    return Array.from(document.images).filter(inCapture).every((img) => img.complete);
+})()
+"#;
+
+/// CSS size of the whole document and the device pixel ratio, as
+/// `[width, height, devicePixelRatio]`.
+const PAGE_SIZE_SCRIPT: &str = "[\
+    Math.max(document.documentElement.scrollWidth, document.body ? document.body.scrollWidth : 0),\
+    Math.max(document.documentElement.scrollHeight, document.body ? document.body.scrollHeight : 0),\
+    window.devicePixelRatio || 1]";
+
+/// Longest `x-wait-for-challenge` waits for an interstitial to clear, further
+/// bounded by the request timeout.
+const CHALLENGE_WAIT_SECS: u64 = 20;
+const CHALLENGE_POLL_INTERVAL_MS: u64 = 500;
+
+/// How often `x-screenshot-wait-assets` re-checks fonts and images.
+const ASSET_POLL_INTERVAL_MS: u64 = 100;
+
+/// How often the JS heap is sampled during the settle for `x-max-heap-mb`.
+const HEAP_POLL_INTERVAL_MS: u64 = 250;
+
+/// Locations tried when `CHROME_PATH` does not point at an executable.
+const CHROME_CANDIDATES: &[&str] = &[
+    "/usr/bin/chromium",
+    "/usr/bin/chromium-browser",
+    "/usr/bin/google-chrome",
+    "/usr/bin/google-chrome-stable",
+    "/snap/bin/chromium",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+];
+
+/// Running total of the main document's body size, checked against the
+/// per-request `x-max-bytes` limit. Sizes are of the decoded body: CDP's
+/// `dataLength` is counted after Chrome has undone any `Content-Encoding`,
+/// which is also what ends up in `page.content()`.
+struct BodySizeLimit {
+    limit: u64,
+    received: u64,
+}
+
+impl BodySizeLimit {
+    fn new(limit: u64) -> Self {
+        Self { limit, received: 0 }
+    }
+
+    fn exceeds_declared(&self, content_length: Option<u64>) -> bool {
+        content_length.is_some_and(|len| len > self.limit)
+    }
+
+    fn record(&mut self, len: u64) -> bool {
+        self.received = self.received.saturating_add(len);
+        self.received > self.limit
+    }
+
+    /// `Content-Length` as a decoded size. For gzip, br and other encodings
+    /// it counts the compressed bytes, so it says nothing reliable about the
+    /// decoded body and is ignored.
+    fn declared_length(headers: &serde_json::Value) -> Option<u64> {
+        let header = |wanted: &str| response_header(headers, wanted);
+
+        if header("content-encoding").is_some_and(|enc| !enc.is_empty() && !enc.eq_ignore_ascii_case("identity")) {
+            return None;
+        }
+        header("content-length").and_then(|value| value.parse().ok())
+    }
+}
+
+/// Case-insensitive lookup in a CDP response header object.
+fn response_header<'a>(headers: &'a serde_json::Value, wanted: &str) -> Option<&'a str> {
+    headers.as_object()?
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+        .and_then(|(_, value)| value.as_str())
+        .map(str::trim)
+}
+
+/// The parts of the main document response a load keeps.
+#[derive(Debug, Clone)]
+struct DocumentResponse {
+    status: i64,
+    validators: OriginValidators,
+    /// `Retry-After` in seconds, whatever the status.
+    retry_after: Option<u64>,
+    /// For reading the body back with `Network.getResponseBody`.
+    request_id: Option<RequestId>,
+    content_type: Option<String>,
+}
+
+impl DocumentResponse {
+    fn from_headers(status: i64, headers: &serde_json::Value) -> Self {
+        let header = |wanted: &str| {
+            response_header(headers, wanted)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            status,
+            validators: OriginValidators {
+                etag: header("etag"),
+                last_modified: header("last-modified"),
+            },
+            retry_after: header("retry-after")
+                .and_then(|value| parse_retry_after(&value, chrono::Utc::now())),
+            request_id: None,
+            content_type: header("content-type"),
+        }
+    }
+
+    /// How long the origin asked us to wait, when it refused the load with
+    /// 429 or 503 and said.
+    fn refused_for(&self) -> Option<u64> {
+        matches!(self.status, 429 | 503).then_some(self.retry_after).flatten()
+    }
+}
+
+/// `Retry-After` as seconds from `now`. The value is either a number of
+/// seconds or an HTTP date, which counts as 0 once it has passed.
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<u64> {
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - now).num_seconds().max(0) as u64)
+}
+
+/// Background watcher that records the main document response during
+/// navigation. Aborted when dropped.
+struct DocumentWatcher {
+    response: Arc<parking_lot::Mutex<Option<DocumentResponse>>>,
+    task: JoinHandle<()>,
+}
+
+impl DocumentWatcher {
+    fn response(&self) -> Option<DocumentResponse> {
+        self.response.lock().clone()
+    }
+}
+
+impl Drop for DocumentWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Background watcher that records the URL of every main-frame navigation
+/// after it starts, such as a script assigning `window.location`. Aborted
+/// when dropped.
+struct FrameNavigationWatcher {
+    urls: Arc<parking_lot::Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl FrameNavigationWatcher {
+    fn urls(&self) -> Vec<String> {
+        self.urls.lock().clone()
+    }
+}
+
+impl Drop for FrameNavigationWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Background watcher that stops the page load once the main document body
+/// grows past the limit. Aborted when dropped.
+struct BodySizeWatcher {
+    exceeded: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl BodySizeWatcher {
+    fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for BodySizeWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Rewrites the page's first document request. `Page.navigate` can only send
+/// a plain GET, so that request is paused through the Fetch domain and
+/// continued as a POST, or with conditional headers added. Later document
+/// requests (redirect hops, iframes) are continued untouched.
+struct DocumentInterceptor {
+    task: JoinHandle<()>,
+}
+
+/// How `DocumentInterceptor` changes the first document request.
+#[derive(Clone)]
+enum DocumentRewrite {
+    Post(PostBody),
+    Conditional(OriginValidators),
+}
+
+impl DocumentInterceptor {
+    async fn start(page: &Page, rewrite: DocumentRewrite) -> Result<Self> {
+        let mut paused = page
+            .event_listener::<fetch::EventRequestPaused>()
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to listen for requests: {}", e)))?;
+
+        let pattern = fetch::RequestPattern {
+            url_pattern: Some("*".to_string()),
+            resource_type: Some(ResourceType::Document),
+            request_stage: Some(fetch::RequestStage::Request),
+        };
+        page.execute(fetch::EnableParams {
+            patterns: Some(vec![pattern]),
+            handle_auth_requests: None,
+        })
+        .await
+        .map_err(|e| AppError::BrowserError(format!("Failed to enable request interception: {}", e)))?;
+
+        let page = page.clone();
+        let task = tokio::spawn(async move {
+            let mut rewritten = false;
+            while let Some(event) = paused.next().await {
+                let params = if rewritten {
+                    fetch::ContinueRequestParams::new(event.request_id.clone())
+                } else {
+                    rewritten = true;
+                    match &rewrite {
+                        DocumentRewrite::Post(post) => Self::post_params(&event, post),
+                        DocumentRewrite::Conditional(validators) => Self::conditional_params(&event, validators),
+                    }
+                };
+                if let Err(e) = page.execute(params).await {
+                    debug!("Failed to continue intercepted request: {}", e);
+                }
+            }
+        });
+
+        Ok(Self { task })
+    }
+
+    fn post_params(event: &fetch::EventRequestPaused, post: &PostBody) -> fetch::ContinueRequestParams {
+        let mut headers = Self::headers_without(event, &["content-type", "content-length"]);
+        headers.push(fetch::HeaderEntry {
+            name: "Content-Type".to_string(),
+            value: post.content_type.clone(),
+        });
+
+        let mut params = fetch::ContinueRequestParams::new(event.request_id.clone());
+        params.method = Some("POST".to_string());
+        params.post_data = Some(base64::engine::general_purpose::STANDARD.encode(&post.body).into());
+        params.headers = Some(headers);
+        params
+    }
+
+    fn conditional_params(
+        event: &fetch::EventRequestPaused,
+        validators: &OriginValidators,
+    ) -> fetch::ContinueRequestParams {
+        let mut headers = Self::headers_without(event, &["if-none-match", "if-modified-since"]);
+        headers.extend(validators.conditional_headers().into_iter().map(|(name, value)| {
+            fetch::HeaderEntry { name: name.to_string(), value }
+        }));
+
+        let mut params = fetch::ContinueRequestParams::new(event.request_id.clone());
+        params.headers = Some(headers);
+        params
+    }
+
+    /// The paused request's headers, minus those named in `excluded`.
+    fn headers_without(event: &fetch::EventRequestPaused, excluded: &[&str]) -> Vec<fetch::HeaderEntry> {
+        event.request.headers.inner()
+            .as_object()
+            .map(|headers| {
+                headers.iter()
+                    .filter(|(name, _)| !excluded.iter().any(|e| name.eq_ignore_ascii_case(e)))
+                    .filter_map(|(name, value)| {
+                        Some(fetch::HeaderEntry {
+                            name: name.clone(),
+                            value: value.as_str()?.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn finish(self, page: &Page) {
+        if let Err(e) = page.execute(fetch::DisableParams::default()).await {
+            debug!("Failed to disable request interception: {}", e);
+        }
+    }
+}
+
+impl Drop for DocumentInterceptor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A CDP call with a method name known only at runtime.
+struct RawCdpCommand<'a>(&'a CdpCommand);
+
+impl serde::Serialize for RawCdpCommand<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match &self.0.params {
+            serde_json::Value::Null => serde_json::Map::new().serialize(serializer),
+            params => params.serialize(serializer),
+        }
+    }
+}
+
+impl chromiumoxide::Method for RawCdpCommand<'_> {
+    fn identifier(&self) -> chromiumoxide::types::MethodId {
+        Cow::Owned(self.0.method.clone())
+    }
+}
+
+impl chromiumoxide::Command for RawCdpCommand<'_> {
+    type Response = serde_json::Value;
+}
+
+/// Result of `take_screenshot`.
+pub struct Screenshot {
+    pub data: Vec<u8>,
+    /// Set when a full-page capture was cut short at `SCREENSHOT_MAX_HEIGHT`.
+    pub warning: Option<String>,
+}
+
+/// Result of `navigate_following_refresh`.
+pub struct Navigation {
+    pub html: String,
+    /// Every URL visited in order, when at least one meta refresh or
+    /// JavaScript redirect was taken.
+    pub redirect_chain: Vec<String>,
+    /// Validators of the document the HTML came from.
+    pub validators: OriginValidators,
+    /// The document as the server sent it, for `x-html-source`. `None` when
+    /// not asked for or when it could not be read.
+    pub source: Option<DocumentSource>,
+    /// When the request timeout runs out, counted from the first
+    /// navigation. Work after the load, like waiting for screenshot assets,
+    /// gets only what is left.
+    pub deadline: tokio::time::Instant,
+}
+
+/// The main document's body before any script touched it, still in the
+/// charset it was served in.
+pub struct DocumentSource {
+    pub body: Vec<u8>,
+    /// The `Content-Type` that declares the body's charset, if any.
+    pub content_type: Option<String>,
+}
+
+impl DocumentSource {
+    /// From a `Network.getResponseBody` result. Chrome only hands text back
+    /// once it has decoded it, so that is UTF-8 whatever the server declared;
+    /// a base64 body is the bytes as served.
+    fn from_response_body(body: &str, base64_encoded: bool, content_type: Option<&str>) -> Result<Self> {
+        if !base64_encoded {
+            return Ok(Self {
+                body: body.as_bytes().to_vec(),
+                content_type: Some("text/html; charset=utf-8".to_string()),
+            });
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(body)
+            .map_err(|e| AppError::BrowserError(format!("Failed to decode document source: {}", e)))?;
+        Ok(Self { body: bytes, content_type: content_type.map(str::to_string) })
+    }
+}
+
+/// How much of the browser a failure took down, as judged from its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureScope {
+    /// Only the tab, its CDP session or one command on it failed; a new page
+    /// on the same browser may well work.
+    Page,
+    /// The connection to the browser itself is gone.
+    Browser,
+}
+
+/// A page handed out by the pool. When the page was opened in a throwaway
+/// browser context (`BROWSER_CONTEXT_ISOLATION`), the context is disposed on
+/// drop, which also closes the page.
+pub struct PooledPage {
+    page: Page,
+    disposable_context: Option<BrowserContextId>,
+    /// Set when the page was opened in a warm context.
+    warm: Option<WarmUse>,
+    browser: Arc<RwLock<Option<Browser>>>,
+    /// Pool slot held for as long as the page is checked out.
+    permit: Option<PagePermit>,
+}
+
+/// The warm context a page was opened in, and whether an earlier page had
+/// already used it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WarmUse {
+    domain: String,
+    reused: bool,
+}
+
+/// Load times of one warm domain, split by whether the context was new.
+#[derive(Debug, Default)]
+struct LoadTotals {
+    cold_loads: u64,
+    cold_ms: u64,
+    warm_loads: u64,
+    warm_ms: u64,
+}
+
+impl Deref for PooledPage {
+    type Target = Page;
+
+    fn deref(&self) -> &Page {
+        &self.page
+    }
+}
+
+impl Drop for PooledPage {
+    fn drop(&mut self) {
+        let Some(context_id) = self.disposable_context.take() else {
+            return;
+        };
+        let browser = self.browser.clone();
+        tokio::spawn(async move {
+            if let Some(browser) = browser.read().await.as_ref() {
+                if let Err(e) = browser.dispose_browser_context(context_id).await {
+                    debug!("Failed to dispose browser context: {}", e);
+                }
+            }
+        });
+    }
+}
 
 pub struct BrowserPool {
     browser: Arc<RwLock<Option<Browser>>>,
-    semaphore: Arc<Semaphore>,
+    permits: Arc<PriorityPermits>,
+    batch_admission: BatchAdmission,
+    acquire_waits: WaitHistogram,
     config: Config,
     is_healthy: Arc<AtomicBool>,
     recreation_count: Arc<AtomicU64>,
+    recreations: parking_lot::Mutex<VecDeque<RecreationEvent>>,
     recreation_lock: Arc<Mutex<()>>,
+    recycle_lock: Mutex<()>,
     user_agent: Arc<UserAgentService>,
+    security: SecurityService,
+    warm_contexts: Mutex<HashMap<String, BrowserContextId>>,
+    warm_latency: parking_lot::Mutex<HashMap<String, LoadTotals>>,
+    last_deep_check: Mutex<Option<(std::time::Instant, bool)>>,
 }
 
 impl BrowserPool {
-    pub async fn new(config: Config) -> Result<Self> {
+    pub async fn new(mut config: Config) -> Result<Self> {
+        match Self::resolve_chrome_path(&config.chrome_path, CHROME_CANDIDATES) {
+            Ok(path) => config.chrome_path = path,
+            // A lazy pool reports the same error on first use instead, so the
+            // API can still come up and serve /health.
+            Err(e) if config.browser_lazy_init => warn!("{}", e),
+            Err(e) => return Err(e),
+        }
+
         let user_agent = Arc::new(UserAgentService::new(&config));
+        let security = SecurityService::new(config.clone());
         let pool = Self {
             browser: Arc::new(RwLock::new(None)),
-            semaphore: Arc::new(Semaphore::new(config.browser_pool_size)),
+            permits: PriorityPermits::new(config.browser_pool_size),
+            batch_admission: BatchAdmission::new(config.batch_page_limit()),
+            acquire_waits: WaitHistogram::default(),
             config,
             is_healthy: Arc::new(AtomicBool::new(false)),
             recreation_count: Arc::new(AtomicU64::new(0)),
+            recreations: parking_lot::Mutex::new(VecDeque::with_capacity(RECREATION_HISTORY)),
             recreation_lock: Arc::new(Mutex::new(())),
+            recycle_lock: Mutex::new(()),
             user_agent,
+            security,
+            warm_contexts: Mutex::new(HashMap::new()),
+            warm_latency: parking_lot::Mutex::new(HashMap::new()),
+            last_deep_check: Mutex::new(None),
         };
 
-        pool.ensure_browser().await?;
+        if pool.config.browser_lazy_init {
+            info!("Lazy browser init enabled, browser will launch on first request");
+        } else {
+            pool.ensure_browser().await?;
+        }
 
         Ok(pool)
     }
 
+    /// Returns `configured` when it names an executable, either as a path or
+    /// as a bare name on `PATH`, and otherwise the first executable candidate.
+    fn resolve_chrome_path(configured: &str, candidates: &[&str]) -> Result<String> {
+        let configured_path = std::path::Path::new(configured);
+        if Self::is_executable(configured_path) {
+            return Ok(configured.to_string());
+        }
+
+        if configured_path.components().count() == 1 {
+            if let Some(found) = std::env::var_os("PATH").and_then(|paths| {
+                std::env::split_paths(&paths)
+                    .map(|dir| dir.join(configured))
+                    .find(|p| Self::is_executable(p))
+            }) {
+                return Ok(found.to_string_lossy().into_owned());
+            }
+        }
+
+        if let Some(found) = candidates
+            .iter()
+            .find(|c| Self::is_executable(std::path::Path::new(c)))
+        {
+            warn!("CHROME_PATH '{}' is not executable, using detected {}", configured, found);
+            return Ok(found.to_string());
+        }
+
+        Err(AppError::ConfigError(format!(
+            "Chromium executable not found at '{}'. Set CHROME_PATH to the path of a Chromium or Chrome binary",
+            configured
+        )))
+    }
+
+    fn is_executable(path: &std::path::Path) -> bool {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return false;
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        {
+            metadata.is_file()
+        }
+    }
+
     async fn create_browser(config: &Config) -> Result<Browser> {
+        let chrome_path = Self::resolve_chrome_path(&config.chrome_path, CHROME_CANDIDATES)?;
         let mut builder = BrowserConfig::builder()
-            .chrome_executable(&config.chrome_path)
+            .chrome_executable(&chrome_path)
             .no_sandbox()
             .arg("--disable-gpu")
             .arg("--disable-dev-shm-usage")
@@ -62,13 +698,10 @@ impl BrowserPool {
             .arg("--mute-audio")
             .arg("--no-first-run")
             .arg("--safebrowsing-disable-auto-update")
-            .arg("--ignore-certificate-errors")
-            .arg("--ignore-ssl-errors")
-            .arg("--ignore-certificate-errors-spki-list")
             .arg("--disable-features=IsolateOrigins,site-per-process")
             .arg("--disable-blink-features=AutomationControlled")
-            .arg("--disable-web-security")
-            .window_size(1920, 1080);
+            .args(Self::security_args(config))
+            .window_size(VIEWPORT.0, VIEWPORT.1);
 
         if let Some(proxy) = std::env::var("HTTPS_PROXY")
             .ok()
@@ -186,21 +819,77 @@ impl BrowserPool {
         }
     }
 
-    pub async fn invalidate_browser(&self) {
-        warn!("Invalidating current browser instance");
+    /// Readiness check that opens `about:blank` in the current browser.
+    /// Results are reused for a few seconds so probes cannot flood Chrome.
+    /// A lazily initialized pool launches its browser on the first call; an
+    /// invalidated browser is reported as unhealthy, not recreated.
+    pub async fn deep_health_check(&self) -> bool {
+        let mut last = self.last_deep_check.lock().await;
+        if let Some((checked_at, healthy)) = *last {
+            if checked_at.elapsed() < Duration::from_secs(DEEP_HEALTH_CACHE_SECS) {
+                return healthy;
+            }
+        }
+
+        if self.recreation_count() == 0 {
+            if let Err(e) = self.ensure_browser().await {
+                warn!("Deep health check could not launch browser: {}", e);
+            }
+        }
+
+        let healthy = self.health_check().await;
+        *last = Some((std::time::Instant::now(), healthy));
+        healthy
+    }
+
+    pub async fn invalidate_browser(&self, reason: RecreationReason) {
+        warn!("Invalidating current browser instance ({:?})", reason);
+        self.record_recreation(reason);
         self.is_healthy.store(false, Ordering::SeqCst);
+        *self.last_deep_check.lock().await = None;
 
         let mut browser_guard = self.browser.write().await;
         if let Some(browser) = browser_guard.take() {
             drop(browser);
         }
+        self.warm_contexts.lock().await.clear();
     }
 
-    pub async fn get_page(&self, options: &CrawlerOptions) -> Result<Page> {
-        let _permit = self.semaphore.acquire().await
-            .map_err(|e| AppError::BrowserError(e.to_string()))?;
+    fn record_recreation(&self, reason: RecreationReason) {
+        let mut recreations = self.recreations.lock();
+        if recreations.len() == RECREATION_HISTORY {
+            recreations.pop_front();
+        }
+        recreations.push_back(RecreationEvent { at: chrono::Utc::now(), reason });
+    }
+
+    /// The latest browser invalidations and why they happened, oldest first.
+    pub fn recent_recreations(&self) -> Vec<RecreationEvent> {
+        self.recreations.lock().iter().cloned().collect()
+    }
+
+    /// Replaces the browser with a fresh instance and returns the new
+    /// recreation count. Waits for every checked-out page to be returned
+    /// first, so in-flight requests finish on the old browser while new ones
+    /// queue for the replacement.
+    pub async fn recycle(&self) -> Result<u64> {
+        let _recycle = self.recycle_lock.lock().await;
+        let _drained = self.permits.acquire_many(Priority::High, self.total_slots()).await;
+
+        info!("Recycling browser instance");
+        self.invalidate_browser(RecreationReason::ManualRecycle).await;
+        self.ensure_browser().await?;
+
+        Ok(self.recreation_count())
+    }
+
+    pub async fn get_page(&self, options: &CrawlerOptions) -> Result<PooledPage> {
+        let queued_at = std::time::Instant::now();
+        let permit = self.permits.acquire(options.priority).await;
+        self.acquire_waits.record(queued_at.elapsed());
 
         let mut last_error = None;
+        let mut page_retries = 0;
 
         for attempt in 0..MAX_RETRIES {
             if attempt > 0 {
@@ -210,29 +899,40 @@ impl BrowserPool {
 
             if let Err(e) = self.ensure_browser().await {
                 error!("Failed to ensure browser: {}", e);
+                if matches!(e, AppError::ConfigError(_)) {
+                    return Err(e);
+                }
                 last_error = Some(e);
                 continue;
             }
 
-            if attempt > 0 || !self.is_healthy.load(Ordering::SeqCst) {
-                if !self.health_check().await {
-                    warn!("Browser health check failed, recreating...");
-                    self.invalidate_browser().await;
-                    continue;
-                }
+            if (attempt > 0 || !self.is_healthy.load(Ordering::SeqCst))
+                && !self.health_check().await
+            {
+                warn!("Browser health check failed, recreating...");
+                self.invalidate_browser(RecreationReason::HealthCheckFailed).await;
+                continue;
             }
 
             match self.try_get_page(options).await {
-                Ok(page) => return Ok(page),
-                Err(e) => {
-                    if Self::is_connection_error(&e) {
+                Ok(mut page) => {
+                    page.permit = Some(permit);
+                    return Ok(page);
+                }
+                Err(e) => match Self::failure_scope(&e) {
+                    Some(FailureScope::Page) if page_retries < MAX_PAGE_RETRIES => {
+                        warn!("Page error getting page: {}, will retry with a new page", e);
+                        page_retries += 1;
+                        last_error = Some(e);
+                    }
+                    Some(_) => {
                         warn!("Connection error getting page: {}, will retry", e);
-                        self.invalidate_browser().await;
+                        self.invalidate_browser(RecreationReason::ConnectionError).await;
+                        page_retries = 0;
                         last_error = Some(e);
-                    } else {
-                        return Err(e);
                     }
-                }
+                    None => return Err(e),
+                },
             }
         }
 
@@ -241,22 +941,142 @@ impl BrowserPool {
         }))
     }
 
-    async fn try_get_page(&self, options: &CrawlerOptions) -> Result<Page> {
+    /// Domain whose warm context should be reused for `url`, if any.
+    fn warm_context_domain(&self, url: &str) -> Option<String> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_lowercase()))?;
+
+        self.config.warm_context_domains.iter()
+            .find(|domain| host == **domain || host.ends_with(&format!(".{}", domain)))
+            .cloned()
+    }
+
+    /// Pick the browser context for a new page. Returns the context id and,
+    /// for a warm context, its domain; a context without one is throwaway
+    /// and disposed with the page.
+    async fn select_context(
+        &self,
+        browser: &Browser,
+        url: &str,
+    ) -> Result<Option<(BrowserContextId, Option<WarmUse>)>> {
+        if !self.config.browser_context_isolation {
+            return Ok(None);
+        }
+
+        let create = || async {
+            tokio::time::timeout(
+                Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+                browser.create_browser_context(CreateBrowserContextParams::default()),
+            )
+            .await
+            .map_err(|_| AppError::BrowserError("Timeout creating browser context - browser connection may be dead".to_string()))?
+            .map_err(|e| AppError::BrowserError(format!("Failed to create browser context: {}", e)))
+        };
+
+        match self.warm_context_domain(url) {
+            Some(domain) => {
+                let mut warm = self.warm_contexts.lock().await;
+                if let Some(id) = warm.get(&domain) {
+                    return Ok(Some((id.clone(), Some(WarmUse { domain, reused: true }))));
+                }
+                let id = create().await?;
+                debug!("Created warm browser context for {}", domain);
+                warm.insert(domain.clone(), id.clone());
+                Ok(Some((id, Some(WarmUse { domain, reused: false }))))
+            }
+            None => Ok(Some((create().await?, None))),
+        }
+    }
+
+    fn device_metrics(device: &DevicePreset) -> SetDeviceMetricsOverrideParams {
+        let mut params = SetDeviceMetricsOverrideParams::new(
+            device.width,
+            device.height,
+            device.device_scale_factor,
+            device.mobile,
+        );
+        params.screen_width = Some(device.width.into());
+        params.screen_height = Some(device.height.into());
+        params
+    }
+
+    /// Apply a device preset's viewport, scale factor and, for mobile
+    /// devices, touch input to `page`, the way DevTools device mode does.
+    async fn emulate_device(page: &Page, device: &DevicePreset) -> Result<()> {
+        tokio::time::timeout(
+            Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+            page.execute(Self::device_metrics(device))
+        )
+        .await
+        .map_err(|_| AppError::BrowserError("Timeout emulating device - browser connection may be dead".to_string()))?
+        .map_err(|e| AppError::BrowserError(format!("Failed to emulate device: {}", e)))?;
+
+        if device.mobile {
+            tokio::time::timeout(
+                Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+                page.execute(SetTouchEmulationEnabledParams::new(true))
+            )
+            .await
+            .map_err(|_| AppError::BrowserError("Timeout enabling touch emulation - browser connection may be dead".to_string()))?
+            .map_err(|e| AppError::BrowserError(format!("Failed to enable touch emulation: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    async fn try_get_page(&self, options: &CrawlerOptions) -> Result<PooledPage> {
         let browser_guard = self.browser.read().await;
         let browser = browser_guard.as_ref()
             .ok_or_else(|| AppError::BrowserError("Browser not initialized".to_string()))?;
 
+        let context = self.select_context(browser, &options.url).await?;
+        let mut params = CreateTargetParams::new("about:blank");
+        params.browser_context_id = context.as_ref().map(|(id, _)| id.clone());
+
         let page = tokio::time::timeout(
             Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
-            browser.new_page("about:blank")
+            browser.new_page(params)
         )
         .await
-        .map_err(|_| AppError::BrowserError("Timeout creating page - browser connection may be dead".to_string()))?
-        .map_err(|e| AppError::BrowserError(format!("Failed to create page: {}", e)))?;
+        .map_err(|_| AppError::BrowserError("Timeout creating page - browser connection may be dead".to_string()))
+        .and_then(|created| {
+            created.map_err(|e| AppError::BrowserError(format!("Failed to create page: {}", e)))
+        });
+
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                if let Some((id, None)) = context {
+                    let _ = browser.dispose_browser_context(id).await;
+                }
+                return Err(e);
+            }
+        };
 
         drop(browser_guard);
 
-        let user_agent = self.user_agent.resolve(options.user_agent.as_deref());
+        let (disposable_context, warm) = match context {
+            Some((id, None)) => (Some(id), None),
+            Some((_, warm)) => (None, warm),
+            None => (None, None),
+        };
+        let page = PooledPage {
+            page,
+            disposable_context,
+            warm,
+            browser: self.browser.clone(),
+            permit: None,
+        };
+
+        let device = options.device.as_deref()
+            .map(|name| {
+                self.config.device_preset(name)
+                    .ok_or_else(|| AppError::InvalidOption(format!("Unknown device preset '{}'", name)))
+            })
+            .transpose()?;
+        let user_agent = self.user_agent.resolve(
+            options.user_agent.as_deref().or(device.as_ref().map(|d| d.user_agent.as_str()))
+        );
         debug!("Using user agent: {}", user_agent);
         tokio::time::timeout(
             Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
@@ -266,47 +1086,290 @@ impl BrowserPool {
         .map_err(|_| AppError::BrowserError("Timeout setting user agent - browser connection may be dead".to_string()))?
         .map_err(|e| AppError::BrowserError(format!("Failed to set user agent: {}", e)))?;
 
-        if let Some(ref cookies_str) = options.cookies {
-            let cookies = Self::parse_cookies(cookies_str, &options.url);
-            if !cookies.is_empty() {
-                let params = SetCookiesParams::new(cookies);
-                tokio::time::timeout(
-                    Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
-                    page.execute(params)
-                )
-                .await
-                .map_err(|_| AppError::BrowserError("Timeout setting cookies - browser connection may be dead".to_string()))?
-                .map_err(|e| AppError::BrowserError(format!("Failed to set cookies: {}", e)))?;
-            }
+        if let Some(device) = &device {
+            Self::emulate_device(&page, device).await?;
+        }
+
+        if options.stealth.unwrap_or(self.config.stealth_mode) {
+            tokio::time::timeout(
+                Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+                page.execute(AddScriptToEvaluateOnNewDocumentParams::new(STEALTH_SCRIPT))
+            )
+            .await
+            .map_err(|_| AppError::BrowserError("Timeout installing stealth script - browser connection may be dead".to_string()))?
+            .map_err(|e| AppError::BrowserError(format!("Failed to install stealth script: {}", e)))?;
+        }
+
+        let mut cookies = options.cookies.as_deref()
+            .map(|cookies_str| Self::parse_cookies(cookies_str, &options.url))
+            .unwrap_or_default();
+        cookies.extend(Self::json_cookies(&options.cookies_json, &options.url));
+
+        if !cookies.is_empty() {
+            let params = SetCookiesParams::new(cookies);
+            tokio::time::timeout(
+                Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+                page.execute(params)
+            )
+            .await
+            .map_err(|_| AppError::BrowserError("Timeout setting cookies - browser connection may be dead".to_string()))?
+            .map_err(|e| AppError::BrowserError(format!("Failed to set cookies: {}", e)))?;
         }
 
         Ok(page)
     }
 
+    /// `navigate_and_wait`, then with `x-follow-meta-refresh` keep loading
+    /// meta-refresh targets until a page stops redirecting. JavaScript
+    /// redirects followed with `x-follow-js-redirect` join the same chain.
+    pub async fn navigate_following_refresh(
+        &self,
+        page: &PooledPage,
+        options: &CrawlerOptions,
+        progress: &ProgressReporter,
+    ) -> Result<Navigation> {
+        let started = std::time::Instant::now();
+        let navigation = self.follow_refresh(page, options, progress).await?;
+        if let Some(warm) = &page.warm {
+            self.record_warm_load(warm, started.elapsed());
+        }
+        Ok(navigation)
+    }
+
+    /// Count a load of a warm domain towards `warm_context_latency`.
+    fn record_warm_load(&self, warm: &WarmUse, elapsed: Duration) {
+        let mut latency = self.warm_latency.lock();
+        let totals = latency.entry(warm.domain.clone()).or_default();
+        let ms = elapsed.as_millis() as u64;
+        if warm.reused {
+            totals.warm_loads += 1;
+            totals.warm_ms += ms;
+        } else {
+            totals.cold_loads += 1;
+            totals.cold_ms += ms;
+        }
+    }
+
+    /// Average load time of each warm domain in a new context and in one
+    /// earlier loads already used, by domain. The difference is what the
+    /// warm context saves; loads that failed are not counted.
+    pub fn warm_context_latency(&self) -> Vec<WarmContextLatency> {
+        let average = |ms: u64, loads: u64| (loads > 0).then(|| ms / loads);
+        let mut domains: Vec<_> = self.warm_latency.lock().iter()
+            .map(|(domain, totals)| WarmContextLatency {
+                domain: domain.clone(),
+                cold_loads: totals.cold_loads,
+                cold_avg_ms: average(totals.cold_ms, totals.cold_loads),
+                warm_loads: totals.warm_loads,
+                warm_avg_ms: average(totals.warm_ms, totals.warm_loads),
+            })
+            .collect();
+        domains.sort_by(|a, b| a.domain.cmp(&b.domain));
+        domains
+    }
+
+    async fn follow_refresh(
+        &self,
+        page: &Page,
+        options: &CrawlerOptions,
+        progress: &ProgressReporter,
+    ) -> Result<Navigation> {
+        let deadline = tokio::time::Instant::now()
+            + Duration::from_secs(self.config.effective_timeout(options.timeout));
+        let first = self.navigate(page, &options.url, options, progress, Some(deadline)).await?;
+        let (mut html, mut validators, mut source) = (first.html, first.validators, first.source);
+        let mut chain = vec![options.url.clone()];
+        chain.extend(first.redirect_chain);
+
+        if options.follow_meta_refresh {
+            let mut current = Self::chain_end(&chain)?;
+            while let Some(target) = ScraperService::meta_refresh_target(&html, &current) {
+                if chain.len() > MAX_REDIRECT_HOPS {
+                    return Err(AppError::BrowserError(format!(
+                        "Stopped after {} redirects from {}",
+                        MAX_REDIRECT_HOPS, options.url
+                    )));
+                }
+                self.check_redirect(current.as_str(), target.as_str())?;
+                debug!("Following meta refresh from {} to {}", current, target);
+
+                // The hop is a plain GET, like a browser following the refresh.
+                let hop = CrawlerOptions {
+                    url: target.to_string(),
+                    post_body: None,
+                    referer: None,
+                    cdp_commands: Vec::new(),
+                    conditional: None,
+                    ..options.clone()
+                };
+                let landed = self.navigate(page, &hop.url, &hop, progress, None).await?;
+                (html, validators, source) = (landed.html, landed.validators, landed.source);
+                chain.push(hop.url);
+                chain.extend(landed.redirect_chain);
+                current = Self::chain_end(&chain)?;
+            }
+        }
+
+        if chain.len() == 1 {
+            chain.clear();
+        } else {
+            // They belong to the last hop, but a revalidation would send them
+            // to the request URL.
+            validators = OriginValidators::default();
+        }
+        Ok(Navigation { html, redirect_chain: chain, validators, source, deadline })
+    }
+
+    /// Check a URL a redirect leads to. The request URL is validated before
+    /// the load, so a hop that stays on the origin it comes from passes; any
+    /// other hop goes through `SecurityService::validate_url`, so a page
+    /// cannot point the engine at a host the request could not name.
+    fn check_redirect(&self, from: &str, target: &str) -> Result<()> {
+        let same_origin = match (url::Url::parse(from), url::Url::parse(target)) {
+            (Ok(from), Ok(target)) => from.origin() == target.origin(),
+            _ => false,
+        };
+        if same_origin {
+            return Ok(());
+        }
+        self.security.validate_url(target).map(|_| ()).inspect_err(|e| {
+            warn!("Refusing redirect from {} to {}: {}", from, target, e);
+        })
+    }
+
+    fn chain_end(chain: &[String]) -> Result<url::Url> {
+        let last = chain.last().map(String::as_str).unwrap_or_default();
+        url::Url::parse(last).map_err(|e| AppError::InvalidUrl(format!("Invalid URL format: {}", e)))
+    }
+
     pub async fn navigate_and_wait(
         &self,
         page: &Page,
         url: &str,
         options: &CrawlerOptions,
+        progress: &ProgressReporter,
     ) -> Result<String> {
-        let timeout = Duration::from_secs(options.timeout.unwrap_or(self.config.request_timeout));
+        let deadline = tokio::time::Instant::now()
+            + Duration::from_secs(self.config.effective_timeout(options.timeout));
+        self.navigate(page, url, options, progress, Some(deadline))
+            .await
+            .map(|navigation| navigation.html)
+    }
+
+    /// `navigate_once`, loading the page one more time after waiting out a
+    /// 429 or 503 whose `Retry-After` is within `ORIGIN_RETRY_AFTER_MAX`.
+    /// The retry is only made with `retry_until` set and when the wait ends
+    /// before it, and it has to finish by `retry_until` as well, so the whole
+    /// load stays within the request timeout. Meta-refresh hops pass `None`
+    /// and never wait.
+    async fn navigate(
+        &self,
+        page: &Page,
+        url: &str,
+        options: &CrawlerOptions,
+        progress: &ProgressReporter,
+        retry_until: Option<tokio::time::Instant>,
+    ) -> Result<Navigation> {
+        let result = self.navigate_once(page, url, options, progress).await;
+        let wait = match &result {
+            Err(AppError::RateLimitExceeded { retry_after: Some(seconds), .. }) => Self::retry_wait(
+                *seconds,
+                self.config.origin_retry_after_max,
+                retry_until,
+                tokio::time::Instant::now(),
+            ),
+            _ => None,
+        };
+        match (wait, retry_until) {
+            (Some(wait), Some(until)) => {
+                info!("{} asked to retry after {:?}, waiting", url, wait);
+                tokio::time::sleep(wait).await;
+                tokio::time::timeout_at(until, self.navigate_once(page, url, options, progress))
+                    .await
+                    .map_err(|_| AppError::Timeout(self.config.effective_timeout(options.timeout)))?
+            }
+            _ => result,
+        }
+    }
+
+    /// How long to wait before retrying an origin that sent `Retry-After:
+    /// seconds`, or `None` when the wait is over `max_wait`, retries are off
+    /// or it would not end before `retry_until`.
+    fn retry_wait(
+        seconds: u64,
+        max_wait: u64,
+        retry_until: Option<tokio::time::Instant>,
+        now: tokio::time::Instant,
+    ) -> Option<Duration> {
+        let until = retry_until?;
+        let wait = Duration::from_secs(seconds);
+        (max_wait > 0 && seconds <= max_wait && now + wait < until).then_some(wait)
+    }
+
+    /// `navigate_and_wait`, also returning the validators of the document
+    /// and, with `x-follow-js-redirect`, the URLs scripts navigated to while
+    /// the page settled. With `options.conditional` set, a 304 from the
+    /// origin ends the load early with `AppError::NotModified`; a 429 or 503
+    /// with `Retry-After` ends it with `AppError::RateLimitExceeded`.
+    async fn navigate_once(
+        &self,
+        page: &Page,
+        url: &str,
+        options: &CrawlerOptions,
+        progress: &ProgressReporter,
+    ) -> Result<Navigation> {
+        progress.emit(LoadEvent::Navigating);
+
+        self.validate_cdp_commands(&options.cdp_commands)?;
+
+        let timeout = Duration::from_secs(self.config.effective_timeout(options.timeout));
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        let body_watcher = match options.max_bytes {
+            Some(limit) => Some(Self::watch_body_size(page, limit).await?),
+            None => None,
+        };
+
+        let document_watcher = Self::watch_document(page).await?;
+
+        let rewrite = match (&options.post_body, &options.conditional) {
+            (Some(post), _) => Some(DocumentRewrite::Post(post.clone())),
+            (None, Some(validators)) if !validators.is_empty() => {
+                Some(DocumentRewrite::Conditional(validators.clone()))
+            }
+            _ => None,
+        };
+        let interceptor = match rewrite {
+            Some(rewrite) => Some(DocumentInterceptor::start(page, rewrite).await?),
+            None => None,
+        };
+
+        let mut navigate = NavigateParams::new(url);
+        if let Some(referer) = &options.referer {
+            // Chrome's default strict-origin-when-cross-origin policy would
+            // cut a cross-origin referrer down to its origin; send it as given.
+            navigate.referrer = Some(referer.clone());
+            navigate.referrer_policy = Some(ReferrerPolicy::UnsafeUrl);
+        }
 
         let result = tokio::time::timeout(timeout, async {
-            page.goto(url)
-                .await
-                .map_err(|e| {
-                    let err_str = e.to_string();
-                    if Self::is_connection_error_str(&err_str) {
-                        self.is_healthy.store(false, Ordering::SeqCst);
-                    }
-                    AppError::BrowserError(format!("Navigation failed: {}", e))
-                })?;
+            let navigated = if options.no_settle {
+                Self::navigate_until_dom_ready(page, navigate).await
+            } else {
+                page.goto(navigate).await.map(|_| ()).map_err(|e| e.to_string())
+            };
+            navigated.map_err(|err_str| {
+                if Self::failure_scope_str(&err_str) == Some(FailureScope::Browser) {
+                    self.is_healthy.store(false, Ordering::SeqCst);
+                }
+                AppError::BrowserError(format!("Navigation failed: {}", err_str))
+            })?;
 
             page.evaluate("document.readyState")
                 .await
                 .map_err(|e| {
                     let err_str = e.to_string();
-                    if Self::is_connection_error_str(&err_str) {
+                    if Self::failure_scope_str(&err_str) == Some(FailureScope::Browser) {
                         self.is_healthy.store(false, Ordering::SeqCst);
                     }
                     AppError::BrowserError(format!("Ready state check failed: {}", e))
@@ -315,10 +1378,51 @@ impl BrowserPool {
             Ok::<(), AppError>(())
         })
         .await
-        .map_err(|_| AppError::Timeout(timeout.as_secs()))?;
+        .map_err(|_| AppError::Timeout(timeout.as_secs()));
+
+        if let Some(interceptor) = interceptor {
+            interceptor.finish(page).await;
+        }
+
+        let result = result?;
+
+        if let (Some(watcher), Some(limit)) = (&body_watcher, options.max_bytes) {
+            if watcher.exceeded() {
+                warn!("Aborted navigation to {}: body exceeded {} bytes", url, limit);
+                return Err(AppError::ContentTooLarge(limit));
+            }
+        }
+        drop(body_watcher);
 
         result?;
 
+        let document = document_watcher.response();
+        drop(document_watcher);
+        if options.conditional.is_some() && document.as_ref().is_some_and(|d| d.status == 304) {
+            debug!("{} is unchanged since the cached copy", url);
+            return Err(AppError::NotModified(url.to_string()));
+        }
+        if let Some(seconds) = document.as_ref().and_then(DocumentResponse::refused_for) {
+            warn!("{} refused the load, retry after {}s", url, seconds);
+            let domain = url::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string))
+                .unwrap_or_else(|| url.to_string());
+            return Err(AppError::RateLimitExceeded { domain, retry_after: Some(seconds) });
+        }
+
+        progress.emit(LoadEvent::Settling);
+
+        let frame_watcher = if options.follow_js_redirect {
+            Some(Self::watch_frame_navigations(page).await?)
+        } else {
+            None
+        };
+
+        if options.wait_for_challenge {
+            Self::wait_for_challenge(page, url, deadline).await?;
+        }
+
         if let Some(ref selector) = options.wait_for_selector {
             tokio::time::timeout(timeout, async {
                 page.find_element(selector)
@@ -329,48 +1433,514 @@ impl BrowserPool {
             .map_err(|_| AppError::Timeout(timeout.as_secs()))??;
         }
 
-        tokio::time::sleep(Duration::from_millis(1000)).await;
+        if !options.wait_for_selectors.is_empty() {
+            tokio::time::timeout_at(
+                deadline,
+                Self::wait_for_selectors(page, &options.wait_for_selectors, options.wait_mode),
+            )
+            .await
+            .map_err(|_| AppError::Timeout(timeout.as_secs()))??;
+        }
+
+        let settle = Self::settle_delay(options, timeout);
+        match options.max_heap_mb {
+            Some(limit_mb) => {
+                if let Err(e) = page.execute(performance::EnableParams::default()).await {
+                    debug!("Failed to enable performance metrics: {}", e);
+                }
+                Self::settle_within_heap(settle, limit_mb, || Self::js_heap_used(page)).await?;
+            }
+            None if !settle.is_zero() => tokio::time::sleep(settle).await,
+            None => {}
+        }
+
+        let js_redirects = match frame_watcher {
+            Some(watcher) => {
+                tokio::time::timeout_at(deadline, self.settle_js_redirects(page, url, &watcher, settle))
+                    .await
+                    .map_err(|_| AppError::Timeout(timeout.as_secs()))??
+            }
+            None => Vec::new(),
+        };
+
+        for command in &options.cdp_commands {
+            page.execute(RawCdpCommand(command))
+                .await
+                .map_err(|e| AppError::BrowserError(format!("CDP command {} failed: {}", command.method, e)))?;
+        }
+
+        // Best effort: clean_html still drops inline-hidden markup if this fails.
+        if options.strip_hidden {
+            match page.evaluate(STRIP_HIDDEN_SCRIPT).await {
+                Ok(result) => debug!("Stripped {:?} hidden elements", result.value()),
+                Err(e) => warn!("Failed to strip hidden elements in the page: {}", e),
+            }
+        }
 
         let html = page
             .content()
             .await
             .map_err(|e| {
                 let err_str = e.to_string();
-                if Self::is_connection_error_str(&err_str) {
+                if Self::failure_scope_str(&err_str) == Some(FailureScope::Browser) {
                     self.is_healthy.store(false, Ordering::SeqCst);
                 }
                 AppError::BrowserError(format!("Failed to get content: {}", e))
             })?;
 
-        Ok(html)
+        if !options.wait_for_challenge && ScraperService::is_challenge_page(&html) {
+            warn!("{} returned an anti-bot interstitial; retry with x-wait-for-challenge", url);
+        }
+
+        // Validators and source describe the first document, not the one
+        // scripts moved to.
+        let document = document.filter(|_| js_redirects.is_empty());
+        let source = match document.as_ref() {
+            Some(DocumentResponse { request_id: Some(request_id), content_type, .. })
+                if options.html_source && options.respond_with == ResponseFormat::Html =>
+            {
+                Self::document_source(page, request_id.clone(), content_type.as_deref())
+                    .await
+                    .inspect_err(|e| warn!("{}", e))
+                    .ok()
+            }
+            _ => None,
+        };
+        let validators = document.map(|d| d.validators).unwrap_or_default();
+        Ok(Navigation { html, redirect_chain: js_redirects, validators, source, deadline })
+    }
+
+    /// The main document's body as it came over the network, before any
+    /// script touched it.
+    async fn document_source(page: &Page, request_id: RequestId, content_type: Option<&str>) -> Result<DocumentSource> {
+        let body = page.execute(GetResponseBodyParams::new(request_id))
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to read document source: {}", e)))?;
+        DocumentSource::from_response_body(&body.body, body.base64_encoded, content_type)
+    }
+
+    async fn watch_frame_navigations(page: &Page) -> Result<FrameNavigationWatcher> {
+        let mut navigations = page
+            .event_listener::<EventFrameNavigated>()
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to listen for frame navigations: {}", e)))?;
+
+        let urls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let seen = urls.clone();
+        let task = tokio::spawn(async move {
+            while let Some(event) = navigations.next().await {
+                if event.frame.parent_id.is_none() {
+                    // A failed load commits Chrome's error page; record the
+                    // URL the page tried to reach instead.
+                    let url = event.frame.unreachable_url.clone().unwrap_or_else(|| event.frame.url.clone());
+                    seen.lock().push(url);
+                }
+            }
+        });
+
+        Ok(FrameNavigationWatcher { urls, task })
     }
 
+    /// Once the settle pause is over, wait for every main-frame navigation
+    /// the watcher saw to finish loading and settle in turn, until the page
+    /// stays put. Returns the URLs navigated to, failing past
+    /// `MAX_REDIRECT_HOPS` or at the first one `check_redirect` refuses.
+    async fn settle_js_redirects(
+        &self,
+        page: &Page,
+        url: &str,
+        watcher: &FrameNavigationWatcher,
+        settle: Duration,
+    ) -> Result<Vec<String>> {
+        let mut followed = 0;
+        loop {
+            let urls = watcher.urls();
+            if urls.len() == followed {
+                return Ok(urls);
+            }
+            if urls.len() > MAX_REDIRECT_HOPS {
+                return Err(AppError::BrowserError(format!(
+                    "Stopped after {} JavaScript redirects from {}",
+                    MAX_REDIRECT_HOPS, url
+                )));
+            }
+            for (i, target) in urls.iter().enumerate().skip(followed) {
+                let from = if i == 0 { url } else { &urls[i - 1] };
+                if let Err(e) = self.check_redirect(from, target) {
+                    if let Err(stop) = page.execute(StopLoadingParams::default()).await {
+                        debug!("Failed to stop loading {}: {}", target, stop);
+                    }
+                    return Err(e);
+                }
+            }
+            followed = urls.len();
+            debug!("{} redirected itself to {}", url, urls[followed - 1]);
+
+            // Evaluating fails while the old document is torn down; keep polling.
+            while !matches!(
+                page.evaluate("document.readyState").await.ok().and_then(|r| r.into_value::<String>().ok()),
+                Some(state) if state == "complete"
+            ) {
+                tokio::time::sleep(Duration::from_millis(SELECTOR_POLL_INTERVAL_MS)).await;
+            }
+            tokio::time::sleep(settle).await;
+        }
+    }
+
+    /// Wait out `settle` while sampling the JS heap every
+    /// `HEAP_POLL_INTERVAL_MS`, failing as soon as a sample is over
+    /// `limit_mb`. A zero settle still takes one sample; samples that could
+    /// not be read are skipped.
+    async fn settle_within_heap<F, Fut>(settle: Duration, limit_mb: u64, mut sample: F) -> Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Option<u64>>,
+    {
+        let limit = limit_mb.saturating_mul(1024 * 1024);
+        let until = tokio::time::Instant::now() + settle;
+        loop {
+            if let Some(used) = sample().await {
+                if used > limit {
+                    warn!("JS heap at {} bytes is over the {} MB limit; aborting the load", used, limit_mb);
+                    return Err(AppError::HeapLimitExceeded(limit_mb));
+                }
+            }
+            let left = until.saturating_duration_since(tokio::time::Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            tokio::time::sleep(left.min(Duration::from_millis(HEAP_POLL_INTERVAL_MS))).await;
+        }
+    }
+
+    /// `JSHeapUsedSize` from `Performance.getMetrics`, in bytes.
+    async fn js_heap_used(page: &Page) -> Option<u64> {
+        match page.execute(performance::GetMetricsParams::default()).await {
+            Ok(metrics) => metrics.result.metrics.iter()
+                .find(|metric| metric.name == "JSHeapUsedSize")
+                .map(|metric| metric.value as u64),
+            Err(e) => {
+                debug!("Failed to read performance metrics: {}", e);
+                None
+            }
+        }
+    }
+
+    /// `Page.navigate` without waiting for the `load` event, for
+    /// `x-no-settle`: returns as soon as the new document fires
+    /// `DOMContentLoaded`, while images and other subresources may still be
+    /// loading.
+    async fn navigate_until_dom_ready(page: &Page, navigate: NavigateParams) -> std::result::Result<(), String> {
+        let mut dom_ready = page.event_listener::<EventDomContentEventFired>()
+            .await
+            .map_err(|e| e.to_string())?;
+        let navigated = page.execute(navigate).await.map_err(|e| e.to_string())?;
+        if let Some(error) = navigated.result.error_text.clone() {
+            return Err(error);
+        }
+        match dom_ready.next().await {
+            Some(_) => Ok(()),
+            None => Err("page closed before DOMContentLoaded".to_string()),
+        }
+    }
+
+    fn settle_delay(options: &CrawlerOptions, timeout: Duration) -> Duration {
+        if options.no_settle {
+            return Duration::ZERO;
+        }
+        if let Some(ms) = options.settle_ms {
+            return Duration::from_millis(ms).min(timeout);
+        }
+        (timeout / SETTLE_TIMEOUT_DIVISOR).clamp(
+            Duration::from_millis(MIN_SETTLE_MS),
+            Duration::from_millis(MAX_SETTLE_MS),
+        )
+    }
+
+    /// Poll until the page no longer looks like an anti-bot interstitial.
+    /// Content errors count as still challenged because the check usually
+    /// clears by navigating to the real page.
+    async fn wait_for_challenge(page: &Page, url: &str, deadline: tokio::time::Instant) -> Result<()> {
+        let until = deadline.min(tokio::time::Instant::now() + Duration::from_secs(CHALLENGE_WAIT_SECS));
+
+        let poll = async {
+            let mut challenged = false;
+            loop {
+                let still_challenged = match page.content().await {
+                    Ok(html) => ScraperService::is_challenge_page(&html),
+                    Err(_) => true,
+                };
+                if !still_challenged {
+                    if challenged {
+                        info!("Anti-bot challenge cleared for {}", url);
+                    }
+                    return;
+                }
+                challenged = true;
+                tokio::time::sleep(Duration::from_millis(CHALLENGE_POLL_INTERVAL_MS)).await;
+            }
+        };
+
+        tokio::time::timeout_at(until, poll)
+            .await
+            .map_err(|_| AppError::ChallengeNotCleared(url.to_string()))
+    }
+
+    fn validate_cdp_commands(&self, commands: &[CdpCommand]) -> Result<()> {
+        if commands.is_empty() {
+            return Ok(());
+        }
+        if !self.config.allow_cdp_passthrough {
+            return Err(AppError::InvalidOption(
+                "x-cdp-commands requires ALLOW_CDP_PASSTHROUGH=true".to_string(),
+            ));
+        }
+        for command in commands {
+            if !CDP_PASSTHROUGH_ALLOWLIST.iter().any(|prefix| command.method.starts_with(prefix)) {
+                return Err(AppError::InvalidOption(format!(
+                    "CDP method '{}' is not allowed", command.method
+                )));
+            }
+            if !command.params.is_null() && !command.params.is_object() {
+                return Err(AppError::InvalidOption(format!(
+                    "params for '{}' must be an object", command.method
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Poll until any or all of `selectors` match, depending on `mode`. The
+    /// caller bounds this with the request deadline.
+    async fn wait_for_selectors(page: &Page, selectors: &[String], mode: WaitMode) -> Result<()> {
+        let list = serde_json::to_string(selectors)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let script = format!(
+            "{}.map(s => {{ try {{ return document.querySelector(s) !== null; }} catch (e) {{ return false; }} }})",
+            list
+        );
+
+        loop {
+            let matches: Vec<bool> = page
+                .evaluate(script.as_str())
+                .await
+                .map_err(|e| AppError::BrowserError(format!("Selector wait failed: {}", e)))?
+                .into_value()
+                .map_err(|e| AppError::BrowserError(format!("Selector wait failed: {}", e)))?;
+
+            if mode.is_satisfied(&matches) {
+                return Ok(());
+            }
+
+            tokio::time::sleep(Duration::from_millis(SELECTOR_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    async fn watch_document(page: &Page) -> Result<DocumentWatcher> {
+        let mut responses = page
+            .event_listener::<EventResponseReceived>()
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to listen for responses: {}", e)))?;
+
+        let response = Arc::new(parking_lot::Mutex::new(None));
+        let slot = response.clone();
+        let task = tokio::spawn(async move {
+            while let Some(event) = responses.next().await {
+                if event.r#type == ResourceType::Document {
+                    let mut document = DocumentResponse::from_headers(
+                        event.response.status,
+                        event.response.headers.inner(),
+                    );
+                    document.request_id = Some(event.request_id.clone());
+                    *slot.lock() = Some(document);
+                    break;
+                }
+            }
+        });
+
+        Ok(DocumentWatcher { response, task })
+    }
+
+    async fn watch_body_size(page: &Page, limit: u64) -> Result<BodySizeWatcher> {
+        let mut responses = page
+            .event_listener::<EventResponseReceived>()
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to listen for responses: {}", e)))?;
+        let mut chunks = page
+            .event_listener::<EventDataReceived>()
+            .await
+            .map_err(|e| AppError::BrowserError(format!("Failed to listen for data: {}", e)))?;
+
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let flag = exceeded.clone();
+        let page = page.clone();
+
+        let task = tokio::spawn(async move {
+            let mut budget = BodySizeLimit::new(limit);
+            let mut document: Option<RequestId> = None;
+
+            loop {
+                let over = tokio::select! {
+                    Some(event) = responses.next() => {
+                        if document.is_none() && event.r#type == ResourceType::Document {
+                            document = Some(event.request_id.clone());
+                            let content_length = BodySizeLimit::declared_length(event.response.headers.inner());
+                            budget.exceeds_declared(content_length)
+                        } else {
+                            false
+                        }
+                    }
+                    Some(event) = chunks.next() => {
+                        document.as_ref() == Some(&event.request_id)
+                            && budget.record(event.data_length.max(0) as u64)
+                    }
+                    else => break,
+                };
+
+                if over {
+                    flag.store(true, Ordering::SeqCst);
+                    if let Err(e) = page.execute(StopLoadingParams::default()).await {
+                        debug!("Failed to stop oversized page load: {}", e);
+                    }
+                    break;
+                }
+            }
+        });
+
+        Ok(BodySizeWatcher { exceeded, task })
+    }
+
+    /// Capture the page as PNG. A clip from `options` takes precedence over
+    /// `full_page`; its coordinates are relative to the document, so regions
+    /// below the fold can be captured too. Waiting for assets stops at
+    /// `deadline`, the end of the request timeout.
     pub async fn take_screenshot(
         &self,
         page: &Page,
+        options: &CrawlerOptions,
         full_page: bool,
-    ) -> Result<Vec<u8>> {
-        let params = ScreenshotParams::builder()
-            .format(CaptureScreenshotFormat::Png)
-            .full_page(full_page)
-            .build();
+        deadline: tokio::time::Instant,
+    ) -> Result<Screenshot> {
+        if options.screenshot_wait_assets {
+            // Best effort: a missing font or a hung image should not cost the capture.
+            if tokio::time::timeout_at(deadline, Self::wait_for_assets(page, full_page)).await.is_err() {
+                warn!("Fonts or images on {} still loading at the request timeout; capturing anyway", options.url);
+            }
+        }
+
+        let mut warning = None;
+        let clip = match &options.screenshot_clip {
+            Some(clip) => Some(Viewport {
+                x: clip.x,
+                y: clip.y,
+                width: clip.width,
+                height: clip.height,
+                scale: 1.0,
+            }),
+            None if full_page => {
+                let max_height = self.config.screenshot_max_height;
+                let capped = Self::page_size(page).await
+                    .and_then(|(width, height, ratio)| Self::capped_page_clip(width, height, ratio, max_height));
+                if let Some(capped) = &capped {
+                    let message = format!(
+                        "Page is taller than SCREENSHOT_MAX_HEIGHT ({}px); captured only its top {}px",
+                        max_height, max_height
+                    );
+                    warn!("{} on {}", message, options.url);
+                    warning = Some(message);
+                    debug!("Capping full-page capture at {}x{}", capped.width, capped.height);
+                }
+                capped
+            }
+            None => None,
+        };
+
+        let builder = ScreenshotParams::builder().format(CaptureScreenshotFormat::Png);
+        let params = match clip {
+            Some(clip) => builder.clip(clip).capture_beyond_viewport(true).build(),
+            None => builder.full_page(full_page).build(),
+        };
 
         let screenshot = page
             .screenshot(params)
             .await
             .map_err(|e| {
                 let err_str = e.to_string();
-                if Self::is_connection_error_str(&err_str) {
+                if Self::failure_scope_str(&err_str) == Some(FailureScope::Browser) {
                     self.is_healthy.store(false, Ordering::SeqCst);
                 }
                 AppError::ScreenshotError(e.to_string())
             })?;
 
-        Ok(screenshot)
+        Ok(Screenshot { data: screenshot, warning })
+    }
+
+    /// Document width and height in CSS pixels and the device pixel ratio,
+    /// or `None` when they could not be read.
+    async fn page_size(page: &Page) -> Option<(f64, f64, f64)> {
+        match page.evaluate(PAGE_SIZE_SCRIPT).await.map(|result| result.into_value::<(f64, f64, f64)>()) {
+            Ok(Ok(size)) => Some(size),
+            Ok(Err(e)) => {
+                debug!("Page size returned an unexpected value: {}", e);
+                None
+            }
+            Err(e) => {
+                debug!("Failed to read the page size: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Clip keeping the top `max_height` device pixels of a page too tall
+    /// to capture whole, or `None` when the page fits. `width` and `height`
+    /// are CSS pixels; each covers `ratio` device pixels in the capture.
+    fn capped_page_clip(width: f64, height: f64, ratio: f64, max_height: u32) -> Option<Viewport> {
+        let max_css_height = (max_height as f64 / ratio.max(1.0)).floor();
+        (height > max_css_height).then_some(Viewport {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height: max_css_height,
+            scale: 1.0,
+        })
+    }
+
+    /// Poll until fonts are ready and the captured images are complete. The
+    /// caller bounds this with the request timeout.
+    async fn wait_for_assets(page: &Page, full_page: bool) {
+        let script = ASSETS_READY_SCRIPT.replace("%FULL_PAGE%", if full_page { "true" } else { "false" });
+        loop {
+            match page.evaluate(script.as_str()).await.map(|result| result.into_value::<bool>()) {
+                Ok(Ok(true)) => return,
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => {
+                    debug!("Asset wait returned an unexpected value: {}", e);
+                    return;
+                }
+                Err(e) => {
+                    debug!("Asset wait failed: {}", e);
+                    return;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(ASSET_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    /// Wait for room under `BATCH_MAX_PAGES` before processing a batch URL.
+    pub async fn admit_batch(&self) -> BatchSlot {
+        self.batch_admission.admit().await
     }
 
     pub fn available_slots(&self) -> usize {
-        self.semaphore.available_permits()
+        self.permits.available()
+    }
+
+    pub fn queued_requests(&self) -> usize {
+        self.permits.queued()
+    }
+
+    pub fn acquire_wait_stats(&self) -> AcquireWaitStats {
+        self.acquire_waits.snapshot()
     }
 
     pub fn total_slots(&self) -> usize {
@@ -385,6 +1955,8 @@ impl BrowserPool {
         self.recreation_count.load(Ordering::SeqCst)
     }
 
+    /// Whether `err` came from a dead page or browser rather than from the
+    /// site being loaded, so the request is worth retrying.
     pub fn is_connection_error(err: &AppError) -> bool {
         match err {
             AppError::BrowserError(msg) => Self::is_connection_error_str(msg),
@@ -393,36 +1965,73 @@ impl BrowserPool {
     }
 
     fn is_connection_error_str(err_msg: &str) -> bool {
-        let connection_error_patterns = [
-            "AlreadyClosed",
-            "Ws(AlreadyClosed)",
-            "WebSocket",
-            "connection",
-            "Connection",
-            "ConnectionClosed",
+        Self::failure_scope_str(err_msg).is_some()
+    }
+
+    /// Whether `err` took down only a page or the whole browser, or `None`
+    /// when it is not a browser failure at all.
+    pub fn failure_scope(err: &AppError) -> Option<FailureScope> {
+        match err {
+            AppError::BrowserError(msg) => Self::failure_scope_str(msg),
+            _ => None,
+        }
+    }
+
+    /// Unmistakable connection failures are checked first, so "Failed to
+    /// create page: Ws(AlreadyClosed)" counts against the browser. A timed
+    /// out command on a page says "browser connection may be dead", but the
+    /// page is retried first and the browser only recreated if that fails.
+    fn failure_scope_str(err_msg: &str) -> Option<FailureScope> {
+        let browser_patterns = [
+            "alreadyclosed",
+            "websocket",
+            "connectionclosed",
+            "connection reset",
+            "connection refused",
+            "connection closed",
             "channel closed",
-            "Channel closed",
             "browser closed",
-            "Browser closed",
-            "target closed",
-            "Target closed",
-            "session closed",
-            "Session closed",
             "pipe",
-            "Pipe",
             "disconnected",
-            "Disconnected",
             "not connected",
-            "Not connected",
+            "timeout creating browser context",
+        ];
+        let page_patterns = [
+            "target closed",
+            "session closed",
+            "failed to create page",
+            "timeout creating page",
+            "timeout setting",
             "may be dead",
-            "Timeout creating page",
-            "Timeout setting",
         ];
 
         let err_lower = err_msg.to_lowercase();
-        connection_error_patterns.iter().any(|pattern| {
-            err_lower.contains(&pattern.to_lowercase())
-        })
+        if browser_patterns.iter().any(|pattern| err_lower.contains(pattern)) {
+            Some(FailureScope::Browser)
+        } else if page_patterns.iter().any(|pattern| err_lower.contains(pattern)) {
+            Some(FailureScope::Page)
+        } else if err_lower.contains("connection") {
+            Some(FailureScope::Browser)
+        } else {
+            None
+        }
+    }
+
+    /// Flags that relax TLS and same-origin checks. Both are on by default to
+    /// keep scraping lenient; deployments can drop them through config.
+    fn security_args(config: &Config) -> Vec<&'static str> {
+        let mut args = Vec::new();
+        if config.ignore_cert_errors {
+            args.extend([
+                "--ignore-certificate-errors",
+                "--ignore-ssl-errors",
+                "--ignore-certificate-errors-spki-list",
+            ]);
+        }
+        if config.disable_web_security {
+            args.push("--disable-web-security");
+        }
+        args
     }
 
     fn no_proxy_to_chrome_bypass(no_proxy: &str) -> String {
@@ -441,6 +2050,33 @@ impl BrowserPool {
             .join(";")
     }
 
+    fn json_cookies(cookies: &[CookieInput], url: &str) -> Vec<CookieParam> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        cookies.iter()
+            .map(|cookie| {
+                let mut param = CookieParam::new(cookie.name.clone(), cookie.value.clone());
+                param.domain = Some(cookie.domain.clone().unwrap_or_else(|| host.clone()));
+                param.path = cookie.path.clone();
+                param.secure = cookie.secure;
+                param.http_only = cookie.http_only;
+                param.same_site = cookie.same_site.as_deref().and_then(|v| {
+                    match v.to_lowercase().as_str() {
+                        "strict" => Some(CookieSameSite::Strict),
+                        "lax" => Some(CookieSameSite::Lax),
+                        "none" => Some(CookieSameSite::None),
+                        _ => None,
+                    }
+                });
+                param.expires = cookie.expires.map(TimeSinceEpoch::new);
+                param
+            })
+            .collect()
+    }
+
     fn parse_cookies(cookies_str: &str, url: &str) -> Vec<CookieParam> {
         let domain = url::Url::parse(url)
             .ok()
@@ -494,6 +2130,40 @@ mod tests {
         assert!(!BrowserPool::is_connection_error_str("JavaScript error"));
     }
 
+    #[test]
+    fn test_failure_scope_separates_page_and_browser_errors() {
+        for msg in [
+            "target closed",
+            "Session closed",
+            "Failed to create page: Target.createTarget failed",
+            "Timeout creating page - browser connection may be dead",
+            "Timeout setting user agent - browser connection may be dead",
+            "Timeout installing stealth script - browser connection may be dead",
+        ] {
+            assert_eq!(BrowserPool::failure_scope_str(msg), Some(FailureScope::Page), "{}", msg);
+        }
+
+        for msg in [
+            "Ws(AlreadyClosed)",
+            "Failed to create page: Ws(AlreadyClosed)",
+            "WebSocket connection failed",
+            "Connection refused",
+            "channel closed",
+            "browser closed unexpectedly",
+            "client disconnected",
+            "Timeout creating browser context - browser connection may be dead",
+        ] {
+            assert_eq!(BrowserPool::failure_scope_str(msg), Some(FailureScope::Browser), "{}", msg);
+        }
+
+        assert_eq!(BrowserPool::failure_scope_str("element not found"), None);
+        assert_eq!(BrowserPool::failure_scope(&AppError::Timeout(30)), None);
+        assert_eq!(
+            BrowserPool::failure_scope(&AppError::BrowserError("target closed".to_string())),
+            Some(FailureScope::Page),
+        );
+    }
+
     #[test]
     fn test_is_connection_error_with_app_error() {
         let err = AppError::BrowserError("Ws(AlreadyClosed)".to_string());
@@ -509,6 +2179,392 @@ mod tests {
         assert!(!BrowserPool::is_connection_error(&err));
     }
 
+    #[test]
+    fn test_missing_chrome_path_reports_config_error() {
+        let err = BrowserPool::resolve_chrome_path("/nonexistent/chrome", &[]).unwrap_err();
+        assert!(matches!(err, AppError::ConfigError(_)));
+        let message = err.to_string();
+        assert!(message.contains("/nonexistent/chrome"));
+        assert!(message.contains("CHROME_PATH"));
+    }
+
+    #[test]
+    fn test_chrome_path_falls_back_to_detected_candidate() {
+        let found = BrowserPool::resolve_chrome_path("/nonexistent/chrome", &["/nonexistent/other", "/bin/sh"])
+            .unwrap();
+        assert_eq!(found, "/bin/sh");
+    }
+
+    #[tokio::test]
+    async fn test_lazy_pool_has_no_browser_until_first_use() {
+        let config = Config {
+            browser_lazy_init: true,
+            chrome_path: "/nonexistent/chrome".to_string(),
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+
+        assert!(pool.browser.read().await.is_none());
+        assert!(!pool.is_healthy());
+        assert_eq!(pool.recreation_count(), 0);
+        assert_eq!(pool.available_slots(), pool.total_slots());
+    }
+
+    #[tokio::test]
+    async fn test_deep_health_caches_result_until_invalidated() {
+        let pool = BrowserPool::new(Config {
+            browser_lazy_init: true,
+            chrome_path: "/nonexistent/chrome".to_string(),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+
+        *pool.last_deep_check.lock().await = Some((std::time::Instant::now(), true));
+        assert!(pool.deep_health_check().await);
+
+        pool.invalidate_browser(RecreationReason::HealthCheckFailed).await;
+        assert!(!pool.deep_health_check().await);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_records_reason() {
+        let pool = BrowserPool::new(Config {
+            browser_lazy_init: true,
+            chrome_path: "/nonexistent/chrome".to_string(),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        assert!(pool.recent_recreations().is_empty());
+
+        pool.invalidate_browser(RecreationReason::ConnectionError).await;
+        pool.invalidate_browser(RecreationReason::ManualRecycle).await;
+        let reasons: Vec<_> = pool.recent_recreations().iter().map(|e| e.reason).collect();
+        assert_eq!(reasons, vec![RecreationReason::ConnectionError, RecreationReason::ManualRecycle]);
+
+        for _ in 0..RECREATION_HISTORY {
+            pool.invalidate_browser(RecreationReason::HealthCheckFailed).await;
+        }
+        let recent = pool.recent_recreations();
+        assert_eq!(recent.len(), RECREATION_HISTORY);
+        assert!(recent.iter().all(|e| e.reason == RecreationReason::HealthCheckFailed));
+        assert!(recent.windows(2).all(|pair| pair[0].at <= pair[1].at));
+    }
+
+    #[tokio::test]
+    async fn test_redirects_leaving_the_origin_are_validated() {
+        let pool = BrowserPool::new(Config {
+            browser_lazy_init: true,
+            chrome_path: "/nonexistent/chrome".to_string(),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+
+        assert!(pool.check_redirect("http://127.0.0.1:8080/", "http://127.0.0.1:8080/final").is_ok());
+        assert!(pool.check_redirect("https://example.com/", "https://www.example.org/").is_ok());
+        for blocked in ["http://169.254.169.254/latest/meta-data/", "http://localhost/", "http://10.0.0.5/"] {
+            let result = pool.check_redirect("https://example.com/", blocked);
+            assert!(matches!(result, Err(AppError::BlockedUrl(_))), "{}", blocked);
+        }
+        // Same host on another port is another origin.
+        assert!(pool.check_redirect("http://127.0.0.1:8080/", "http://127.0.0.1:9000/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_warm_context_domain_matching() {
+        let config = Config {
+            browser_lazy_init: true,
+            browser_context_isolation: true,
+            warm_context_domains: vec!["example.com".to_string()],
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+
+        assert_eq!(
+            pool.warm_context_domain("https://example.com/a"),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            pool.warm_context_domain("https://docs.Example.com/b"),
+            Some("example.com".to_string())
+        );
+        assert_eq!(pool.warm_context_domain("https://notexample.com/"), None);
+        assert_eq!(pool.warm_context_domain("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn test_warm_context_latency_splits_cold_and_warm_loads() {
+        let pool = BrowserPool::new(Config { browser_lazy_init: true, ..Config::default() }).await.unwrap();
+        let cold = WarmUse { domain: "example.com".to_string(), reused: false };
+        let warm = WarmUse { reused: true, ..cold.clone() };
+
+        pool.record_warm_load(&cold, Duration::from_millis(900));
+        pool.record_warm_load(&warm, Duration::from_millis(300));
+        pool.record_warm_load(&warm, Duration::from_millis(100));
+        pool.record_warm_load(&WarmUse { domain: "docs.rs".to_string(), reused: false }, Duration::from_millis(50));
+
+        let latency = pool.warm_context_latency();
+        assert_eq!(latency.len(), 2);
+        assert_eq!(latency[0].domain, "docs.rs");
+        assert_eq!(latency[0].warm_avg_ms, None);
+        let example = &latency[1];
+        assert_eq!((example.cold_loads, example.cold_avg_ms), (1, Some(900)));
+        assert_eq!((example.warm_loads, example.warm_avg_ms), (2, Some(200)));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_repeated_loads_of_a_warm_domain_are_measured() {
+        use axum::{routing::get, Router};
+
+        let app = Router::new().route("/", get(|| async { axum::response::Html("<html><body><p>Warm</p></body></html>") }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            browser_context_isolation: true,
+            warm_context_domains: vec!["127.0.0.1".to_string()],
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let mut options = CrawlerOptions::new(format!("http://{}/", addr));
+        options.no_settle = true;
+
+        for _ in 0..3 {
+            let page = pool.get_page(&options).await.unwrap();
+            pool.navigate_following_refresh(&page, &options, &ProgressReporter::disabled())
+                .await
+                .unwrap();
+        }
+
+        let latency = pool.warm_context_latency();
+        assert_eq!(latency.len(), 1);
+        assert_eq!((latency[0].cold_loads, latency[0].warm_loads), (1, 2));
+        assert!(latency[0].cold_avg_ms.is_some() && latency[0].warm_avg_ms.is_some());
+    }
+
+    #[test]
+    fn test_no_settle_skips_settle_delay() {
+        let mut options = CrawlerOptions::new("https://example.com".to_string());
+        let timeout = Duration::from_secs(30);
+        assert_eq!(BrowserPool::settle_delay(&options, timeout), Duration::from_secs(1));
+
+        options.no_settle = true;
+        assert_eq!(BrowserPool::settle_delay(&options, timeout), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_settle_aborts_when_heap_grows_past_limit() {
+        const MB: u64 = 1024 * 1024;
+        let settle = Duration::from_millis(2 * HEAP_POLL_INTERVAL_MS);
+
+        let mut growing = vec![Some(300 * MB), None, Some(20 * MB)];
+        let err = BrowserPool::settle_within_heap(settle, 100, || std::future::ready(growing.pop().flatten()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::HeapLimitExceeded(100)), "{}", err);
+        assert!(growing.is_empty());
+
+        let mut samples = 0;
+        BrowserPool::settle_within_heap(settle, 100, || {
+            samples += 1;
+            std::future::ready(Some(60 * MB))
+        })
+        .await
+        .unwrap();
+        assert!(samples >= 2);
+
+        let mut samples = 0;
+        BrowserPool::settle_within_heap(Duration::ZERO, 100, || {
+            samples += 1;
+            std::future::ready(None)
+        })
+        .await
+        .unwrap();
+        assert_eq!(samples, 1);
+    }
+
+    #[test]
+    fn test_settle_delay_scales_with_timeout() {
+        let mut options = CrawlerOptions::new("https://example.com".to_string());
+        let settle = |options: &CrawlerOptions, secs| BrowserPool::settle_delay(options, Duration::from_secs(secs));
+
+        assert_eq!(settle(&options, 5), Duration::from_millis(MIN_SETTLE_MS));
+        assert_eq!(settle(&options, 60), Duration::from_secs(2));
+        assert_eq!(settle(&options, 180), Duration::from_millis(MAX_SETTLE_MS));
+        for secs in [1, 15, 45, 90, 600] {
+            let delay = settle(&options, secs);
+            assert!(delay >= Duration::from_millis(MIN_SETTLE_MS) && delay <= Duration::from_millis(MAX_SETTLE_MS));
+        }
+
+        options.settle_ms = Some(4000);
+        assert_eq!(settle(&options, 60), Duration::from_millis(4000));
+        options.settle_ms = Some(0);
+        assert_eq!(settle(&options, 60), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_body_size_limit() {
+        let mut budget = BodySizeLimit::new(1000);
+        assert!(!budget.exceeds_declared(None));
+        assert!(!budget.exceeds_declared(Some(1000)));
+        assert!(budget.exceeds_declared(Some(1001)));
+
+        assert!(!budget.record(400));
+        assert!(!budget.record(600));
+        assert!(budget.record(1));
+
+        let mut streamed = BodySizeLimit::new(64 * 1024);
+        let exceeded_after = (1..=100)
+            .find(|_| streamed.record(1024))
+            .unwrap();
+        assert_eq!(exceeded_after, 65);
+    }
+
+    #[test]
+    fn test_declared_length_ignores_encoded_bodies() {
+        let identity = serde_json::json!({"Content-Length": "2048"});
+        assert_eq!(BodySizeLimit::declared_length(&identity), Some(2048));
+
+        let explicit = serde_json::json!({"content-length": "10", "Content-Encoding": "identity"});
+        assert_eq!(BodySizeLimit::declared_length(&explicit), Some(10));
+
+        for encoding in ["gzip", "br", "deflate"] {
+            let encoded = serde_json::json!({"content-length": "10", "content-encoding": encoding});
+            assert_eq!(BodySizeLimit::declared_length(&encoded), None, "{}", encoding);
+        }
+    }
+
+    /// Gzip with compression off, so the body is larger than its payload.
+    fn gzip_stored(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::none());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_stored_gzip_fixture_round_trips() {
+        use std::io::Read;
+
+        let html = b"<html><body><p>decoded from gzip</p></body></html>";
+        let body = gzip_stored(html);
+        assert!(body.len() > html.len() + 5);
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(body.as_slice()).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, html);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_gzip_document_is_decoded_and_limited_by_decoded_size() {
+        use axum::{http::header, routing::get, Router};
+
+        let html = "<html><body><p id=\"greeting\">decoded from gzip</p></body></html>";
+        let body = gzip_stored(html.as_bytes());
+        let encoded_len = body.len();
+        let app = Router::new().route(
+            "/gzip",
+            get(move || {
+                let body = body.clone();
+                async move {
+                    (
+                        [(header::CONTENT_TYPE, "text/html"), (header::CONTENT_ENCODING, "gzip")],
+                        body,
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let url = format!("http://{}/gzip", addr);
+
+        // Stored gzip is larger than its payload, so a limit between the two
+        // only passes if the decoded size is what counts.
+        let mut options = CrawlerOptions::new(url.clone());
+        options.max_bytes = Some(html.len() as u64 + 5);
+        assert!(options.max_bytes.unwrap() < encoded_len as u64);
+
+        let page = pool.get_page(&options).await.unwrap();
+        let content = pool
+            .navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        assert!(content.contains("decoded from gzip"), "{}", content);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_streamed_document_over_max_bytes_is_aborted() {
+        use axum::{body::Body, http::header, routing::get, Router};
+
+        // Chunked with no Content-Length, so only the streamed size can trip
+        // the limit. The stream would take about a minute to finish.
+        let app = Router::new().route(
+            "/stream",
+            get(|| async {
+                let head = futures::stream::once(async { "<html><body>".to_string() });
+                let chunks = futures::stream::unfold(0u32, |i| async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Some((format!("<p>chunk {} {}</p>", i, "x".repeat(1024)), i + 1))
+                })
+                .take(6000);
+                let stream = head.chain(chunks).map(Ok::<_, std::convert::Infallible>);
+                ([(header::CONTENT_TYPE, "text/html")], Body::from_stream(stream))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let url = format!("http://{}/stream", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        options.max_bytes = Some(64 * 1024);
+        options.no_settle = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let started = std::time::Instant::now();
+        let result = pool
+            .navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await;
+
+        assert!(matches!(result, Err(AppError::ContentTooLarge(65536))), "{:?}", result.map(|html| html.len()));
+        assert!(started.elapsed() < Duration::from_secs(20), "aborted after {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_security_flags_follow_config() {
+        let mut config = Config::default();
+        let args = BrowserPool::security_args(&config);
+        assert!(args.contains(&"--disable-web-security"));
+        assert!(args.contains(&"--ignore-certificate-errors"));
+
+        config.disable_web_security = false;
+        config.ignore_cert_errors = false;
+        assert!(BrowserPool::security_args(&config).is_empty());
+    }
+
     #[test]
     fn test_no_proxy_to_chrome_bypass() {
         assert_eq!(
@@ -535,4 +2591,848 @@ mod tests {
             "10.0.0.0/8"
         );
     }
+
+    #[test]
+    fn test_post_params_rewrite_navigation() {
+        let event: fetch::EventRequestPaused = serde_json::from_value(serde_json::json!({
+            "requestId": "interception-1",
+            "request": {
+                "url": "https://example.com/search",
+                "method": "GET",
+                "headers": {"User-Agent": "test-agent", "content-type": "text/plain"},
+                "initialPriority": "VeryHigh",
+                "referrerPolicy": "no-referrer"
+            },
+            "frameId": "frame-1",
+            "resourceType": "Document"
+        }))
+        .unwrap();
+        let post = PostBody {
+            body: "q=rust&page=2".to_string(),
+            content_type: "application/x-www-form-urlencoded".to_string(),
+        };
+
+        let params = DocumentInterceptor::post_params(&event, &post);
+
+        assert_eq!(params.method.as_deref(), Some("POST"));
+        let post_data: &str = params.post_data.as_ref().unwrap().as_ref();
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD.decode(post_data).unwrap(),
+            b"q=rust&page=2"
+        );
+
+        let headers = params.headers.unwrap();
+        let content_types: Vec<_> = headers.iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("content-type"))
+            .map(|h| h.value.as_str())
+            .collect();
+        assert_eq!(content_types, vec!["application/x-www-form-urlencoded"]);
+        assert!(headers.iter().any(|h| h.name == "User-Agent" && h.value == "test-agent"));
+    }
+
+    #[test]
+    fn test_conditional_params_and_document_validators() {
+        let event: fetch::EventRequestPaused = serde_json::from_value(serde_json::json!({
+            "requestId": "interception-1",
+            "request": {
+                "url": "https://example.com/article",
+                "method": "GET",
+                "headers": {"User-Agent": "test-agent", "if-none-match": "\"old\""},
+                "initialPriority": "VeryHigh",
+                "referrerPolicy": "no-referrer"
+            },
+            "frameId": "frame-1",
+            "resourceType": "Document"
+        }))
+        .unwrap();
+        let validators = OriginValidators {
+            etag: Some("\"v2\"".to_string()),
+            last_modified: Some("Wed, 14 Oct 2026 10:00:00 GMT".to_string()),
+        };
+
+        let params = DocumentInterceptor::conditional_params(&event, &validators);
+
+        assert!(params.method.is_none());
+        let headers = params.headers.unwrap();
+        let value = |name: &str| {
+            headers.iter()
+                .filter(|h| h.name.eq_ignore_ascii_case(name))
+                .map(|h| h.value.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(value("if-none-match"), vec!["\"v2\""]);
+        assert_eq!(value("if-modified-since"), vec!["Wed, 14 Oct 2026 10:00:00 GMT"]);
+        assert_eq!(value("user-agent"), vec!["test-agent"]);
+
+        let document = DocumentResponse::from_headers(
+            200,
+            &serde_json::json!({"ETag": "\"v2\"", "Last-Modified": " ", "Content-Type": "text/html"}),
+        );
+        assert_eq!(document.validators.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(document.validators.last_modified, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_conditional_navigation_against_etag_fixture() {
+        use axum::{http::{header, HeaderMap, StatusCode}, response::IntoResponse, routing::get, Router};
+
+        let app = Router::new().route(
+            "/article",
+            get(|headers: HeaderMap| async move {
+                if headers.get(header::IF_NONE_MATCH).is_some_and(|v| v == "\"v1\"") {
+                    return StatusCode::NOT_MODIFIED.into_response();
+                }
+                (
+                    [(header::ETAG, "\"v1\"")],
+                    axum::response::Html("<html><body><p>Article body</p></body></html>"),
+                )
+                    .into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+        let mut options = CrawlerOptions::new(format!("http://{}/article", addr));
+        let progress = ProgressReporter::disabled();
+
+        let page = pool.get_page(&options).await.unwrap();
+        let navigation = pool.navigate_following_refresh(&page, &options, &progress).await.unwrap();
+        assert!(navigation.html.contains("Article body"));
+        assert_eq!(navigation.validators.etag.as_deref(), Some("\"v1\""));
+        drop(page);
+
+        options.conditional = Some(navigation.validators);
+        let page = pool.get_page(&options).await.unwrap();
+        let err = pool.navigate_following_refresh(&page, &options, &progress).await.err().unwrap();
+        assert!(matches!(err, AppError::NotModified(_)), "expected not modified, got {}", err);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_validators_are_dropped_after_a_meta_refresh() {
+        use axum::{http::header, routing::get, Router};
+
+        let app = Router::new()
+            .route(
+                "/old",
+                get(|| async {
+                    axum::response::Html(r#"<html><head><meta http-equiv="refresh" content="0; url=/new"></head></html>"#)
+                }),
+            )
+            .route(
+                "/new",
+                get(|| async {
+                    ([(header::ETAG, "\"new\"")], axum::response::Html("<html><body><p>Moved here</p></body></html>"))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let mut options = CrawlerOptions::new(format!("http://{}/old", addr));
+        options.follow_meta_refresh = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let navigation = pool
+            .navigate_following_refresh(&page, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        assert!(navigation.html.contains("Moved here"));
+        assert_eq!(navigation.redirect_chain.len(), 2);
+        assert!(navigation.validators.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_document_source_keeps_the_served_charset() {
+        let page = "<html><head><meta charset=\"Shift_JIS\"></head><body><p>東京で桜が咲きました。</p></body></html>";
+        let (served, _, _) = encoding_rs::SHIFT_JIS.encode(page);
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&served);
+        let converter = crate::services::ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.jp/".to_string());
+        options.respond_with = ResponseFormat::Html;
+
+        let sources = [
+            DocumentSource::from_response_body(&encoded, true, Some("text/html; charset=Shift_JIS")).unwrap(),
+            DocumentSource::from_response_body(&encoded, true, None).unwrap(),
+            // Chrome decoded this one already; the meta tag no longer applies.
+            DocumentSource::from_response_body(page, false, Some("text/html; charset=Shift_JIS")).unwrap(),
+        ];
+        for source in sources {
+            let response = converter
+                .process_bytes(&source.body, source.content_type.as_deref(), &options, &mut Vec::new())
+                .await
+                .unwrap();
+            assert!(response.content.contains("東京で桜が咲きました。"), "{}", response.content);
+        }
+    }
+
+    #[test]
+    fn test_retry_wait_is_bounded_by_the_request_timeout() {
+        let now = tokio::time::Instant::now();
+        let until = Some(now + Duration::from_secs(10));
+
+        assert_eq!(BrowserPool::retry_wait(3, 5, until, now), Some(Duration::from_secs(3)));
+        assert_eq!(BrowserPool::retry_wait(6, 5, until, now), None);
+        assert_eq!(BrowserPool::retry_wait(3, 0, until, now), None);
+        assert_eq!(BrowserPool::retry_wait(3, 5, None, now), None);
+        // Only 2s of the timeout are left, so waiting 3s would overrun it.
+        assert_eq!(BrowserPool::retry_wait(3, 5, until, now + Duration::from_secs(8)), None);
+    }
+
+    #[test]
+    fn test_retry_after_is_kept_for_refusals() {
+        let now = chrono::DateTime::parse_from_rfc2822("Thu, 15 Oct 2026 12:00:00 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("120", now), Some(120));
+        assert_eq!(parse_retry_after("Thu, 15 Oct 2026 12:00:30 GMT", now), Some(30));
+        assert_eq!(parse_retry_after("Thu, 15 Oct 2026 11:00:00 GMT", now), Some(0));
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        let refused = |status| DocumentResponse::from_headers(status, &serde_json::json!({"Retry-After": "3"}));
+        assert_eq!(refused(429).refused_for(), Some(3));
+        assert_eq!(refused(503).refused_for(), Some(3));
+        assert_eq!(refused(200).refused_for(), None);
+        assert_eq!(DocumentResponse::from_headers(429, &serde_json::json!({})).refused_for(), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_origin_retry_after_is_waited_out_or_passed_on() {
+        use axum::{extract::State, http::{header, StatusCode}, response::IntoResponse, routing::get, Router};
+        use std::sync::atomic::AtomicUsize;
+
+        // The first request to /busy is refused for a second, later ones
+        // succeed; /closed always asks for an hour. /stall refuses for a
+        // second and then hangs on the retry.
+        let hits = Arc::new(AtomicUsize::new(0));
+        let stall_hits = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/busy",
+                get(|State(hits): State<Arc<AtomicUsize>>| async move {
+                    if hits.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "1")], "slow down")
+                            .into_response();
+                    }
+                    axum::response::Html("<html><body><p>Open again</p></body></html>").into_response()
+                }),
+            )
+            .route(
+                "/closed",
+                get(|| async { (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "3600")], "go away") }),
+            )
+            .route(
+                "/stall",
+                get({
+                    let stall_hits = stall_hits.clone();
+                    move || async move {
+                        if stall_hits.fetch_add(1, Ordering::SeqCst) == 0 {
+                            return (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "1")], "slow down")
+                                .into_response();
+                        }
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        axum::response::Html("<html><body>late</body></html>").into_response()
+                    }
+                }),
+            )
+            .with_state(hits.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let progress = ProgressReporter::disabled();
+
+        let options = CrawlerOptions::new(format!("http://{}/busy", addr));
+        let page = pool.get_page(&options).await.unwrap();
+        let navigation = pool.navigate_following_refresh(&page, &options, &progress).await.unwrap();
+        assert!(navigation.html.contains("Open again"));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        drop(page);
+
+        let options = CrawlerOptions::new(format!("http://{}/closed", addr));
+        let page = pool.get_page(&options).await.unwrap();
+        let err = pool.navigate_following_refresh(&page, &options, &progress).await.err().unwrap();
+        let AppError::RateLimitExceeded { domain, retry_after } = err else {
+            panic!("expected rate limited, got {}", err);
+        };
+        assert_eq!(domain, "127.0.0.1");
+        assert_eq!(retry_after, Some(3600));
+
+        // The retry shares the request timeout with the first attempt and
+        // the wait, rather than starting a fresh one.
+        let mut options = CrawlerOptions::new(format!("http://{}/stall", addr));
+        options.timeout = Some(3);
+        let page = pool.get_page(&options).await.unwrap();
+        let started = std::time::Instant::now();
+        let err = pool.navigate_following_refresh(&page, &options, &progress).await.err().unwrap();
+        assert!(matches!(err, AppError::Timeout(_)), "expected a timeout, got {}", err);
+        assert!(started.elapsed() < Duration::from_secs(4), "took {:?}", started.elapsed());
+        assert_eq!(stall_hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_html_source_is_the_document_before_scripts_ran() {
+        use axum::{routing::get, Router};
+
+        let app = Router::new().route(
+            "/page",
+            get(|| async {
+                axum::response::Html(
+                    "<html><body><p id=\"status\">sent by the server</p>\
+                     <script>document.getElementById('status').textContent = 'rewritten by script';</script>\
+                     </body></html>",
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let progress = ProgressReporter::disabled();
+        let mut options = CrawlerOptions::new(format!("http://{}/page", addr));
+        options.respond_with = ResponseFormat::Html;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let rendered = pool.navigate_following_refresh(&page, &options, &progress).await.unwrap();
+        assert!(rendered.html.contains("rewritten by script"));
+        assert!(rendered.source.is_none());
+        drop(page);
+
+        options.html_source = true;
+        let page = pool.get_page(&options).await.unwrap();
+        let navigation = pool.navigate_following_refresh(&page, &options, &progress).await.unwrap();
+        let source = navigation.source.unwrap();
+        let source = String::from_utf8(source.body).unwrap();
+        assert!(source.contains("<p id=\"status\">sent by the server</p>"), "{}", source);
+        assert!(navigation.html.contains("rewritten by script"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_no_settle_returns_at_dom_content_loaded() {
+        use axum::{http::header, routing::get, Router};
+
+        // The load event waits for an image that takes five seconds.
+        let app = Router::new()
+            .route(
+                "/article",
+                get(|| async {
+                    axum::response::Html("<html><body><p>Ready early</p><img src=\"/slow.png\"></body></html>")
+                }),
+            )
+            .route(
+                "/slow.png",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    ([(header::CONTENT_TYPE, "image/png")], Vec::<u8>::new())
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let url = format!("http://{}/article", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        options.no_settle = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let started = std::time::Instant::now();
+        let content = pool
+            .navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(3), "returned after {:?}", started.elapsed());
+        assert!(content.contains("Ready early"), "{}", content);
+        let state: String = page.evaluate("document.readyState").await.unwrap().into_value().unwrap();
+        assert_eq!(state, "interactive");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_screenshot_waits_for_late_image() {
+        use axum::{http::header, routing::get, Router};
+
+        // The image is added after the load event, so navigation alone does
+        // not wait for it.
+        let app = Router::new()
+            .route(
+                "/gallery",
+                get(|| async {
+                    axum::response::Html(
+                        "<html><body><script>window.addEventListener('load', () => {\
+                         const img = document.createElement('img'); img.src = '/slow.png';\
+                         document.body.appendChild(img); });</script></body></html>",
+                    )
+                }),
+            )
+            .route(
+                "/slow.png",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(1500)).await;
+                    ([(header::CONTENT_TYPE, "image/png")], Vec::<u8>::new())
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+        let url = format!("http://{}/gallery", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        // Wait for the load event, which adds the image, but don't settle.
+        options.settle_ms = Some(0);
+        options.screenshot_wait_assets = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        pool.navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        let started = std::time::Instant::now();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(30);
+        pool.take_screenshot(&page, &options, false, deadline).await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(1000), "captured after {:?}", started.elapsed());
+        let complete: bool = page
+            .evaluate("document.images.length === 1 && document.images[0].complete")
+            .await
+            .unwrap()
+            .into_value()
+            .unwrap();
+        assert!(complete);
+    }
+
+    #[test]
+    fn test_tall_pages_are_capped_at_max_height() {
+        assert!(BrowserPool::capped_page_clip(1920.0, 16384.0, 1.0, 16384).is_none());
+
+        let clip = BrowserPool::capped_page_clip(1280.0, 50000.0, 1.0, 16384).unwrap();
+        assert_eq!((clip.x, clip.y, clip.width, clip.height), (0.0, 0.0, 1280.0, 16384.0));
+        assert_eq!(clip.scale, 1.0);
+
+        // At 3x a 16384px-tall capture covers 5461 CSS pixels.
+        assert!(BrowserPool::capped_page_clip(390.0, 5461.0, 3.0, 16384).is_none());
+        let clip = BrowserPool::capped_page_clip(390.0, 16384.0, 3.0, 16384).unwrap();
+        assert_eq!(clip.height, 5461.0);
+        assert!(clip.height * 3.0 <= 16384.0);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_full_page_screenshot_of_tall_page_is_capped() {
+        use axum::{routing::get, Router};
+
+        let app = Router::new().route(
+            "/tall",
+            get(|| async {
+                axum::response::Html(r#"<html><body style="margin:0"><div style="height:30000px">Tall</div></body></html>"#)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            screenshot_max_height: 4000,
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let url = format!("http://{}/tall", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        options.no_settle = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        pool.navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(30);
+        let screenshot = pool.take_screenshot(&page, &options, true, deadline).await.unwrap();
+
+        // PNG IHDR: width and height are big-endian u32s at bytes 16..24.
+        let height = u32::from_be_bytes(screenshot.data[20..24].try_into().unwrap());
+        assert_eq!(height, 4000);
+        assert!(screenshot.warning.unwrap().contains("SCREENSHOT_MAX_HEIGHT"));
+    }
+
+    #[test]
+    fn test_device_metrics_follow_preset() {
+        let pixel = Config::default().device_preset("pixel-7").unwrap();
+        let params = BrowserPool::device_metrics(&pixel);
+        assert_eq!((params.width, params.height), (412, 915));
+        assert_eq!((params.screen_width, params.screen_height), (Some(412), Some(915)));
+        assert_eq!(params.device_scale_factor, 2.625);
+        assert!(params.mobile);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_device_preset_sets_user_agent_and_viewport() {
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let mut options = CrawlerOptions::new("about:blank".to_string());
+        options.device = Some("iphone-13".to_string());
+
+        let page = pool.get_page(&options).await.unwrap();
+        let seen: serde_json::Value = page
+            .evaluate("({ ua: navigator.userAgent, width: window.innerWidth, height: window.innerHeight, dpr: window.devicePixelRatio })")
+            .await
+            .unwrap()
+            .into_value()
+            .unwrap();
+
+        let iphone = Config::default().device_preset("iphone-13").unwrap();
+        assert_eq!(seen["ua"], iphone.user_agent.as_str());
+        assert_eq!(seen["width"], 390);
+        assert_eq!(seen["height"], 844);
+        assert_eq!(seen["dpr"], 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_post_navigation_against_echo_fixture() {
+        use axum::{routing::post, Router};
+
+        let app = Router::new().route(
+            "/echo",
+            post(|body: String| async move {
+                axum::response::Html(format!("<html><body><pre id=\"echo\">{}</pre></body></html>", body))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+        let url = format!("http://{}/echo", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        options.post_body = Some(PostBody {
+            body: "q=rust".to_string(),
+            content_type: "application/x-www-form-urlencoded".to_string(),
+        });
+
+        let page = pool.get_page(&options).await.unwrap();
+        let html = pool
+            .navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+
+        assert!(html.contains("q=rust"), "echo fixture did not receive the body: {}", html);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_strip_hidden_removes_computed_hidden_elements() {
+        use axum::{routing::get, Router};
+
+        let app = Router::new().route(
+            "/hidden",
+            get(|| async {
+                axum::response::Html(
+                    "<html><head><style>.gone { display: none }</style></head><body>\
+                     <p>Visible text</p><div class=\"gone\"><p>Hidden by stylesheet</p></div>\
+                     <p hidden>Hidden by attribute</p>\
+                     <div style=\"visibility: hidden\">Invisible wrapper text\
+                     <p style=\"visibility: visible\">Revealed child</p><p>Invisible sibling</p></div></body></html>",
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let pool = BrowserPool::new(Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+        let url = format!("http://{}/hidden", addr);
+        let mut options = CrawlerOptions::new(url.clone());
+        options.strip_hidden = true;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let html = pool
+            .navigate_and_wait(&page, &url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+
+        assert!(html.contains("Visible text"));
+        assert!(!html.contains("Hidden by stylesheet"), "{}", html);
+        assert!(!html.contains("Hidden by attribute"), "{}", html);
+        assert!(html.contains("Revealed child"), "{}", html);
+        assert!(!html.contains("Invisible wrapper text") && !html.contains("Invisible sibling"), "{}", html);
+    }
+
+    #[test]
+    fn test_stealth_script_patches_automation_tells() {
+        for tell in ["'webdriver'", "'plugins'", "window.chrome"] {
+            assert!(STEALTH_SCRIPT.contains(tell), "stealth script does not patch {}", tell);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_stealth_script_is_registered_on_page() {
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+        let url = "data:text/html,<p>stealth</p>";
+
+        let mut options = CrawlerOptions::new(url.to_string());
+        options.stealth = Some(true);
+        let page = pool.get_page(&options).await.unwrap();
+        page.goto(url).await.unwrap();
+
+        let webdriver: Option<bool> = page
+            .evaluate("navigator.webdriver")
+            .await
+            .unwrap()
+            .into_value()
+            .ok();
+        assert_ne!(webdriver, Some(true));
+
+        let has_chrome: bool = page
+            .evaluate("typeof window.chrome === 'object'")
+            .await
+            .unwrap()
+            .into_value()
+            .unwrap();
+        assert!(has_chrome);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_wait_for_challenge_until_content_swaps() {
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            request_timeout: 10,
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+
+        // The interstitial replaces itself with the article after 1.5s.
+        let url = "data:text/html,<title>Just a moment...</title>\
+            <div id='cf-browser-verification'>Checking your browser</div>\
+            <script>setTimeout(() => { document.title = 'Article'; \
+            document.body.innerHTML = '<p>Real content</p>'; }, 1500)</script>";
+
+        let mut options = CrawlerOptions::new(url.to_string());
+        options.wait_for_challenge = true;
+        let page = pool.get_page(&options).await.unwrap();
+        let html = pool
+            .navigate_and_wait(&page, url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        assert!(html.contains("Real content"));
+
+        let stuck = "data:text/html,<title>Just a moment...</title><p>Checking</p>";
+        let mut options = CrawlerOptions::new(stuck.to_string());
+        options.wait_for_challenge = true;
+        options.timeout = Some(2);
+        let page = pool.get_page(&options).await.unwrap();
+        let result = pool
+            .navigate_and_wait(&page, stuck, &options, &ProgressReporter::disabled())
+            .await;
+        assert!(matches!(result, Err(AppError::ChallengeNotCleared(_))));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_wait_for_selectors_any_and_all() {
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            request_timeout: 5,
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+
+        // #fast appears after 200ms, #slow never does.
+        let url = "data:text/html,<script>setTimeout(() => { \
+            const el = document.createElement('div'); el.id = 'fast'; \
+            document.body.appendChild(el); }, 200)</script>";
+
+        let mut options = CrawlerOptions::new(url.to_string());
+        options.wait_for_selectors = vec!["#slow".to_string(), "#fast".to_string()];
+        options.wait_mode = WaitMode::Any;
+
+        let page = pool.get_page(&options).await.unwrap();
+        let html = pool
+            .navigate_and_wait(&page, url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        assert!(html.contains("id=\"fast\""));
+
+        options.wait_mode = WaitMode::All;
+        let page = pool.get_page(&options).await.unwrap();
+        let result = pool
+            .navigate_and_wait(&page, url, &options, &ProgressReporter::disabled())
+            .await;
+        assert!(matches!(result, Err(AppError::Timeout(5))));
+    }
+
+    fn cdp(method: &str, params: serde_json::Value) -> CdpCommand {
+        CdpCommand { method: method.to_string(), params }
+    }
+
+    #[tokio::test]
+    async fn test_cdp_passthrough_validation() {
+        let pool = BrowserPool::new(Config {
+            browser_lazy_init: true,
+            allow_cdp_passthrough: true,
+            ..Config::default()
+        })
+        .await
+        .unwrap();
+
+        let throttle = cdp("Emulation.setCPUThrottlingRate", serde_json::json!({"rate": 4}));
+        assert!(pool.validate_cdp_commands(std::slice::from_ref(&throttle)).is_ok());
+
+        for rejected in [
+            cdp("Runtime.evaluate", serde_json::json!({"expression": "1"})),
+            cdp("Page.navigate", serde_json::json!({"url": "http://10.0.0.1/"})),
+            cdp("Emulation.setCPUThrottlingRate", serde_json::json!([4])),
+        ] {
+            let result = pool.validate_cdp_commands(&[rejected]);
+            assert!(matches!(result, Err(AppError::InvalidOption(_))));
+        }
+
+        let disabled = BrowserPool::new(Config { browser_lazy_init: true, ..Config::default() })
+            .await
+            .unwrap();
+        assert!(matches!(
+            disabled.validate_cdp_commands(&[throttle]),
+            Err(AppError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_string_cookies_use_page_domain() {
+        let cookies = BrowserPool::parse_cookies("a=1; b = 2; broken", "https://shop.example.com/cart");
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!((cookies[1].name.as_str(), cookies[1].value.as_str()), ("b", "2"));
+        assert!(cookies.iter().all(|c| c.domain.as_deref() == Some("shop.example.com")));
+    }
+
+    #[test]
+    fn test_json_cookies_map_all_attributes() {
+        let input = vec![
+            CookieInput {
+                name: "session".to_string(),
+                value: "abc".to_string(),
+                domain: Some(".example.com".to_string()),
+                path: Some("/app".to_string()),
+                secure: Some(true),
+                http_only: Some(true),
+                same_site: Some("None".to_string()),
+                expires: Some(1_900_000_000.0),
+            },
+            CookieInput {
+                name: "theme".to_string(),
+                value: "dark".to_string(),
+                domain: None,
+                path: None,
+                secure: None,
+                http_only: None,
+                same_site: None,
+                expires: None,
+            },
+        ];
+
+        let cookies = BrowserPool::json_cookies(&input, "https://shop.example.com/");
+
+        let session = &cookies[0];
+        assert_eq!(session.domain.as_deref(), Some(".example.com"));
+        assert_eq!(session.path.as_deref(), Some("/app"));
+        assert_eq!((session.secure, session.http_only), (Some(true), Some(true)));
+        assert_eq!(session.same_site, Some(CookieSameSite::None));
+        assert_eq!(session.expires, Some(TimeSinceEpoch::new(1_900_000_000.0)));
+
+        let theme = &cookies[1];
+        assert_eq!(theme.domain.as_deref(), Some("shop.example.com"));
+        assert!(theme.path.is_none() && theme.same_site.is_none() && theme.expires.is_none());
+    }
+
+    #[test]
+    fn test_raw_cdp_command_serializes_params() {
+        let command = cdp("Emulation.setCPUThrottlingRate", serde_json::json!({"rate": 4}));
+        let raw = RawCdpCommand(&command);
+
+        assert_eq!(chromiumoxide::Method::identifier(&raw), "Emulation.setCPUThrottlingRate");
+        assert_eq!(serde_json::to_value(&raw).unwrap(), serde_json::json!({"rate": 4}));
+
+        let bare = cdp("Emulation.clearDeviceMetricsOverride", serde_json::Value::Null);
+        assert_eq!(serde_json::to_value(RawCdpCommand(&bare)).unwrap(), serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium (set CHROME_PATH)"]
+    async fn test_cdp_passthrough_executes_benign_command() {
+        let config = Config {
+            chrome_path: std::env::var("CHROME_PATH").unwrap_or_else(|_| Config::default().chrome_path),
+            allow_cdp_passthrough: true,
+            ..Config::default()
+        };
+        let pool = BrowserPool::new(config).await.unwrap();
+        let url = "data:text/html,<p>throttled</p>";
+
+        let mut options = CrawlerOptions::new(url.to_string());
+        options.cdp_commands = vec![cdp("Emulation.setCPUThrottlingRate", serde_json::json!({"rate": 2}))];
+
+        let page = pool.get_page(&options).await.unwrap();
+        let html = pool
+            .navigate_and_wait(&page, url, &options, &ProgressReporter::disabled())
+            .await
+            .unwrap();
+        assert!(html.contains("throttled"));
+    }
 }
@@ -1,41 +1,121 @@
-use crate::models::LoadResponse;
+use crate::models::{CacheStatsResponse, LoadResponse, OriginValidators};
 use dashmap::DashMap;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use tracing::debug;
+use tracing::{debug, warn};
 
 struct CacheEntry {
-    response: LoadResponse,
+    response: StoredResponse,
     created_at: Instant,
     ttl: Duration,
 }
 
+enum StoredResponse {
+    Plain(Box<LoadResponse>),
+    /// Gzipped JSON of the response. `origin_validators` is not serialized,
+    /// so it is kept beside it.
+    Gzip {
+        body: Vec<u8>,
+        origin_validators: Option<OriginValidators>,
+    },
+}
+
+impl StoredResponse {
+    /// Compresses `response` when `compress` is set, falling back to
+    /// storing it as is if that fails.
+    fn new(response: LoadResponse, compress: bool) -> Self {
+        if !compress {
+            return Self::Plain(Box::new(response));
+        }
+        match gzip_json(&response) {
+            Ok(body) => Self::Gzip { body, origin_validators: response.origin_validators },
+            Err(e) => {
+                warn!("Failed to compress cache entry for {}: {}", response.url, e);
+                Self::Plain(Box::new(response))
+            }
+        }
+    }
+
+    fn response(&self) -> Option<LoadResponse> {
+        match self {
+            Self::Plain(response) => Some(LoadResponse::clone(response)),
+            Self::Gzip { body, origin_validators } => match gunzip_json(body) {
+                Ok(mut response) => {
+                    response.origin_validators = origin_validators.clone();
+                    Some(response)
+                }
+                Err(e) => {
+                    warn!("Failed to decompress cache entry: {}", e);
+                    None
+                }
+            },
+        }
+    }
+}
+
+fn gzip_json(response: &LoadResponse) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    serde_json::to_writer(&mut encoder, response)?;
+    encoder.flush()?;
+    encoder.finish()
+}
+
+fn gunzip_json(body: &[u8]) -> std::io::Result<LoadResponse> {
+    let mut json = Vec::new();
+    GzDecoder::new(body).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
 pub struct CacheService {
     cache: DashMap<String, CacheEntry>,
     default_ttl: Duration,
+    compress: bool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
 impl CacheService {
     pub fn new(default_ttl_secs: u64) -> Self {
+        Self::with_compression(default_ttl_secs, false)
+    }
+
+    /// With `compress`, entries are kept as gzipped JSON and decoded on
+    /// every read, trading CPU for memory (`CACHE_COMPRESS`).
+    pub fn with_compression(default_ttl_secs: u64, compress: bool) -> Self {
         Self {
             cache: DashMap::new(),
             default_ttl: Duration::from_secs(default_ttl_secs),
+            compress,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
     pub fn get(&self, key: &str) -> Option<LoadResponse> {
         if let Some(entry) = self.cache.get(key) {
             if entry.created_at.elapsed() < entry.ttl {
-                debug!("Cache hit for {}", key);
-                let mut response = entry.response.clone();
-                response.metadata.cached = true;
-                return Some(response);
+                if let Some(mut response) = entry.response.response() {
+                    debug!("Cache hit for {}", key);
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    response.metadata.cached = true;
+                    return Some(response);
+                }
             } else {
                 debug!("Cache expired for {}", key);
                 drop(entry);
-                self.cache.remove(key);
+                if self.cache.remove(key).is_some() {
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
             }
         }
         debug!("Cache miss for {}", key);
+        self.misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
@@ -46,15 +126,24 @@ impl CacheService {
                 .unwrap_or(entry.ttl);
 
             if entry.created_at.elapsed() < max_age {
-                debug!("Cache hit for {} (tolerance: {:?})", key, tolerance_secs);
-                let mut response = entry.response.clone();
-                response.metadata.cached = true;
-                return Some(response);
+                if let Some(mut response) = entry.response.response() {
+                    debug!("Cache hit for {} (tolerance: {:?})", key, tolerance_secs);
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    response.metadata.cached = true;
+                    return Some(response);
+                }
             }
         }
+        self.misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
+    /// The stored response for `key` even if it has expired, without
+    /// touching hit or miss counters.
+    pub fn peek(&self, key: &str) -> Option<LoadResponse> {
+        self.cache.get(key).and_then(|entry| entry.response.response())
+    }
+
     pub fn set(&self, key: String, response: LoadResponse, ttl_secs: Option<u64>) {
         let ttl = ttl_secs
             .map(Duration::from_secs)
@@ -63,7 +152,7 @@ impl CacheService {
         debug!("Caching response for {} (TTL: {:?})", key, ttl);
 
         self.cache.insert(key, CacheEntry {
-            response,
+            response: StoredResponse::new(response, self.compress),
             created_at: Instant::now(),
             ttl,
         });
@@ -73,8 +162,19 @@ impl CacheService {
         self.cache.remove(key);
     }
 
-    pub fn clear(&self) {
+    /// Remove every cached variant (format, options) of a URL. Only keys
+    /// whose URL part is exactly `url` match, so `https://a.com` leaves
+    /// `https://a.com:8080` alone.
+    pub fn invalidate_url(&self, url: &str) -> usize {
+        let before = self.cache.len();
+        self.cache.retain(|key, _| key.rsplit_once(':').map(|(u, _)| u) != Some(url));
+        before.saturating_sub(self.cache.len())
+    }
+
+    pub fn clear(&self) -> usize {
+        let removed = self.cache.len();
         self.cache.clear();
+        removed
     }
 
     pub fn cleanup_expired(&self) -> usize {
@@ -87,6 +187,7 @@ impl CacheService {
             keep
         });
         debug!("Cache cleanup: removed {} expired entries", removed);
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
         removed
     }
 
@@ -94,9 +195,24 @@ impl CacheService {
         self.cache.len()
     }
 
+    pub fn stats(&self) -> CacheStatsResponse {
+        CacheStatsResponse {
+            size: self.size(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
     pub fn generate_cache_key(url: &str, options_hash: u64) -> String {
         format!("{}:{}", url, options_hash)
     }
+
+    /// Stands in for the URL part of the key when a client names the entry
+    /// with `x-cache-key`, so `invalidate_url` can drop it under this name.
+    pub fn client_key(key: &str) -> String {
+        format!("key:{}", key)
+    }
 }
 
 impl Default for CacheService {
@@ -104,3 +220,102 @@ impl Default for CacheService {
         Self::new(3600)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_response;
+
+    #[test]
+    fn test_stats_count_hits_and_misses() {
+        let cache = CacheService::new(60);
+        cache.set("https://a.com:Markdown".to_string(), test_response("https://a.com", "content"), None);
+
+        assert!(cache.get_with_tolerance("https://a.com:Markdown", None).is_some());
+        assert!(cache.get("https://a.com:Markdown").is_some());
+        assert!(cache.get("https://b.com:Markdown").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.size, 1);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.evictions, 0);
+    }
+
+    #[test]
+    fn test_expired_entries_count_as_evictions() {
+        let cache = CacheService::new(60);
+        cache.set("https://a.com:Markdown".to_string(), test_response("https://a.com", "content"), Some(0));
+        cache.set("https://b.com:Markdown".to_string(), test_response("https://b.com", "content"), Some(0));
+
+        assert!(cache.get("https://a.com:Markdown").is_none());
+        assert_eq!(cache.cleanup_expired(), 1);
+
+        let stats = cache.stats();
+        assert_eq!(stats.size, 0);
+        assert_eq!(stats.evictions, 2);
+    }
+
+    #[test]
+    fn test_compressed_entries_round_trip() {
+        let cache = CacheService::with_compression(60, true);
+        let mut stored = test_response("https://a.com", "content");
+        stored.content = "# Heading\n\nSome markdown that repeats. ".repeat(2000);
+        stored.title = Some("Title".to_string());
+        stored.warnings = vec!["warning".to_string()];
+        stored.origin_validators = Some(OriginValidators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        });
+        cache.set("https://a.com:Markdown".to_string(), stored.clone(), None);
+
+        let entry = cache.cache.get("https://a.com:Markdown").unwrap();
+        let StoredResponse::Gzip { body, .. } = &entry.response else {
+            panic!("entry was not compressed");
+        };
+        assert!(body.len() * 10 < stored.content.len(), "{} bytes", body.len());
+        drop(entry);
+
+        let cached = cache.get("https://a.com:Markdown").unwrap();
+        assert!(cached.metadata.cached);
+        let mut expected = serde_json::to_value(&stored).unwrap();
+        expected["metadata"]["cached"] = true.into();
+        assert_eq!(serde_json::to_value(&cached).unwrap(), expected);
+        assert_eq!(cached.origin_validators, stored.origin_validators);
+        assert_eq!(cache.peek("https://a.com:Markdown").unwrap().content, stored.content);
+    }
+
+    #[test]
+    fn test_invalidate_url_removes_all_format_variants() {
+        let cache = CacheService::new(60);
+        cache.set("https://a.com:Markdown".to_string(), test_response("https://a.com", "content"), None);
+        cache.set("https://a.com:Html".to_string(), test_response("https://a.com", "content"), None);
+        cache.set("https://a.com/page:Markdown".to_string(), test_response("https://a.com/page", "content"), None);
+
+        assert_eq!(cache.invalidate_url("https://a.com"), 2);
+        assert_eq!(cache.size(), 1);
+        assert!(cache.get("https://a.com/page:Markdown").is_some());
+
+        assert_eq!(cache.clear(), 1);
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_invalidate_url_leaves_other_ports_and_keys() {
+        let cache = CacheService::new(60);
+        cache.set(CacheService::generate_cache_key("https://a.com", 1), test_response("https://a.com", "content"), None);
+        cache.set(CacheService::generate_cache_key("https://a.com:8080/x", 1), test_response("https://a.com:8080/x", "content"), None);
+        cache.set(CacheService::generate_cache_key("https://a.com:8080", 2), test_response("https://a.com:8080", "content"), None);
+        let docs = CacheService::client_key("docs");
+        let docs_intro = CacheService::client_key("docs:intro");
+        cache.set(CacheService::generate_cache_key(&docs, 1), test_response("https://a.com", "content"), None);
+        cache.set(CacheService::generate_cache_key(&docs_intro, 1), test_response("https://a.com", "content"), None);
+
+        assert_eq!(cache.invalidate_url("https://a.com"), 1);
+        assert_eq!(cache.size(), 4);
+        assert_eq!(cache.invalidate_url(&docs), 1);
+        assert!(cache.peek(&CacheService::generate_cache_key(&docs_intro, 1)).is_some());
+        assert_eq!(cache.invalidate_url("https://a.com:8080"), 1);
+        assert!(cache.peek(&CacheService::generate_cache_key("https://a.com:8080/x", 1)).is_some());
+    }
+}
@@ -0,0 +1,78 @@
+use encoding_rs::{Encoding, UTF_8};
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+
+lazy_static! {
+    static ref META_CHARSET: Regex = Regex::new(
+        r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_\-:.]+)"#
+    ).unwrap();
+}
+
+/// How far into the document `<meta charset>` is looked for, matching the
+/// HTML prescan window.
+const PRESCAN_BYTES: usize = 1024;
+
+/// The encoding declared for an HTML body: a byte order mark wins, then the
+/// `charset` parameter of `Content-Type`, then `<meta charset>` or
+/// `<meta http-equiv="Content-Type">` near the top of the document.
+pub fn detect_charset(bytes: &[u8], content_type: Option<&str>) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+
+    let from_header = content_type.and_then(|value| {
+        value.split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+            .and_then(|(_, label)| Encoding::for_label(label.trim().trim_matches('"').as_bytes()))
+    });
+    if from_header.is_some() {
+        return from_header;
+    }
+
+    let head = &bytes[..bytes.len().min(PRESCAN_BYTES)];
+    META_CHARSET.captures(head)
+        .and_then(|caps| caps.get(1))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+}
+
+/// Decode an HTML body to UTF-8 using its declared charset, falling back to
+/// lossy UTF-8 when none is declared or the label is unknown.
+pub fn decode_html(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = detect_charset(bytes, content_type).unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, SHIFT_JIS};
+
+    #[test]
+    fn test_charset_sources_in_priority_order() {
+        let page = br#"<html><head><meta charset="shift_jis"></head></html>"#;
+        assert_eq!(detect_charset(page, None), Some(SHIFT_JIS));
+        assert_eq!(detect_charset(page, Some("text/html; charset=GBK")), Some(GBK));
+        assert_eq!(detect_charset(b"\xEF\xBB\xBF<html>", Some("text/html; charset=gbk")), Some(UTF_8));
+
+        let http_equiv = br#"<meta http-equiv="Content-Type" content="text/html; charset=gb2312">"#;
+        assert_eq!(detect_charset(http_equiv, None), Some(GBK));
+        assert_eq!(detect_charset(b"<html></html>", Some("text/html")), None);
+    }
+
+    #[test]
+    fn test_decode_shift_jis_page() {
+        let (bytes, _, _) = SHIFT_JIS.encode("<html><head><meta charset=\"Shift_JIS\"></head><body>日本語</body></html>");
+        assert!(String::from_utf8(bytes.to_vec()).is_err());
+
+        assert!(decode_html(&bytes, None).contains("日本語"));
+    }
+
+    #[test]
+    fn test_unknown_charset_falls_back_to_lossy_utf8() {
+        let decoded = decode_html(b"<meta charset=\"x-made-up\">caf\xC3\xA9 \xFF", None);
+        assert!(decoded.contains("café"));
+        assert!(decoded.contains('\u{FFFD}'));
+    }
+}
@@ -0,0 +1,100 @@
+/// Split `content` into consecutive pieces of at most `max_chars` characters
+/// that concatenate back to the input. Each piece ends at the last paragraph
+/// or heading boundary that fits, else at the last sentence end, else at the
+/// last whitespace, and is only cut mid-word when none of those exist.
+/// Sentence ends cover both `. ` style punctuation and the CJK full stops
+/// that are not followed by a space.
+pub fn chunk_content(content: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut rest = content;
+
+    while !rest.is_empty() {
+        let limit = rest.char_indices().nth(max_chars).map_or(rest.len(), |(i, _)| i);
+        if limit == rest.len() {
+            chunks.push(rest.to_string());
+            break;
+        }
+
+        let window = &rest[..limit];
+        let cut = block_cut(window)
+            .or_else(|| sentence_cut(window))
+            .or_else(|| word_cut(window))
+            .unwrap_or(limit);
+        chunks.push(rest[..cut].to_string());
+        rest = &rest[cut..];
+    }
+
+    chunks
+}
+
+/// End of the last blank line, or the start of the last heading line.
+fn block_cut(window: &str) -> Option<usize> {
+    let blank = window.rfind("\n\n").map(|i| i + 2);
+    let heading = window.rfind("\n#").map(|i| i + 1);
+    blank.max(heading).filter(|&cut| cut > 0)
+}
+
+fn sentence_cut(window: &str) -> Option<usize> {
+    let mut cut = None;
+    let mut chars = window.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '。' | '！' | '？' => cut = Some(i + c.len_utf8()),
+            '.' | '!' | '?' => {
+                if let Some(&(j, next)) = chars.peek() {
+                    if next.is_whitespace() {
+                        cut = Some(j + next.len_utf8());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    cut.filter(|&cut| cut > 0)
+}
+
+fn word_cut(window: &str) -> Option<usize> {
+    window.char_indices()
+        .rfind(|(_, c)| c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .filter(|&cut| cut > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_chunked(content: &str, max: usize) -> Vec<String> {
+        let chunks = chunk_content(content, max);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= max, "chunk over {}: {:?}", max, chunk);
+        }
+        assert_eq!(chunks.concat(), content);
+        chunks
+    }
+
+    #[test]
+    fn test_chunks_fit_and_reassemble() {
+        let content = "# Title\n\nFirst paragraph is here. It has two sentences.\n\n## Section\nSecond paragraph, a little longer than the first one. Then another sentence follows it.\n\nShort.";
+        for max in [1, 7, 20, 40, 60, 200] {
+            assert_chunked(content, max);
+        }
+
+        let chunks = assert_chunked(content, 60);
+        assert_eq!(chunks[0], "# Title\n\nFirst paragraph is here. It has two sentences.\n\n");
+        assert!(chunks[1].starts_with("## Section\n"));
+
+        assert_eq!(assert_chunked(content, 1000), vec![content.to_string()]);
+        assert!(chunk_content("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_chunks_prefer_sentence_ends_over_words() {
+        let chunks = assert_chunked("One sentence here. Another one that runs on and on", 30);
+        assert_eq!(chunks[0], "One sentence here. ");
+
+        let chunks = assert_chunked("第一句话。第二句话比较长一些。第三句。", 10);
+        assert_eq!(chunks[0], "第一句话。");
+    }
+}
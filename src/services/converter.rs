@@ -1,54 +1,143 @@
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{AppError, Result};
 use crate::models::{
-    CrawlerOptions, LoadResponse, PageSnapshot, ResponseFormat, ResponseMetadata,
+    CrawlerOptions, ExtractedContent, ExtractionStrategy, LoadResponse, PageSnapshot, ResponseFormat,
+    ResponseMetadata,
 };
-use crate::services::{MarkdownService, ReadabilityService, ScraperService};
-use std::time::Instant;
+use crate::services::charset;
+use crate::services::chunker::chunk_content;
+use crate::services::{
+    AltTextGenerator, HttpAltTextGenerator, MarkdownService, ReadabilityService, ScraperService,
+};
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::debug;
 
-pub struct ConverterService {
-    #[allow(dead_code)]
-    config: Config,
+const ALT_TEXT_CONCURRENCY: usize = 4;
+const ALT_TEXT_DEADLINE_SECS: u64 = 15;
+
+/// Pages at least this large resolve images and links on a second blocking
+/// task while the content is converted. Below it the hand-off costs more
+/// than it saves.
+const PARALLEL_MIN_HTML_BYTES: usize = 64 * 1024;
+
+/// Extracted text shorter than this, in characters, counts as empty for the
+/// empty-content template.
+const EMPTY_TEXT_CHARS: usize = 10;
+
+/// Result of [`ConverterService::convert`].
+struct Converted {
+    snapshot: PageSnapshot,
+    content: String,
+    text: Option<String>,
+    byline: Option<String>,
+    excerpt: Option<String>,
+    warnings: Vec<String>,
+    extraction: Option<ExtractionStrategy>,
+}
+
+/// The services a conversion runs through, shared with the blocking-pool
+/// threads its CPU-bound steps run on.
+struct Pipeline {
     scraper: ScraperService,
     readability: ReadabilityService,
     markdown: MarkdownService,
 }
 
+pub struct ConverterService {
+    config: Config,
+    pipeline: Arc<Pipeline>,
+    alt_text: Option<Arc<dyn AltTextGenerator>>,
+}
+
 impl ConverterService {
     pub fn new(config: Config) -> Self {
+        let alt_text = HttpAltTextGenerator::from_config(&config)
+            .map(|g| Arc::new(g) as Arc<dyn AltTextGenerator>);
+
         Self {
+            pipeline: Arc::new(Pipeline {
+                scraper: ScraperService::new()
+                    .with_paywall_selectors(&config.paywall_selectors)
+                    .with_soft_404_phrases(&config.soft_404_phrases),
+                readability: ReadabilityService::new(),
+                markdown: MarkdownService::new(),
+            }),
             config,
-            scraper: ScraperService::new(),
-            readability: ReadabilityService::new(),
-            markdown: MarkdownService::new(),
+            alt_text,
         }
     }
 
+    pub fn with_alt_text_generator(mut self, generator: Arc<dyn AltTextGenerator>) -> Self {
+        self.alt_text = Some(generator);
+        self
+    }
+
+    /// Like [`Self::process`], for a raw response body that has not been
+    /// decoded yet, such as the `x-html-source` document. The body is
+    /// transcoded to UTF-8 from the charset declared in `content_type` or
+    /// the document itself; `page.content()` is already decoded by Chromium.
+    pub async fn process_bytes(
+        &self,
+        body: &[u8],
+        content_type: Option<&str>,
+        options: &CrawlerOptions,
+        warnings: &mut Vec<String>,
+    ) -> Result<LoadResponse> {
+        let html = charset::decode_html(body, content_type);
+        self.process(&html, options, warnings).await
+    }
+
+    /// Convert a rendered page into the response. Non-fatal issues are
+    /// appended to `warnings`.
     pub async fn process(
         &self,
         html: &str,
         options: &CrawlerOptions,
+        warnings: &mut Vec<String>,
+    ) -> Result<LoadResponse> {
+        let parallel = html.len() >= PARALLEL_MIN_HTML_BYTES;
+        self.process_with(html, options, warnings, parallel).await
+    }
+
+    /// [`Self::process`] with images and links resolved either on their own
+    /// blocking task, alongside content conversion, or after it. The page is
+    /// parsed once either way and both produce the same response.
+    async fn process_with(
+        &self,
+        html: &str,
+        options: &CrawlerOptions,
+        warnings: &mut Vec<String>,
+        parallel: bool,
     ) -> Result<LoadResponse> {
         let start = Instant::now();
 
-        let snapshot = self.scraper.parse_html(html, options)?;
+        let (snapshot, assets) = {
+            let (html, options) = (html.to_string(), options.clone());
+            self.blocking(move |p| p.scraper.parse_page(&html, &options)).await?
+        };
 
-        let content = match options.respond_with {
-            ResponseFormat::Html => {
-                snapshot.html.clone()
-            }
-            ResponseFormat::Text => {
-                let extracted = self.readability.extract_without_readability(&snapshot);
-                extracted.text_content
-            }
-            ResponseFormat::Screenshot | ResponseFormat::Pageshot => {
-                String::new()
-            }
-            ResponseFormat::Markdown | ResponseFormat::Default => {
-                self.convert_to_markdown(&snapshot)?
-            }
+        let (converted, (images, links)) = if parallel {
+            let assets = {
+                let options = options.clone();
+                self.blocking(move |p| p.scraper.resolve_assets(assets, &options))
+            };
+            let (converted, assets) = tokio::join!(self.convert(snapshot, options), assets);
+            (converted?, assets)
+        } else {
+            let converted = self.convert(snapshot, options).await?;
+            (converted, self.pipeline.scraper.resolve_assets(assets, options))
         };
+        let Converted { mut snapshot, content, text, byline, excerpt, warnings: conversion_warnings, extraction } =
+            converted;
+        snapshot.images = images;
+        snapshot.links = links;
+        warnings.extend(conversion_warnings);
+
+        if options.with_generated_alt {
+            self.fill_missing_alt(&mut snapshot, options, warnings).await;
+        }
 
         let processing_time_ms = start.elapsed().as_millis() as u64;
 
@@ -56,72 +145,900 @@ impl ConverterService {
             url: options.url.clone(),
             title: snapshot.title.clone(),
             content,
+            chunks: None,
             published_time: snapshot.published_time.clone(),
+            byline,
+            excerpt,
             images: None,
             links: None,
             screenshot_url: None,
+            text,
+            warnings: Vec::new(),
             metadata: ResponseMetadata {
                 processing_time_ms,
                 cached: false,
+                diff: None,
+                blocked: false,
+                extraction,
             },
+            og: None,
+            feeds: Vec::new(),
+            redirect_chain: Vec::new(),
+            paywalled: snapshot.paywalled,
+            soft_404: snapshot.soft_404,
+            applied_options: None,
+            origin_validators: None,
         };
 
+        if options.with_structured_data && !snapshot.open_graph.is_empty() {
+            response.og = Some(snapshot.open_graph.clone());
+        }
+
+        if options.with_feeds {
+            response.feeds = snapshot.feeds.clone();
+        }
+
+        let dropped = snapshot.images.iter().filter(|img| img.data_url_dropped).count();
+        if dropped > 0 {
+            warnings.push(format!(
+                "Dropped {} inline image(s) over x-max-data-url-bytes",
+                dropped
+            ));
+        }
+
         if options.with_images_summary {
+            let limit = options.images_summary_limit.unwrap_or(snapshot.images.len());
+            if limit < snapshot.images.len() {
+                warnings.push(format!(
+                    "Images summary truncated to {} of {}",
+                    limit,
+                    snapshot.images.len()
+                ));
+            }
             response.images = Some(
-                snapshot.images.iter().map(|img| crate::models::ImageInfo {
+                snapshot.images.iter().take(limit).map(|img| crate::models::ImageInfo {
                     src: img.src.clone(),
                     alt: img.alt.clone(),
                     width: img.width,
                     height: img.height,
+                    data_url_dropped: img.data_url_dropped,
                 }).collect()
             );
 
             if matches!(options.respond_with, ResponseFormat::Default | ResponseFormat::Markdown) {
-                response.content = self.markdown.add_images_summary(&response.content, &snapshot.images);
+                response.content = self.pipeline.markdown.add_images_summary(
+                    &response.content,
+                    &snapshot.images,
+                    options.images_summary_limit,
+                );
             }
         }
 
         if options.with_links_summary {
+            let limit = options.links_summary_limit.unwrap_or(snapshot.links.len());
+            if limit < snapshot.links.len() {
+                warnings.push(format!(
+                    "Links summary truncated to {} of {}",
+                    limit,
+                    snapshot.links.len()
+                ));
+            }
             response.links = Some(
-                snapshot.links.iter().map(|link| crate::models::LinkInfo {
+                snapshot.links.iter().take(limit).map(|link| crate::models::LinkInfo {
                     href: link.href.clone(),
                     text: link.text.clone(),
+                    rel: link.rel.clone(),
                 }).collect()
             );
 
             if matches!(options.respond_with, ResponseFormat::Default | ResponseFormat::Markdown) {
-                response.content = self.markdown.add_links_summary(&response.content, &snapshot.links);
+                response.content = self.pipeline.markdown.add_links_summary(
+                    &response.content,
+                    &snapshot.links,
+                    options.links_summary_limit,
+                );
             }
         }
 
+        // Everything above works on `\n`; the requested endings go on last.
+        response.content = options.line_endings.apply(&response.content);
+        response.text = response.text.map(|text| options.line_endings.apply(&text));
+
+        if let Some(size) = options.chunk_size {
+            response.chunks = Some(chunk_content(&response.content, size));
+        }
+
         Ok(response)
     }
 
-    fn convert_to_markdown(&self, snapshot: &PageSnapshot) -> Result<String> {
-        debug!("Using rule-based conversion");
+    /// Run a CPU-bound step on the blocking pool, so a large page does not
+    /// hold up an async worker while it converts. A panic in the step is
+    /// passed on as if the step had run here.
+    async fn blocking<T, F>(&self, step: F) -> T
+    where
+        F: FnOnce(&Pipeline) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let pipeline = self.pipeline.clone();
+        match tokio::task::spawn_blocking(move || step(&pipeline)).await {
+            Ok(value) => value,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
 
-        let cleaned_html = self.readability.clean_html(&snapshot.html);
-        let cleaned_snapshot = PageSnapshot {
-            url: snapshot.url.clone(),
-            html: cleaned_html,
-            title: snapshot.title.clone(),
-            published_time: snapshot.published_time.clone(),
-            images: snapshot.images.clone(),
-            links: snapshot.links.clone(),
-            has_pdf: snapshot.has_pdf,
+    /// The converted content for the requested format. The snapshot comes
+    /// without images and links; [`Self::process_with`] resolves those.
+    async fn convert(&self, snapshot: PageSnapshot, options: &CrawlerOptions) -> Result<Converted> {
+        let mut warnings = snapshot.warnings.clone();
+
+        if options.soft_404_strict && snapshot.soft_404 {
+            return Err(AppError::SoftNotFound(options.url.clone()));
+        }
+
+        if options.respect_meta_robots && snapshot.robots.noindex {
+            return Err(AppError::BlockedUrl(format!(
+                "{} is marked noindex by <meta name=\"robots\">",
+                options.url
+            )));
+        }
+
+        let mut text = None;
+        let mut extracted_len = None;
+        let extraction = snapshot.extraction;
+        let mut byline = snapshot.byline.clone();
+        let mut excerpt = snapshot.description.clone();
+
+        let content = match options.respond_with {
+            ResponseFormat::Html | ResponseFormat::Text => {
+                let (snapshot, options) = (snapshot.clone(), options.clone());
+                let content;
+                (content, extracted_len) = self.blocking(move |p| p.plain_content(&snapshot, &options)).await;
+                content
+            }
+            ResponseFormat::Screenshot | ResponseFormat::Pageshot => {
+                String::new()
+            }
+            ResponseFormat::Markdown | ResponseFormat::Default => {
+                if let Some(strategy) = extraction {
+                    debug!("Converting {} as {:?}", options.url, strategy);
+                }
+
+                let listing_options;
+                let options = if extraction == Some(ExtractionStrategy::Listing) {
+                    listing_options = CrawlerOptions { raw_extract: true, ..options.clone() };
+                    &listing_options
+                } else {
+                    options
+                };
+
+                let extracted = self.extract_cleaned(&snapshot, options, &mut warnings).await?;
+                extracted_len = Some(text_length(&extracted.text_content));
+                if options.with_text {
+                    text = Some(self.plain_text(&extracted, options).await);
+                }
+                byline = extracted.byline.clone();
+                excerpt = extracted.excerpt.clone();
+
+                let keep_tables = extraction == Some(ExtractionStrategy::Tables);
+                let options = options.clone();
+                self.blocking(move |p| {
+                    if keep_tables {
+                        p.markdown.convert_keeping_tables(&extracted, &options)
+                    } else {
+                        p.markdown.convert_to_markdown(&extracted, &options)
+                    }
+                })
+                .await?
+            }
         };
 
-        let extracted = self.readability.extract_content(&cleaned_snapshot)?;
-        let markdown = self.markdown.convert_to_markdown(&extracted)?;
+        if let (Some(min), Some(len)) = (options.min_content_length, extracted_len) {
+            if len < min {
+                return Err(AppError::ExtractionError(format!(
+                    "Extracted {} characters from {}, below x-min-content-length of {}",
+                    len, options.url, min
+                )));
+            }
+        }
 
-        Ok(markdown)
+        if options.with_text && text.is_none() {
+            let extracted = self.extract_cleaned(&snapshot, options, &mut warnings).await?;
+            text = Some(self.plain_text(&extracted, options).await);
+        }
+
+        let template = options.empty_template.as_deref()
+            .or(self.config.empty_content_template.as_deref());
+        let content = match (template, extracted_len) {
+            (Some(template), Some(len)) if len < EMPTY_TEXT_CHARS => {
+                debug!("Extraction of {} left {} characters, using the empty-content template", options.url, len);
+                fill_empty_template(template, &options.url, snapshot.title.as_deref())
+            }
+            _ => content,
+        };
+
+        Ok(Converted { snapshot, content, text, byline, excerpt, warnings, extraction })
+    }
+
+    /// Ask the alt text generator for images without a description. Only the
+    /// images that end up in the summary are sent; failures leave the alt
+    /// empty and add a warning.
+    async fn fill_missing_alt(
+        &self,
+        snapshot: &mut PageSnapshot,
+        options: &CrawlerOptions,
+        warnings: &mut Vec<String>,
+    ) {
+        let Some(generator) = self.alt_text.clone() else {
+            debug!("x-with-generated-alt set but ALT_TEXT_SERVICE_URL is not configured");
+            return;
+        };
+
+        let limit = options.images_summary_limit.unwrap_or(snapshot.images.len());
+        let pending: Vec<(usize, String)> = snapshot.images.iter()
+            .enumerate()
+            .take(limit)
+            .filter(|(_, img)| img.alt.as_deref().is_none_or(|alt| alt.trim().is_empty()))
+            .filter(|(_, img)| !img.data_url_dropped)
+            .map(|(i, img)| (i, img.src.clone()))
+            .collect();
+
+        if pending.is_empty() {
+            return;
+        }
+
+        let page_url = options.url.as_str();
+        let requested = pending.len();
+        let generated = tokio::time::timeout(
+            Duration::from_secs(ALT_TEXT_DEADLINE_SECS),
+            stream::iter(pending)
+                .map(|(i, src)| {
+                    let generator = generator.clone();
+                    async move { (i, generator.generate(&src, page_url).await) }
+                })
+                .buffer_unordered(ALT_TEXT_CONCURRENCY)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap_or_default();
+
+        let mut filled = 0;
+        for (i, result) in generated {
+            match result {
+                Ok(alt) if !alt.is_empty() => {
+                    snapshot.images[i].alt = Some(alt);
+                    filled += 1;
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Alt text generation failed: {}", e),
+            }
+        }
+
+        if filled < requested {
+            warnings.push(format!(
+                "Generated alt text for {} of {} images",
+                filled, requested
+            ));
+        }
+    }
+
+    /// Clean the snapshot and run readability on it. Both the markdown and
+    /// the `x-with-text` plain text are derived from this result. With
+    /// `x-raw-extract` the snapshot is passed through untouched.
+    async fn extract_cleaned(
+        &self,
+        snapshot: &PageSnapshot,
+        options: &CrawlerOptions,
+        warnings: &mut Vec<String>,
+    ) -> Result<ExtractedContent> {
+        let (snapshot, strip_hidden) = (snapshot.clone(), options.strip_hidden);
+        if options.raw_extract {
+            debug!("Raw extraction requested, skipping cleanup and readability");
+            return Ok(self.blocking(move |p| {
+                let raw_snapshot = PageSnapshot {
+                    html: p.readability.strip_non_content(&snapshot.html),
+                    ..snapshot
+                };
+                p.readability.extract_without_readability(&raw_snapshot)
+            })
+            .await);
+        }
+
+        debug!("Using rule-based conversion");
+
+        let cleaned_snapshot = self.blocking(move |p| PageSnapshot {
+            html: p.readability.clean_html(&snapshot.html, strip_hidden),
+            warnings: Vec::new(),
+            ..snapshot
+        })
+        .await;
+        let extracted = self.pipeline.readability.extract_content(&cleaned_snapshot).await?;
+
+        if let Some(ref reason) = extracted.fallback_reason {
+            warnings.push(format!("Readability extraction failed ({}); converted the raw HTML", reason));
+        }
+
+        Ok(extracted)
+    }
+
+    /// The plain text of `extracted`, cut after `x-max-paragraphs` blocks
+    /// like the markdown is.
+    async fn plain_text(&self, extracted: &ExtractedContent, options: &CrawlerOptions) -> String {
+        match options.max_paragraphs {
+            Some(max) => {
+                let html = extracted.content.clone();
+                self.blocking(move |p| p.readability.extract_text_capped(&html, max)).await
+            }
+            None => extracted.text_content.clone(),
+        }
     }
 
     pub fn get_scraper(&self) -> &ScraperService {
-        &self.scraper
+        &self.pipeline.scraper
     }
 
     pub fn get_markdown_service(&self) -> &MarkdownService {
-        &self.markdown
+        &self.pipeline.markdown
+    }
+}
+
+impl Pipeline {
+    /// Content for the `html` and `text` formats, with the length of the
+    /// page text when the format or `x-min-content-length` needs it.
+    fn plain_content(&self, snapshot: &PageSnapshot, options: &CrawlerOptions) -> (String, Option<usize>) {
+        if options.respond_with == ResponseFormat::Text {
+            let extracted = self.readability.extract_without_readability(snapshot);
+            let len = text_length(&extracted.text_content);
+            let text = match options.max_paragraphs {
+                Some(max) => self.readability.extract_text_capped(&extracted.content, max),
+                None => extracted.text_content,
+            };
+            return (text, Some(len));
+        }
+
+        let len = options.min_content_length.map(|_| {
+            text_length(&self.readability.extract_without_readability(snapshot).text_content)
+        });
+        let content = if options.sanitize_html {
+            self.readability.sanitize_html(&snapshot.html, options.strip_hidden)
+        } else {
+            snapshot.html.clone()
+        };
+        (content, len)
+    }
+}
+
+fn fill_empty_template(template: &str, url: &str, title: Option<&str>) -> String {
+    template
+        .replace("{url}", url)
+        .replace("{title}", title.unwrap_or(url))
+}
+
+/// Length in characters with runs of whitespace counted once, so layout
+/// padding does not lift a near-empty page over the threshold.
+fn text_length(text: &str) -> usize {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let chars: usize = words.iter().map(|w| w.chars().count()).sum();
+    chars + words.len().saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LineEndings;
+
+    const NOINDEX_PAGE: &str = r#"<html><head><title>Private</title>
+        <meta name="robots" content="noindex"></head>
+        <body><p>Hidden article</p></body></html>"#;
+
+    #[tokio::test]
+    async fn test_noindex_blocks_when_meta_robots_respected() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/private".to_string());
+        options.respect_meta_robots = true;
+
+        let result = converter.process(NOINDEX_PAGE, &options, &mut Vec::new()).await;
+        assert!(matches!(result, Err(AppError::BlockedUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn test_noindex_ignored_by_default() {
+        let converter = ConverterService::new(Config::default());
+        let options = CrawlerOptions::new("https://example.com/private".to_string());
+
+        let response = converter.process(NOINDEX_PAGE, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("Hidden article"));
+    }
+
+    #[tokio::test]
+    async fn test_selector_fallback_adds_warning() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/post".to_string());
+        options.target_selector = Some("article, main".to_string());
+        let html = "<html><body><main><p>Fallback body</p></main></body></html>";
+
+        let mut warnings = Vec::new();
+        let response = converter.process(html, &options, &mut warnings).await.unwrap();
+
+        assert!(response.content.contains("Fallback body"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("fell back to 'main'"));
+    }
+
+    #[tokio::test]
+    async fn test_summary_truncation_adds_warning() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/".to_string());
+        options.with_links_summary = true;
+        options.links_summary_limit = Some(1);
+        let html = r#"<html><body><p>Links</p><a href="/a">A</a><a href="/b">B</a></body></html>"#;
+
+        let mut warnings = Vec::new();
+        converter.process(html, &options, &mut warnings).await.unwrap();
+        assert_eq!(warnings, vec!["Links summary truncated to 1 of 2"]);
+
+        let mut warnings = Vec::new();
+        converter.process(NOINDEX_PAGE, &CrawlerOptions::new("https://example.com/".to_string()), &mut warnings)
+            .await
+            .unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_min_content_length_rejects_tiny_extraction() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/empty".to_string());
+        options.min_content_length = Some(20);
+        let html = "<html><body><div>\n\n   Loading   \n\n</div></body></html>";
+
+        let result = converter.process(html, &options, &mut Vec::new()).await;
+        assert!(matches!(result, Err(AppError::ExtractionError(_))));
+
+        options.respond_with = ResponseFormat::Text;
+        let result = converter.process(html, &options, &mut Vec::new()).await;
+        assert!(matches!(result, Err(AppError::ExtractionError(_))));
+
+        options.min_content_length = Some(7);
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("Loading"));
+    }
+
+    #[tokio::test]
+    async fn test_pre_block_keeps_its_newlines() {
+        let converter = ConverterService::new(Config::default());
+        let options = CrawlerOptions::new("https://example.com/art".to_string());
+        let html = "<html><body><article><h1>Diagram</h1>\
+            <p>The pipeline, drawn for readers who like boxes and arrows in their documentation.</p>\
+            <pre>+-----+    +-----+\n|  a  | -> |  b  |\n+-----+    +-----+</pre>\
+            <p>Everything  after   the diagram is ordinary prose and may be collapsed.</p>\
+            </article></body></html>";
+
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(
+            response.content.contains("+-----+    +-----+\n|  a  | -> |  b  |\n+-----+    +-----+"),
+            "{}",
+            response.content
+        );
+        assert!(response.content.contains("Everything after the diagram"));
+    }
+
+    #[tokio::test]
+    async fn test_byline_and_excerpt_are_surfaced() {
+        let converter = ConverterService::new(Config::default());
+        let options = CrawlerOptions::new("https://example.com/posts/1".to_string());
+        let article = r#"<html><head><title>Launch</title></head><body><article>
+            <h1>Launch day</h1>
+            <p class="byline">By Jane Doe</p>
+            <p>The first paragraph of the article explains what launched today and why it matters to readers.</p>
+            <p>A second paragraph adds more detail about the launch and the team behind it.</p>
+        </article></body></html>"#;
+
+        let response = converter.process(article, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(response.byline.as_deref(), Some("Jane Doe"));
+        assert!(response.excerpt.as_deref().unwrap().starts_with("The first paragraph"), "{:?}", response.excerpt);
+
+        let with_meta = article.replace(
+            "<title>Launch</title>",
+            r#"<title>Launch</title><meta name="author" content="Sam Roe"><meta name="description" content="What launched.">"#,
+        );
+        let response = converter.process(&with_meta, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(response.byline.as_deref(), Some("Sam Roe"));
+        assert_eq!(response.excerpt.as_deref(), Some("What launched."));
+    }
+
+    #[tokio::test]
+    async fn test_strip_hidden_drops_inline_hidden_blocks() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/".to_string());
+        let html = r#"<html><body><p>Visible paragraph</p>
+            <div style="display: none"><p>Hidden promo</p></div>
+            <p style="color:red;visibility:hidden">Invisible note</p>
+            <section hidden><p>Hidden section</p></section></body></html>"#;
+
+        let cleaned = converter.pipeline.readability.clean_html(html, false);
+        assert!(cleaned.contains("Hidden promo") && cleaned.contains("Hidden section"));
+
+        options.strip_hidden = true;
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("Visible paragraph"));
+        for hidden in ["Hidden promo", "Invisible note", "Hidden section"] {
+            assert!(!response.content.contains(hidden), "{} leaked: {}", hidden, response.content);
+        }
+
+        let revealed = r#"<html><body><p>Visible paragraph</p>
+            <div style="visibility: hidden"><p>Hidden wrapper</p>
+            <p style="visibility: visible">Revealed child</p></div></body></html>"#;
+        let cleaned = converter.pipeline.readability.clean_html(revealed, true);
+        assert!(cleaned.contains("Revealed child"), "{}", cleaned);
+    }
+
+    struct NumberedAlt(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl AltTextGenerator for NumberedAlt {
+        async fn generate(&self, image_url: &str, _page_url: &str) -> Result<String> {
+            if image_url.contains("broken") {
+                return Err(AppError::ExtractionError("unreachable".into()));
+            }
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(format!("Generated for {}", image_url.rsplit('/').next().unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generated_alt_fills_missing_descriptions() {
+        let generator = Arc::new(NumberedAlt(Default::default()));
+        let converter = ConverterService::new(Config::default())
+            .with_alt_text_generator(generator.clone());
+
+        let mut options = CrawlerOptions::new("https://example.com/".to_string());
+        options.with_images_summary = true;
+        options.with_generated_alt = true;
+        let html = r#"<html><body><p>Gallery</p>
+            <img src="https://example.com/a.png">
+            <img src="https://example.com/b.png" alt="Existing">
+            <img src="https://example.com/broken.png"></body></html>"#;
+
+        let mut warnings = Vec::new();
+        let response = converter.process(html, &options, &mut warnings).await.unwrap();
+        let alts: Vec<_> = response.images.unwrap().into_iter().map(|i| i.alt).collect();
+
+        assert_eq!(alts, vec![
+            Some("Generated for a.png".to_string()),
+            Some("Existing".to_string()),
+            None,
+        ]);
+        assert!(response.content.contains("[Generated for a.png](https://example.com/a.png)"));
+        assert_eq!(generator.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(warnings, vec!["Generated alt text for 1 of 2 images"]);
+
+        options.with_generated_alt = false;
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.images.unwrap()[0].alt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_noindex_page_generates_no_alt_text() {
+        let generator = Arc::new(NumberedAlt(Default::default()));
+        let converter = ConverterService::new(Config::default())
+            .with_alt_text_generator(generator.clone());
+
+        let mut options = CrawlerOptions::new("https://example.com/private".to_string());
+        options.with_generated_alt = true;
+        options.respect_meta_robots = true;
+        let html = r#"<html><head><meta name="robots" content="noindex"></head>
+            <body><p>Private gallery</p><img src="https://example.com/a.png"></body></html>"#;
+
+        let result = converter.process(html, &options, &mut Vec::new()).await;
+
+        assert!(matches!(result, Err(AppError::BlockedUrl(_))));
+        assert_eq!(generator.0.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shift_jis_body_is_transcoded() {
+        let converter = ConverterService::new(Config::default());
+        let options = CrawlerOptions::new("https://example.jp/".to_string());
+        let (body, _, _) = encoding_rs::SHIFT_JIS.encode(
+            "<html><head><title>ニュース</title></head><body><article><p>東京で桜が咲きました。</p></article></body></html>",
+        );
+
+        let response = converter
+            .process_bytes(&body, Some("text/html; charset=Shift_JIS"), &options, &mut Vec::new())
+            .await
+            .unwrap();
+
+        assert!(response.content.contains("東京で桜が咲きました。"));
+    }
+
+    #[tokio::test]
+    async fn test_raw_extract_keeps_navigation_content() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://docs.example.com/".to_string());
+        // `default` would pick the listing conversion for this page itself.
+        options.respond_with = ResponseFormat::Markdown;
+        let links: String = (1..=20)
+            .map(|i| format!(r#"<li><a href="/guide/{i}">Guide chapter {i}</a></li>"#))
+            .collect();
+        let html = format!(
+            "<html><head><title>Docs</title><script>var tracking = 1;</script></head><body>\
+            <nav><ul>{links}</ul></nav><footer><p>Maintained by the docs team</p></footer></body></html>"
+        );
+
+        let cleaned = converter.process(&html, &options, &mut Vec::new()).await.unwrap();
+        options.raw_extract = true;
+        let raw = converter.process(&html, &options, &mut Vec::new()).await.unwrap();
+
+        assert!(raw.content.len() > cleaned.content.len());
+        assert!(raw.content.contains("Guide chapter 20"));
+        assert!(raw.content.contains("Maintained by the docs team"));
+        assert!(!cleaned.content.contains("Guide chapter 20"));
+        assert!(!raw.content.contains("var tracking"));
+    }
+
+    #[tokio::test]
+    async fn test_default_format_picks_extraction_by_page_type() {
+        let converter = ConverterService::new(Config::default());
+        let options = CrawlerOptions::new("https://example.com/".to_string());
+        let prose = "The committee met on Tuesday to review the proposal in detail, and after a long \
+            discussion agreed to publish the findings later this month.";
+
+        let rows: String = (1..=12)
+            .map(|i| format!("<tr><td>Region {i}</td><td colspan=\"2\">{i}00 units shipped</td></tr>"))
+            .collect();
+        let table_page = format!(
+            "<html><head><title>Sales</title></head><body><main><h1>Quarterly sales</h1>\
+            <table><thead><tr><th>Region</th><th colspan=\"2\">Shipped</th></tr></thead><tbody>{rows}</tbody></table>\
+            </main></body></html>"
+        );
+        let article_page = format!(
+            "<html><head><title>News</title></head><body><nav><a href=\"/\">Home</a></nav>\
+            <article><h1>Committee report</h1><p>{prose}</p><p>{prose}</p><p>{prose}</p><p>{prose}</p></article>\
+            </body></html>"
+        );
+        let links: String = (1..=20)
+            .map(|i| format!(r#"<li><a href="/item/{i}">Listing entry number {i}</a></li>"#))
+            .collect();
+        let listing_page = format!(
+            "<html><head><title>Index</title></head><body><nav><ul>{links}</ul></nav>\
+            <footer><p>Updated daily</p></footer></body></html>"
+        );
+
+        let tables = converter.process(&table_page, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(tables.metadata.extraction, Some(ExtractionStrategy::Tables));
+        assert!(tables.content.contains("<table>"), "{}", tables.content);
+        assert!(tables.content.contains("colspan=\"2\""), "{}", tables.content);
+        assert!(!tables.content.contains("| Region |"), "{}", tables.content);
+
+        let article = converter.process(&article_page, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(article.metadata.extraction, Some(ExtractionStrategy::Article));
+        assert!(article.content.contains("The committee met"));
+        assert!(!article.content.contains("[Home]"), "{}", article.content);
+
+        let listing = converter.process(&listing_page, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(listing.metadata.extraction, Some(ExtractionStrategy::Listing));
+        assert!(listing.content.contains("Listing entry number 20"), "{}", listing.content);
+
+        let mut markdown = options.clone();
+        markdown.respond_with = ResponseFormat::Markdown;
+        let explicit = converter.process(&listing_page, &markdown, &mut Vec::new()).await.unwrap();
+        assert_eq!(explicit.metadata.extraction, None);
+        assert!(!explicit.content.contains("Listing entry number 20"));
+    }
+
+    #[tokio::test]
+    async fn test_with_text_returns_markdown_and_plain_text() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/article".to_string());
+        let html = "<html><head><title>Post</title></head><body><article>\
+            <h1>Heading</h1><p>Some <strong>bold</strong> words.</p></article></body></html>";
+
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.text.is_none());
+
+        options.with_text = true;
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        let text = response.text.unwrap();
+
+        assert!(response.content.contains("**bold**"));
+        assert!(text.contains("Some bold words."));
+        assert!(!text.contains("**"));
+
+        options.respond_with = ResponseFormat::Html;
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("<strong>"));
+        assert_eq!(response.text.as_deref(), Some(text.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_max_paragraphs_cuts_plain_text() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/article".to_string());
+        options.max_paragraphs = Some(1);
+        options.with_text = true;
+        let html = "<html><head><title>Post</title></head><body><article>\
+            <p>First paragraph of the article.</p><p>Second paragraph of the article.</p></article></body></html>";
+
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        let text = response.text.unwrap();
+        assert!(text.contains("First paragraph") && !text.contains("Second"), "{}", text);
+        assert!(text.ends_with("[…]"));
+
+        options.respond_with = ResponseFormat::Text;
+        options.with_text = false;
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("First paragraph"));
+        assert!(!response.content.contains("Second"), "{}", response.content);
+    }
+
+    #[tokio::test]
+    async fn test_sanitize_html_applies_to_html_format() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/embed".to_string());
+        options.respond_with = ResponseFormat::Html;
+        let html = r#"<html><head><title>Embed</title></head><body>
+            <p onclick="go()">Text with a <a href="/more">link</a>.</p><script>track()</script></body></html>"#;
+
+        let raw = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(raw.content.contains("<script>"));
+
+        options.sanitize_html = true;
+        let safe = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(safe.content, r#"<p>Text with a <a href="/more">link</a>.</p>"#);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_size_adds_chunks_of_full_content() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/article".to_string());
+        let html = "<html><head><title>Post</title></head><body><article>\
+            <h1>Heading</h1><p>First paragraph with a sentence. And another.</p>\
+            <p>Second paragraph that is a bit longer than the first one.</p></article></body></html>";
+
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.chunks.is_none());
+
+        options.chunk_size = Some(50);
+        let response = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        let chunks = response.chunks.unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 50));
+        assert_eq!(chunks.concat(), response.content);
+    }
+
+    #[tokio::test]
+    async fn test_soft_404_page_is_flagged_and_fails_when_strict() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/missing".to_string());
+        let not_found = "<html><head><title>Oops! | Example</title></head><body>\
+            <nav><a href=\"/\">Home</a></nav><main><h1>Page Not Found</h1>\
+            <p>Sorry, we looked everywhere.</p></main></body></html>";
+        let article = "<html><head><title>Post</title></head><body><article>\
+            <h1>Heading</h1><p>Regular article body.</p></article></body></html>";
+
+        let response = converter.process(not_found, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.soft_404);
+        assert!(!converter.process(article, &options, &mut Vec::new()).await.unwrap().soft_404);
+
+        options.soft_404_strict = true;
+        let err = converter.process(not_found, &options, &mut Vec::new()).await.unwrap_err();
+        assert!(matches!(err, AppError::SoftNotFound(_)), "{}", err);
+        assert!(converter.process(article, &options, &mut Vec::new()).await.is_ok());
+    }
+
+    fn large_page() -> String {
+        let mut body = String::from("<html><head><title>Long read</title></head><body><article><h1>Long read</h1>");
+        for i in 0..400 {
+            body.push_str(&format!(
+                "<p>Paragraph {i} with <a href=\"/section/{i}\">an internal link</a> and \
+                 <a href=\"https://other.example/{i}\" rel=\"nofollow\">an external one</a>.</p>\
+                 <img src=\"/img/{i}.png\" alt=\"Figure {i}\" width=\"640\" height=\"480\">"
+            ));
+        }
+        body.push_str("</article></body></html>");
+        body
+    }
+
+    #[tokio::test]
+    async fn test_parallel_conversion_matches_sequential() {
+        let converter = ConverterService::new(Config::default());
+        let html = large_page();
+        assert!(html.len() >= PARALLEL_MIN_HTML_BYTES);
+
+        let mut options = CrawlerOptions::new("https://example.com/long".to_string());
+        options.with_images_summary = true;
+        options.with_links_summary = true;
+        options.links_summary_limit = Some(100);
+        options.with_text = true;
+        options.chunk_size = Some(4000);
+
+        let mut outputs = Vec::new();
+        for parallel in [true, false] {
+            let mut warnings = Vec::new();
+            let mut response = converter.process_with(&html, &options, &mut warnings, parallel).await.unwrap();
+            response.metadata.processing_time_ms = 0;
+            outputs.push((serde_json::to_value(&response).unwrap(), warnings));
+        }
+
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(outputs[0].0["images"].as_array().unwrap().len(), 400);
+        assert_eq!(outputs[0].0["links"].as_array().unwrap().len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_conversion_leaves_the_runtime_free() {
+        let converter = ConverterService::new(Config::default());
+        let html = large_page();
+        let mut options = CrawlerOptions::new("https://example.com/long".to_string());
+        options.with_images_summary = true;
+        options.with_links_summary = true;
+
+        // The test runtime has one thread; a ticker on it only keeps ticking
+        // if conversion runs somewhere else.
+        let ticks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = ticks.clone();
+        let ticker = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+        tokio::task::yield_now().await;
+
+        let start = Instant::now();
+        let response = converter.process(&html, &options, &mut Vec::new()).await.unwrap();
+        let elapsed = start.elapsed();
+        ticker.abort();
+
+        assert_eq!(response.images.unwrap().len(), 400);
+        assert_eq!(response.links.unwrap().len(), 800);
+        let ticked = ticks.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(
+            ticked as u128 * 4 >= elapsed.as_millis(),
+            "{} ticks in {:?}", ticked, elapsed,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_template_fills_placeholders() {
+        let converter = ConverterService::new(Config {
+            empty_content_template: Some("Nothing at {url}".to_string()),
+            ..Config::default()
+        });
+        let mut options = CrawlerOptions::new("https://example.com/blank".to_string());
+        let empty = "<html><head><title>Blank page</title></head><body><div></div></body></html>";
+
+        let response = converter.process(empty, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(response.content, "Nothing at https://example.com/blank");
+
+        options.empty_template = Some("# {title}\n\nNo readable content at <{url}>.".to_string());
+        let response = converter.process(empty, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(response.content, "# Blank page\n\nNo readable content at <https://example.com/blank>.");
+
+        let article = "<html><head><title>Post</title></head><body><article>\
+            <p>Enough text to count as real content.</p></article></body></html>";
+        let response = converter.process(article, &options, &mut Vec::new()).await.unwrap();
+        assert!(response.content.contains("Enough text to count as real content."));
+    }
+
+    #[tokio::test]
+    async fn test_line_endings_lf_and_crlf() {
+        let converter = ConverterService::new(Config::default());
+        let mut options = CrawlerOptions::new("https://example.com/endings".to_string());
+        options.with_text = true;
+        let html = "<html><head><title>Endings</title></head><body><article>\r\n\
+            <h1>Heading</h1>\r\n<p>First paragraph.</p>\r<p>Second paragraph.</p></article></body></html>";
+
+        let lf = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert!(lf.content.contains("First paragraph.\n\nSecond paragraph."), "{:?}", lf.content);
+        assert!(!lf.content.contains('\r'));
+        assert!(!lf.text.as_deref().unwrap().contains('\r'));
+
+        options.line_endings = LineEndings::Crlf;
+        options.chunk_size = Some(30);
+        let crlf = converter.process(html, &options, &mut Vec::new()).await.unwrap();
+        assert_eq!(crlf.content, lf.content.replace('\n', "\r\n"));
+        assert!(!crlf.content.replace("\r\n", "").contains(['\r', '\n']));
+        assert_eq!(crlf.text, lf.text.map(|text| text.replace('\n', "\r\n")));
+        assert_eq!(crlf.chunks.unwrap().concat(), crlf.content);
     }
 }
@@ -0,0 +1,83 @@
+use crate::models::ContentDiff;
+use std::collections::HashMap;
+
+const SAMPLE_LINES: usize = 10;
+const SAMPLE_LINE_CHARS: usize = 200;
+
+/// Compare two documents line by line. The common prefix and suffix are
+/// skipped and the rest is matched as a multiset, which keeps the cost linear
+/// while still counting edits in the middle of a page; a line that only moved
+/// counts as unchanged.
+pub fn diff_lines(old: &str, new: &str) -> ContentDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines.iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..].iter().rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for line in old_middle {
+        *remaining.entry(line).or_default() += 1;
+    }
+
+    let mut added = Vec::new();
+    for line in new_middle {
+        match remaining.get_mut(line) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => added.push(*line),
+        }
+    }
+
+    let mut removed = Vec::new();
+    for line in old_middle {
+        if let Some(count) = remaining.get_mut(line) {
+            if *count > 0 {
+                *count -= 1;
+                removed.push(*line);
+            }
+        }
+    }
+
+    let sample = removed.iter().map(|l| ("- ", *l))
+        .chain(added.iter().map(|l| ("+ ", *l)))
+        .take(SAMPLE_LINES)
+        .map(|(sign, line)| format!("{}{}", sign, line.chars().take(SAMPLE_LINE_CHARS).collect::<String>()))
+        .collect();
+
+    ContentDiff {
+        changed: old != new,
+        lines_added: added.len(),
+        lines_removed: removed.len(),
+        sample,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_content_is_unchanged() {
+        let diff = diff_lines("# Title\n\nBody", "# Title\n\nBody");
+        assert_eq!(diff, ContentDiff { changed: false, lines_added: 0, lines_removed: 0, sample: Vec::new() });
+    }
+
+    #[test]
+    fn test_edited_line_counts_as_removed_and_added() {
+        let diff = diff_lines("# Title\nPrice: 10\nFooter", "# Title\nPrice: 12\nFooter");
+        assert!(diff.changed);
+        assert_eq!((diff.lines_added, diff.lines_removed), (1, 1));
+        assert_eq!(diff.sample, vec!["- Price: 10", "+ Price: 12"]);
+
+        let appended = diff_lines("a\nb", "a\nb\nc\nd");
+        assert_eq!((appended.lines_added, appended.lines_removed), (2, 0));
+    }
+}
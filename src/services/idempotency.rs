@@ -0,0 +1,211 @@
+use crate::error::{AppError, Result};
+use crate::models::LoadResponse;
+use dashmap::DashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Most keys remembered at once; the oldest are dropped first.
+const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+const MAX_KEY_LENGTH: usize = 255;
+
+struct IdempotencyEntry {
+    url: String,
+    fingerprint: String,
+    created_at: Instant,
+    result: Arc<OnceCell<LoadResponse>>,
+}
+
+/// Remembers the response produced for each `Idempotency-Key`, so a client
+/// retrying the same request gets the original result instead of a second
+/// load. Unlike the response cache this is keyed on client intent, not on
+/// the URL and options, and it holds concurrent retries until the first
+/// attempt finishes. Failed attempts are not remembered.
+pub struct IdempotencyService {
+    entries: DashMap<String, IdempotencyEntry>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl IdempotencyService {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self::with_capacity(ttl_secs, MAX_IDEMPOTENCY_KEYS)
+    }
+
+    fn with_capacity(ttl_secs: u64, capacity: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            ttl: Duration::from_secs(ttl_secs),
+            capacity,
+        }
+    }
+
+    /// Return the response stored for `key`, or run `load` and store its
+    /// result. Reusing a key for a different URL, or for options with a
+    /// different `fingerprint`, is rejected.
+    pub async fn run<F, Fut>(&self, key: &str, url: &str, fingerprint: &str, load: F) -> Result<LoadResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<LoadResponse>>,
+    {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(AppError::InvalidOption(format!(
+                "Idempotency-Key must be 1 to {} characters", MAX_KEY_LENGTH
+            )));
+        }
+
+        self.evict();
+
+        let result = {
+            let mut entry = self.entries
+                .entry(key.to_string())
+                .or_insert_with(|| self.new_entry(url, fingerprint));
+            if entry.created_at.elapsed() >= self.ttl {
+                *entry = self.new_entry(url, fingerprint);
+            }
+            if entry.url != url {
+                return Err(AppError::InvalidOption(
+                    "Idempotency-Key was already used for a different URL".to_string(),
+                ));
+            }
+            if entry.fingerprint != fingerprint {
+                return Err(AppError::InvalidOption(
+                    "Idempotency-Key was already used with different options".to_string(),
+                ));
+            }
+            entry.result.clone()
+        };
+
+        if let Some(response) = result.get() {
+            debug!("Replaying response for Idempotency-Key {}", key);
+            return Ok(response.clone());
+        }
+
+        result.get_or_try_init(load).await.cloned()
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn new_entry(&self, url: &str, fingerprint: &str) -> IdempotencyEntry {
+        IdempotencyEntry {
+            url: url.to_string(),
+            fingerprint: fingerprint.to_string(),
+            created_at: Instant::now(),
+            result: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Drop expired keys, then the oldest ones while the map is full.
+    fn evict(&self) {
+        if self.entries.len() < self.capacity {
+            return;
+        }
+
+        self.entries.retain(|_, entry| entry.created_at.elapsed() < self.ttl);
+
+        while self.entries.len() >= self.capacity {
+            let oldest = self.entries
+                .iter()
+                .min_by_key(|entry| entry.created_at)
+                .map(|entry| entry.key().clone());
+            match oldest {
+                Some(key) => {
+                    self.entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_response;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_same_key_runs_one_load() {
+        let service = Arc::new(IdempotencyService::new(60));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let url = "https://example.com/";
+
+        let attempts: Vec<_> = (0..3)
+            .map(|_| {
+                let service = service.clone();
+                let loads = loads.clone();
+                tokio::spawn(async move {
+                    service
+                        .run("retry-1", url, "markdown", || async move {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok(test_response(url, "content"))
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        for attempt in attempts {
+            assert_eq!(attempt.await.unwrap().unwrap().url, url);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        service.run("retry-2", url, "markdown", || async { Ok(test_response(url, "content")) }).await.unwrap();
+        assert_eq!(service.size(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_is_not_remembered() {
+        let service = IdempotencyService::new(60);
+        let url = "https://example.com/";
+
+        let first = service
+            .run("key", url, "markdown", || async { Err(AppError::Timeout(30)) })
+            .await;
+        assert!(matches!(first, Err(AppError::Timeout(30))));
+
+        let retry = service.run("key", url, "markdown", || async { Ok(test_response(url, "content")) }).await;
+        assert!(retry.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_key_reuse_for_other_url_and_expiry() {
+        let service = IdempotencyService::new(0);
+        service.run("key", "https://a.com/", "markdown", || async { Ok(test_response("https://a.com/", "content")) }).await.unwrap();
+
+        // With a zero TTL the key has already expired and can be reused.
+        let reused = service.run("key", "https://b.com/", "markdown", || async { Ok(test_response("https://b.com/", "content")) }).await;
+        assert_eq!(reused.unwrap().url, "https://b.com/");
+
+        let service = IdempotencyService::new(60);
+        service.run("key", "https://a.com/", "markdown", || async { Ok(test_response("https://a.com/", "content")) }).await.unwrap();
+        let conflict = service.run("key", "https://b.com/", "markdown", || async { Ok(test_response("https://b.com/", "content")) }).await;
+        assert!(matches!(conflict, Err(AppError::InvalidOption(_))));
+    }
+
+    #[tokio::test]
+    async fn test_key_reuse_with_other_options_is_rejected() {
+        let service = IdempotencyService::new(60);
+        let url = "https://a.com/";
+        service.run("key", url, "markdown", || async { Ok(test_response(url, "content")) }).await.unwrap();
+
+        let conflict = service.run("key", url, "html", || async { Ok(test_response(url, "content")) }).await;
+        assert!(matches!(conflict, Err(AppError::InvalidOption(_))));
+        assert!(service.run("key", url, "markdown", || async { Ok(test_response(url, "content")) }).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oldest_keys_are_evicted_at_capacity() {
+        let service = IdempotencyService::with_capacity(60, 2);
+        for key in ["a", "b", "c"] {
+            service.run(key, "https://a.com/", "markdown", || async { Ok(test_response("https://a.com/", "content")) }).await.unwrap();
+        }
+        assert_eq!(service.size(), 2);
+        assert!(!service.entries.contains_key("a"));
+    }
+}
@@ -1,8 +1,10 @@
 use crate::error::Result;
-use crate::models::{ExtractedContent, ImageData, LinkData};
+use crate::models::{CrawlerOptions, ExtractedContent, ImageData, LinkData, MarkdownBullet, MarkdownEmphasis};
+use crate::services::readability::protect_preformatted;
 use html2md::parse_html;
 use regex::Regex;
 use lazy_static::lazy_static;
+use scraper::{Html, Selector};
 
 lazy_static! {
     static ref MULTIPLE_NEWLINES: Regex = Regex::new(r"\n{3,}").unwrap();
@@ -12,8 +14,24 @@ lazy_static! {
     static ref BROKEN_LINKS: Regex = Regex::new(r"\[([^\]]*)\]\s+\(([^)]*)\)").unwrap();
     static ref EMPTY_HEADERS: Regex = Regex::new(r"^#{1,6}\s*$").unwrap();
     static ref SVG_CONTENT: Regex = Regex::new(r"<svg[^>]*>[\s\S]*?</svg>").unwrap();
+    static ref ATX_HEADING: Regex = Regex::new(r"^(#{1,6})\s+(.*?)(?:\s+#+)?\s*$").unwrap();
+    static ref SETEXT_UNDERLINE: Regex = Regex::new(r"^(={3,}|-{3,})\s*$").unwrap();
+    static ref TABLE_CAPTION: Regex =
+        Regex::new(r"(?is)(<table\b[^>]*>)\s*<caption\b[^>]*>(.*?)</caption>").unwrap();
+    static ref FIGURE: Regex = Regex::new(r"(?is)<figure\b([^>]*)>(.*?)</figure>").unwrap();
+    static ref FIGCAPTION: Regex = Regex::new(r"(?is)<figcaption\b[^>]*>(.*?)</figcaption>").unwrap();
+    static ref BULLET_ITEM: Regex = Regex::new(r"^(\s*)[-*+]( .*)$").unwrap();
+    static ref THEMATIC_BREAK: Regex = Regex::new(r"^\s*([-*_])(?:\s*([-*_])){2,}\s*$").unwrap();
+    static ref INLINE_CODE_OR_LINK_TARGET: Regex = Regex::new(r"`[^`]*`|\]\([^)]*\)").unwrap();
+    static ref ASTERISK_STRONG: Regex = Regex::new(r"\*\*([^*\s](?:[^*]*[^*\s\\])?)\*\*").unwrap();
+    static ref ASTERISK_EM: Regex = Regex::new(r"\*([^*\s](?:[^*]*[^*\s\\])?)\*").unwrap();
+    static ref UNDERSCORE_STRONG: Regex = Regex::new(r"__([^_\s](?:[^_]*[^_\s\\])?)__").unwrap();
+    static ref UNDERSCORE_EM: Regex = Regex::new(r"_([^_\s](?:[^_]*[^_\s\\])?)_").unwrap();
 }
 
+/// Marks the end of output cut short by `x-max-paragraphs`.
+pub(crate) const PARAGRAPH_ELLIPSIS: &str = "[…]";
+
 pub struct MarkdownService;
 
 impl MarkdownService {
@@ -21,22 +39,44 @@ impl MarkdownService {
         Self
     }
 
-    pub fn convert_to_markdown(&self, content: &ExtractedContent) -> Result<String> {
-        let cleaned_html = self.preprocess_html(&content.content);
+    pub fn convert_to_markdown(&self, content: &ExtractedContent, options: &CrawlerOptions) -> Result<String> {
+        self.convert_with(content, options, false)
+    }
+
+    /// [`Self::convert_to_markdown`], leaving every table as HTML. Markdown
+    /// tables cannot express merged cells or block content in a cell.
+    pub fn convert_keeping_tables(&self, content: &ExtractedContent, options: &CrawlerOptions) -> Result<String> {
+        self.convert_with(content, options, true)
+    }
+
+    fn convert_with(&self, content: &ExtractedContent, options: &CrawlerOptions, keep_tables: bool) -> Result<String> {
+        let mut cleaned_html = self.preprocess_html(&content.content);
+
+        let mut tables = Vec::new();
+        if keep_tables {
+            (cleaned_html, tables) = lift_tables(&cleaned_html);
+        }
 
         let markdown = parse_html(&cleaned_html);
 
-        let tidied = self.tidy_markdown(&markdown);
+        let mut tidied = self.tidy_markdown(&markdown, options);
+        for (i, table) in tables.iter().enumerate() {
+            tidied = tidied.replace(&table_placeholder(i), table);
+        }
+
+        if let Some(max) = options.max_paragraphs {
+            tidied = self.truncate_paragraphs(&tidied, max);
+        }
 
         let with_metadata = self.add_metadata_header(&tidied, content);
 
         Ok(with_metadata)
     }
 
-    pub fn convert_raw(&self, html: &str) -> Result<String> {
+    pub fn convert_raw(&self, html: &str, options: &CrawlerOptions) -> Result<String> {
         let cleaned_html = self.preprocess_html(html);
         let markdown = parse_html(&cleaned_html);
-        let tidied = self.tidy_markdown(&markdown);
+        let tidied = self.tidy_markdown(&markdown, options);
         Ok(tidied)
     }
 
@@ -47,6 +87,8 @@ impl MarkdownService {
 
         result = self.remove_style_attributes(&result);
 
+        result = self.lift_captions(&result);
+
         result = self.normalize_whitespace(&result);
 
         result
@@ -60,12 +102,34 @@ impl MarkdownService {
         class_pattern.replace_all(&result, "").to_string()
     }
 
+    /// html2md drops `<caption>` and runs `<figcaption>` into the image, so
+    /// both become paragraphs: a table caption as a bold line above the
+    /// table, a figure caption as an italic line at the end of its figure.
+    fn lift_captions(&self, html: &str) -> String {
+        let result = TABLE_CAPTION.replace_all(html, |caps: &regex::Captures| {
+            format!("{}{}", caption_paragraph(&caps[2], "strong"), &caps[1])
+        });
+
+        let result = FIGURE.replace_all(&result, |caps: &regex::Captures| {
+            let captions: String = FIGCAPTION
+                .captures_iter(&caps[2])
+                .map(|c| caption_paragraph(&c[1], "em"))
+                .collect();
+            let body = FIGCAPTION.replace_all(&caps[2], "");
+            format!("<figure{}>{}{}</figure>", &caps[1], body, captions)
+        });
+
+        FIGCAPTION
+            .replace_all(&result, |caps: &regex::Captures| caption_paragraph(&caps[1], "em"))
+            .to_string()
+    }
+
     fn normalize_whitespace(&self, html: &str) -> String {
         let ws_pattern = Regex::new(r">\s+<").unwrap();
-        ws_pattern.replace_all(html, "> <").to_string()
+        protect_preformatted(html, |html| ws_pattern.replace_all(html, "> <").to_string())
     }
 
-    fn tidy_markdown(&self, markdown: &str) -> String {
+    fn tidy_markdown(&self, markdown: &str, options: &CrawlerOptions) -> String {
         let mut result = markdown.to_string();
 
         result = BROKEN_LINKS.replace_all(&result, "[$1]($2)").to_string();
@@ -86,9 +150,138 @@ impl MarkdownService {
 
         result = self.fix_code_blocks(&result);
 
+        if options.normalize_headings {
+            result = self.normalize_headings(&result);
+        }
+
+        result = self.normalize_markers(&result, options.markdown_bullet, options.markdown_emphasis);
+
         result.trim().to_string()
     }
 
+    /// Shifts heading levels so the shallowest one becomes `#`, keeping the
+    /// gaps between levels. html2md writes h1/h2 as setext underlines, so
+    /// those are rewritten as ATX along the way, and closing `###` runs are
+    /// dropped. Fenced code is left alone.
+    fn normalize_headings(&self, markdown: &str) -> String {
+        let lines: Vec<&str> = markdown.lines().collect();
+        // (line index, level, text, consumes the following underline)
+        let mut headings: Vec<(usize, usize, &str, bool)> = Vec::new();
+        let mut in_fence = false;
+
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            } else if !in_fence {
+                if let Some(caps) = ATX_HEADING.captures(line) {
+                    let text = caps.get(2).map_or("", |m| m.as_str());
+                    headings.push((i, caps[1].len(), text, false));
+                } else if let Some(next) = lines.get(i + 1) {
+                    if !line.trim().is_empty() && SETEXT_UNDERLINE.is_match(next) {
+                        let level = if next.starts_with('=') { 1 } else { 2 };
+                        headings.push((i, level, line.trim(), true));
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let Some(shift) = headings.iter().map(|h| h.1).min().map(|min| min - 1) else {
+            return markdown.to_string();
+        };
+        if shift == 0 {
+            return markdown.to_string();
+        }
+
+        let mut out: Vec<String> = Vec::with_capacity(lines.len());
+        let mut headings = headings.into_iter().peekable();
+        let mut i = 0;
+        while i < lines.len() {
+            match headings.peek() {
+                Some(&(index, level, text, underlined)) if index == i => {
+                    out.push(format!("{} {}", "#".repeat(level - shift), text));
+                    headings.next();
+                    i += if underlined { 2 } else { 1 };
+                }
+                _ => {
+                    out.push(lines[i].to_string());
+                    i += 1;
+                }
+            }
+        }
+        out.join("\n")
+    }
+
+    /// Rewrites bullet list markers to `bullet` and emphasis delimiters to
+    /// `emphasis`. Fenced code, inline code, link targets, thematic breaks
+    /// and backslash-escaped characters are left alone; underscores only
+    /// count as delimiters at word boundaries, so `snake_case` survives.
+    fn normalize_markers(&self, markdown: &str, bullet: MarkdownBullet, emphasis: MarkdownEmphasis) -> String {
+        let mut in_fence = false;
+        markdown
+            .lines()
+            .map(|line| {
+                if line.trim_start().starts_with("```") {
+                    in_fence = !in_fence;
+                    return line.to_string();
+                }
+                if in_fence || THEMATIC_BREAK.is_match(line) {
+                    return line.to_string();
+                }
+                let line = BULLET_ITEM.replace(line, |caps: &regex::Captures| {
+                    format!("{}{}{}", &caps[1], bullet.marker(), &caps[2])
+                });
+                normalize_emphasis(&line, emphasis)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Keeps the first `max` blocks separated by blank lines, then appends
+    /// `PARAGRAPH_ELLIPSIS` if anything was cut. Headings are kept but not
+    /// counted, and a fenced code block is one block even with blank lines.
+    fn truncate_paragraphs(&self, markdown: &str, max: usize) -> String {
+        let mut blocks: Vec<String> = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        let mut in_fence = false;
+
+        for line in markdown.lines() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if line.trim().is_empty() && !in_fence {
+                if !current.is_empty() {
+                    blocks.push(current.join("\n"));
+                    current.clear();
+                }
+            } else {
+                current.push(line);
+            }
+        }
+        if !current.is_empty() {
+            blocks.push(current.join("\n"));
+        }
+
+        let mut kept = Vec::new();
+        let mut counted = 0;
+        for (i, block) in blocks.iter().enumerate() {
+            if counted == max {
+                if blocks[i..].iter().any(|b| !ATX_HEADING.is_match(b)) {
+                    kept.push(PARAGRAPH_ELLIPSIS.to_string());
+                }
+                break;
+            }
+            if !ATX_HEADING.is_match(block) {
+                counted += 1;
+            }
+            kept.push(block.clone());
+        }
+        kept.join("\n\n")
+    }
+
     fn fix_list_formatting(&self, markdown: &str) -> String {
         let mut lines: Vec<String> = Vec::new();
         let mut prev_was_list = false;
@@ -99,8 +292,8 @@ impl MarkdownService {
             let is_list_item = trimmed.starts_with("- ")
                 || trimmed.starts_with("* ")
                 || trimmed.starts_with("+ ")
-                || trimmed.chars().next().map_or(false, |c| c.is_ascii_digit())
-                    && trimmed.chars().nth(1).map_or(false, |c| c == '.' || c == ')');
+                || trimmed.chars().next().is_some_and(|c| c.is_ascii_digit())
+                    && trimmed.chars().nth(1).is_some_and(|c| c == '.' || c == ')');
 
             if is_list_item && !prev_was_list && !lines.is_empty() {
                 let last = lines.last().unwrap_or(&empty_string);
@@ -148,36 +341,47 @@ impl MarkdownService {
         format!("{}\n\n---\n\n{}", header_parts.join("\n"), markdown)
     }
 
-    pub fn add_images_summary(&self, markdown: &str, images: &[ImageData]) -> String {
+    pub fn add_images_summary(&self, markdown: &str, images: &[ImageData], limit: Option<usize>) -> String {
         if images.is_empty() {
             return markdown.to_string();
         }
 
         let mut summary = String::from("\n\n---\n\n## Images\n\n");
 
-        for (i, image) in images.iter().enumerate() {
+        let shown = limit.unwrap_or(images.len()).min(images.len());
+        for (i, image) in images.iter().take(shown).enumerate() {
             let alt = image.alt.as_deref().unwrap_or("No description");
             summary.push_str(&format!("{}. [{}]({})\n", i + 1, alt, image.src));
         }
+        Self::push_remainder(&mut summary, images.len() - shown);
 
         format!("{}{}", markdown, summary)
     }
 
-    pub fn add_links_summary(&self, markdown: &str, links: &[LinkData]) -> String {
+    pub fn add_links_summary(&self, markdown: &str, links: &[LinkData], limit: Option<usize>) -> String {
         if links.is_empty() {
             return markdown.to_string();
         }
 
         let mut summary = String::from("\n\n---\n\n## Links\n\n");
 
-        for (i, link) in links.iter().enumerate() {
+        let shown = limit.unwrap_or(links.len()).min(links.len());
+        for (i, link) in links.iter().take(shown).enumerate() {
             let text = link.text.as_deref().unwrap_or(&link.href);
-            summary.push_str(&format!("{}. [{}]({})\n", i + 1, text, link.href));
+            let rel = link.rel.as_ref().map(|rel| format!(" (rel: {})", rel)).unwrap_or_default();
+            summary.push_str(&format!("{}. [{}]({}){}\n", i + 1, text, link.href, rel));
         }
+        Self::push_remainder(&mut summary, links.len() - shown);
 
         format!("{}{}", markdown, summary)
     }
 
+    fn push_remainder(summary: &mut String, remaining: usize) {
+        if remaining > 0 {
+            summary.push_str(&format!("\n... and {} more\n", remaining));
+        }
+    }
+
     #[allow(dead_code)]
     pub fn number_images(&self, markdown: &str, images: &[ImageData]) -> String {
         let mut result = markdown.to_string();
@@ -199,8 +403,305 @@ impl MarkdownService {
     }
 }
 
+/// Swaps each outermost `<table>` for a placeholder paragraph that html2md
+/// passes through untouched, returning the tables' HTML in order.
+fn lift_tables(html: &str) -> (String, Vec<String>) {
+    let fragment = Html::parse_fragment(html);
+    let Ok(selector) = Selector::parse("table") else {
+        return (html.to_string(), Vec::new());
+    };
+
+    let mut lifted = fragment.root_element().inner_html();
+    let mut tables = Vec::new();
+    for table in fragment.select(&selector) {
+        let nested = table.ancestors()
+            .any(|a| a.value().as_element().is_some_and(|el| el.name() == "table"));
+        if nested {
+            continue;
+        }
+        let markup = table.html();
+        lifted = lifted.replacen(&markup, &format!("<p>{}</p>", table_placeholder(tables.len())), 1);
+        tables.push(markup);
+    }
+    (lifted, tables)
+}
+
+/// Terminated, so the placeholder for table 1 is not a prefix of the one
+/// for table 10.
+fn table_placeholder(index: usize) -> String {
+    format!("WLETABLE{}END", index)
+}
+
+/// Wraps caption markup in `<p><tag>..</tag></p>`, or nothing when it has
+/// no text.
+fn caption_paragraph(inner: &str, tag: &str) -> String {
+    let inner = inner.trim();
+    if inner.is_empty() {
+        return String::new();
+    }
+    format!("<p><{tag}>{inner}</{tag}></p>")
+}
+
+/// Rewrites the emphasis in one line, outside inline code and link
+/// targets.
+fn normalize_emphasis(line: &str, emphasis: MarkdownEmphasis) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    for protected in INLINE_CODE_OR_LINK_TARGET.find_iter(line) {
+        out.push_str(&swap_emphasis(&line[last..protected.start()], emphasis));
+        out.push_str(protected.as_str());
+        last = protected.end();
+    }
+    out.push_str(&swap_emphasis(&line[last..], emphasis));
+    out
+}
+
+fn swap_emphasis(text: &str, emphasis: MarkdownEmphasis) -> String {
+    let (strong, em, underscores) = match emphasis {
+        MarkdownEmphasis::Asterisk => (&*UNDERSCORE_STRONG, &*UNDERSCORE_EM, true),
+        MarkdownEmphasis::Underscore => (&*ASTERISK_STRONG, &*ASTERISK_EM, false),
+    };
+    let marker = emphasis.marker();
+    let text = replace_delimited(text, strong, &format!("{marker}{marker}"), underscores);
+    replace_delimited(&text, em, &marker.to_string(), underscores)
+}
+
+/// Replaces each match of `pattern` with its inner text wrapped in
+/// `delimiter`, skipping matches opened by an escaped character and, for
+/// underscores, matches inside a word.
+fn replace_delimited(text: &str, pattern: &Regex, delimiter: &str, word_bounded: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in pattern.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let before = text[..whole.start()].chars().next_back();
+        let after = text[whole.end()..].chars().next();
+        let inside_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric());
+        if before == Some('\\') || word_bounded && (inside_word(before) || inside_word(after)) {
+            continue;
+        }
+        out.push_str(&text[last..whole.start()]);
+        out.push_str(delimiter);
+        out.push_str(&caps[1]);
+        out.push_str(delimiter);
+        last = whole.end();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
 impl Default for MarkdownService {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(html: &str, normalize_headings: bool) -> String {
+        let mut options = CrawlerOptions::new("https://example.com".to_string());
+        options.normalize_headings = normalize_headings;
+        MarkdownService::new().convert_raw(html, &options).unwrap()
+    }
+
+    #[test]
+    fn test_max_paragraphs_caps_blocks() {
+        let html = "<h3>Title</h3><p>One</p><p>Two</p><pre><code>a\n\nb</code></pre><h3>Next</h3><p>Three</p><p>Four</p>";
+        let options = CrawlerOptions::new("https://example.com".to_string());
+        let service = MarkdownService::new();
+        let converted = service.convert_raw(html, &options).unwrap();
+
+        let markdown = service.truncate_paragraphs(&converted, 2);
+        assert!(markdown.contains("One") && markdown.contains("Two"));
+        assert!(!markdown.contains("Three"));
+        assert!(markdown.ends_with(PARAGRAPH_ELLIPSIS));
+
+        let markdown = service.truncate_paragraphs(&converted, 4);
+        assert!(markdown.contains("Three") && !markdown.contains("Four"));
+        assert!(markdown.contains("a\n\nb"), "code block was split: {}", markdown);
+
+        let all = service.truncate_paragraphs("Only\n\nTwo", 5);
+        assert_eq!(all, "Only\n\nTwo");
+    }
+
+    #[test]
+    fn test_normalize_headings_promotes_shallowest_to_h1() {
+        let html = "<h3>Article</h3><p>Intro</p><h4>Section</h4><p>Body</p><h6>Aside</h6><pre><code>### not a heading</code></pre>";
+
+        let markdown = convert(html, true);
+        let headings: Vec<&str> = markdown.lines().filter(|l| l.starts_with('#')).collect();
+        assert_eq!(headings, vec!["# Article", "## Section", "#### Aside", "### not a heading"]);
+
+        assert!(convert(html, false).starts_with("### Article"));
+    }
+
+    #[test]
+    fn test_table_caption_is_bold_line_above_table() {
+        let html = "<p>Before</p><table><caption> Quarterly sales </caption><tr><th>Q</th><th>N</th></tr><tr><td>1</td><td>2</td></tr></table><p>After</p>";
+
+        let markdown = convert(html, false);
+        let lines: Vec<&str> = markdown.lines().filter(|l| !l.is_empty()).collect();
+        let caption = lines.iter().position(|l| *l == "**Quarterly sales**").unwrap();
+        assert_eq!(lines[caption + 1], "| Q | N |");
+    }
+
+    #[test]
+    fn test_figcaption_is_italic_line_below_image() {
+        for html in [
+            "<p>Before</p><figure><img src=\"a.png\" alt=\"A\"><figcaption>A cat on a mat</figcaption></figure><p>After</p>",
+            "<p>Before</p><figure><figcaption>A cat on a mat</figcaption><img src=\"a.png\" alt=\"A\"></figure><p>After</p>",
+        ] {
+            let markdown = convert(html, false);
+            let lines: Vec<&str> = markdown.lines().filter(|l| !l.is_empty()).collect();
+            let image = lines.iter().position(|l| *l == "![A](a.png)").unwrap();
+            assert_eq!(lines[image + 1], "*A cat on a mat*", "{}", markdown);
+        }
+
+        let empty = convert("<figure><img src=\"a.png\" alt=\"A\"><figcaption> </figcaption></figure>", false);
+        assert_eq!(empty, "![A](a.png)");
+    }
+
+    #[test]
+    fn test_kept_tables_are_restored_in_place() {
+        let html: String = (0..12)
+            .map(|i| format!("<p>Before {i}</p><table><tr><td rowspan=\"2\">cell {i}</td></tr></table>"))
+            .collect();
+        let content = ExtractedContent {
+            url: "https://example.com".to_string(),
+            title: None,
+            content: html,
+            text_content: String::new(),
+            published_time: None,
+            byline: None,
+            excerpt: None,
+            images: Vec::new(),
+            links: Vec::new(),
+            fallback_reason: None,
+        };
+        let options = CrawlerOptions::new("https://example.com".to_string());
+
+        let markdown = MarkdownService::new().convert_keeping_tables(&content, &options).unwrap();
+
+        assert!(!markdown.contains("WLETABLE"), "{}", markdown);
+        let cells: Vec<usize> = (0..12)
+            .map(|i| markdown.find(&format!(">cell {i}<")).unwrap_or_else(|| panic!("table {i} missing: {markdown}")))
+            .collect();
+        assert!(cells.windows(2).all(|pair| pair[0] < pair[1]), "{}", markdown);
+        for i in 0..12 {
+            assert_eq!(markdown.matches(&format!(">cell {i}<")).count(), 1, "{}", markdown);
+        }
+    }
+
+    fn convert_styled(html: &str, bullet: &str, emphasis: &str) -> String {
+        let mut options = CrawlerOptions::new("https://example.com".to_string());
+        options.markdown_bullet = MarkdownBullet::from_header(bullet);
+        options.markdown_emphasis = MarkdownEmphasis::from_header(emphasis);
+        MarkdownService::new().convert_raw(html, &options).unwrap()
+    }
+
+    #[test]
+    fn test_bullets_default_to_dash_and_follow_the_option() {
+        let html = "<ul><li>One</li><li>Two<ul><li>Nested</li></ul></li></ul><hr><pre><code>* kept\n+ kept</code></pre>";
+
+        let markdown = convert_styled(html, "", "");
+        assert!(markdown.contains("- One\n") && markdown.contains("- Two"), "{}", markdown);
+        assert!(markdown.lines().any(|l| l.trim_start() == "- Nested"), "{}", markdown);
+        assert!(markdown.contains("* kept\n+ kept"), "{}", markdown);
+
+        let service = MarkdownService::new();
+        let mixed = "* one\n+ two\n  - three\n\n* * *\n\n---";
+        assert_eq!(
+            service.normalize_markers(mixed, MarkdownBullet::Plus, MarkdownEmphasis::Asterisk),
+            "+ one\n+ two\n  + three\n\n* * *\n\n---",
+        );
+        assert_eq!(
+            service.normalize_markers(mixed, MarkdownBullet::Asterisk, MarkdownEmphasis::Asterisk),
+            "* one\n* two\n  * three\n\n* * *\n\n---",
+        );
+    }
+
+    #[test]
+    fn test_emphasis_is_normalized_to_the_requested_delimiter() {
+        let service = MarkdownService::new();
+        let mixed = "Some _em_ and *em*, __strong__ and **strong**, `_code_` [a](https://x.com/_p_) snake_case_name \\*literal\\*";
+
+        assert_eq!(
+            service.normalize_markers(mixed, MarkdownBullet::Dash, MarkdownEmphasis::Asterisk),
+            "Some *em* and *em*, **strong** and **strong**, `_code_` [a](https://x.com/_p_) snake_case_name \\*literal\\*",
+        );
+        assert_eq!(
+            service.normalize_markers(mixed, MarkdownBullet::Dash, MarkdownEmphasis::Underscore),
+            "Some _em_ and _em_, __strong__ and __strong__, `_code_` [a](https://x.com/_p_) snake_case_name \\*literal\\*",
+        );
+
+        let markdown = convert_styled("<p>An <em>em</em> and a <strong>strong</strong> word.</p>", "-", "_");
+        assert_eq!(markdown, "An _em_ and a __strong__ word.");
+    }
+
+    fn links(count: usize) -> Vec<LinkData> {
+        (0..count)
+            .map(|i| LinkData {
+                href: format!("https://example.com/{}", i),
+                text: Some(format!("Link {}", i)),
+                rel: None,
+                is_internal: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_links_summary_cap() {
+        let service = MarkdownService::new();
+        let summary = service.add_links_summary("Body", &links(1000), Some(10));
+
+        let listed = summary.lines().filter(|l| l.contains("](https://example.com/")).count();
+        assert_eq!(listed, 10);
+        assert!(summary.contains("10. [Link 9](https://example.com/9)"));
+        assert!(!summary.contains("Link 10]"));
+        assert!(summary.trim_end().ends_with("... and 990 more"));
+    }
+
+    #[test]
+    fn test_links_summary_shows_rel() {
+        let mut links = links(2);
+        links[0].rel = Some("nofollow noopener".to_string());
+        let summary = MarkdownService::new().add_links_summary("Body", &links, None);
+
+        assert!(summary.contains("1. [Link 0](https://example.com/0) (rel: nofollow noopener)\n"));
+        assert!(summary.contains("2. [Link 1](https://example.com/1)\n"));
+    }
+
+    #[test]
+    fn test_links_summary_without_cap_lists_everything() {
+        let service = MarkdownService::new();
+        let summary = service.add_links_summary("Body", &links(25), None);
+
+        let listed = summary.lines().filter(|l| l.contains("](https://example.com/")).count();
+        assert_eq!(listed, 25);
+        assert!(!summary.contains("more"));
+
+        let summary = service.add_links_summary("Body", &links(5), Some(10));
+        assert!(!summary.contains("more"));
+    }
+
+    #[test]
+    fn test_images_summary_cap() {
+        let service = MarkdownService::new();
+        let images: Vec<ImageData> = (0..50)
+            .map(|i| ImageData {
+                src: format!("https://example.com/{}.png", i),
+                alt: None,
+                width: None,
+                height: None,
+                data_url: None,
+                data_url_dropped: false,
+            })
+            .collect();
+
+        let summary = service.add_images_summary("Body", &images, Some(3));
+        assert!(summary.contains("3. [No description](https://example.com/2.png)"));
+        assert!(summary.contains("... and 47 more"));
+    }
+}
@@ -4,15 +4,28 @@ pub mod readability;
 pub mod markdown;
 pub mod converter;
 pub mod screenshot;
+pub mod screenshot_store;
 pub mod cache;
 pub mod security;
 pub mod user_agent;
+pub mod progress;
+pub mod alt_text;
+pub mod permits;
+pub mod charset;
+pub mod idempotency;
+pub mod diff;
+pub mod chunker;
+pub mod in_flight;
 
 pub use browser::BrowserPool;
-pub use scraper::ScraperService;
+pub use scraper::{RawAssets, ScraperService};
 pub use readability::ReadabilityService;
 pub use markdown::MarkdownService;
 pub use converter::ConverterService;
 pub use screenshot::ScreenshotService;
 pub use cache::CacheService;
+pub use idempotency::IdempotencyService;
+pub use in_flight::InFlightRegistry;
 pub use security::SecurityService;
+pub use progress::ProgressReporter;
+pub use alt_text::{AltTextGenerator, HttpAltTextGenerator};
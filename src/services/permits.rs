@@ -0,0 +1,324 @@
+use crate::models::{AcquireWaitStats, Priority, WaitBucket};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+/// Upper bounds of the acquire wait buckets, in milliseconds. Waits longer
+/// than the last bound land in the overflow bucket.
+const WAIT_BUCKETS_MS: [u64; 9] = [10, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Counting permits for browser pages that are handed out by priority.
+/// When the pool is saturated, a released permit goes to the oldest waiter
+/// of the highest priority instead of whoever asked first.
+pub struct PriorityPermits {
+    state: Mutex<PermitState>,
+}
+
+struct PermitState {
+    available: usize,
+    /// Waiters per priority, highest first.
+    queues: [VecDeque<oneshot::Sender<PagePermit>>; 3],
+}
+
+/// Held for as long as a page is in use; releasing it wakes the next waiter.
+pub struct PagePermit {
+    permits: Option<Arc<PriorityPermits>>,
+}
+
+impl PriorityPermits {
+    pub fn new(total: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(PermitState {
+                available: total,
+                queues: Default::default(),
+            }),
+        })
+    }
+
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> PagePermit {
+        let receiver = {
+            let mut state = self.state.lock();
+            if state.available > 0 {
+                state.available -= 1;
+                return PagePermit { permits: Some(self.clone()) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.queues[Self::rank(priority)].push_back(sender);
+            receiver
+        };
+
+        // The sender half lives in the queue, which this struct owns, so it is
+        // only dropped after a permit has been sent.
+        receiver.await.expect("permit queue dropped while waiting")
+    }
+
+    /// Acquires `count` permits one at a time, for callers that need the
+    /// pool to themselves.
+    pub async fn acquire_many(self: &Arc<Self>, priority: Priority, count: usize) -> Vec<PagePermit> {
+        let mut held = Vec::with_capacity(count);
+        for _ in 0..count {
+            held.push(self.acquire(priority).await);
+        }
+        held
+    }
+
+    pub fn available(&self) -> usize {
+        self.state.lock().available
+    }
+
+    /// Waiters still queued for a permit. Waiters whose request was
+    /// cancelled are not counted even before `release` drops them.
+    pub fn queued(&self) -> usize {
+        self.state.lock()
+            .queues
+            .iter()
+            .flatten()
+            .filter(|waiter| !waiter.is_closed())
+            .count()
+    }
+
+    fn rank(priority: Priority) -> usize {
+        match priority {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock();
+        for queue in state.queues.iter_mut() {
+            while let Some(waiter) = queue.pop_front() {
+                // A waiter whose request was cancelled hands the permit back
+                // through `Err`. Disarm it so dropping it does not re-enter
+                // `release` while the lock is held, then try the next waiter.
+                match waiter.send(PagePermit { permits: Some(self.clone()) }) {
+                    Ok(()) => return,
+                    Err(mut permit) => {
+                        permit.permits.take();
+                    }
+                }
+            }
+        }
+        state.available += 1;
+    }
+}
+
+/// Caps how many URLs from batch-style requests (batch, OpenWebUI, crawl)
+/// are in flight at once across all of them. Batch URLs take this slot
+/// before queueing for a page, so however large the batch, the rest of the
+/// pool stays free for single requests.
+pub struct BatchAdmission {
+    slots: Arc<Semaphore>,
+}
+
+/// Held while one batch URL is being processed.
+pub struct BatchSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl BatchAdmission {
+    pub fn new(limit: usize) -> Self {
+        Self { slots: Arc::new(Semaphore::new(limit)) }
+    }
+
+    pub async fn admit(&self) -> BatchSlot {
+        let permit = self.slots.clone()
+            .acquire_owned()
+            .await
+            .expect("batch admission semaphore is never closed");
+        BatchSlot { _permit: permit }
+    }
+}
+
+/// Histogram of how long callers waited for a permit.
+#[derive(Default)]
+pub struct WaitHistogram {
+    buckets: [AtomicU64; WAIT_BUCKETS_MS.len() + 1],
+    count: AtomicU64,
+    total_ms: AtomicU64,
+}
+
+impl WaitHistogram {
+    pub fn record(&self, waited: Duration) {
+        let ms = waited.as_millis() as u64;
+        let bucket = WAIT_BUCKETS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(WAIT_BUCKETS_MS.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> AcquireWaitStats {
+        let buckets = self.buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| WaitBucket {
+                le_ms: WAIT_BUCKETS_MS.get(i).copied(),
+                count: count.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        AcquireWaitStats {
+            count: self.count.load(Ordering::Relaxed),
+            total_ms: self.total_ms.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+impl Drop for PagePermit {
+    fn drop(&mut self) {
+        if let Some(permits) = self.permits.take() {
+            permits.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_high_priority_is_served_before_queued_low() {
+        let permits = PriorityPermits::new(1);
+        let held = permits.acquire(Priority::Normal).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let mut waiters = Vec::new();
+        for (name, priority) in [("low-1", Priority::Low), ("low-2", Priority::Low), ("high", Priority::High)] {
+            let permits = permits.clone();
+            let order = order.clone();
+            waiters.push(tokio::spawn(async move {
+                let _permit = permits.acquire(priority).await;
+                order.lock().push(name);
+            }));
+            // Make sure each waiter is queued before the next one.
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+
+        assert_eq!(*order.lock(), vec!["high", "low-1", "low-2"]);
+        assert_eq!(permits.available(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_many_waits_for_outstanding_permits() {
+        let permits = PriorityPermits::new(2);
+        let held = permits.acquire(Priority::Normal).await;
+
+        let drain = {
+            let permits = permits.clone();
+            tokio::spawn(async move { permits.acquire_many(Priority::High, 2).await.len() })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!drain.is_finished());
+
+        drop(held);
+        assert_eq!(drain.await.unwrap(), 2);
+        assert_eq!(permits.available(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth_tracks_waiters() {
+        let permits = PriorityPermits::new(1);
+        let held = permits.acquire(Priority::Normal).await;
+        assert_eq!(permits.queued(), 0);
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let permits = permits.clone();
+                tokio::spawn(async move { drop(permits.acquire(Priority::Normal).await) })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(permits.queued(), 2);
+
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(permits.queued(), 0);
+    }
+
+    #[test]
+    fn test_wait_histogram_buckets() {
+        let histogram = WaitHistogram::default();
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_millis(300));
+        histogram.record(Duration::from_secs(60));
+
+        let stats = histogram.snapshot();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.total_ms, 60_303);
+        assert_eq!(stats.buckets[0], WaitBucket { le_ms: Some(10), count: 1 });
+        assert_eq!(stats.buckets[4], WaitBucket { le_ms: Some(500), count: 1 });
+        assert_eq!(stats.buckets.last(), Some(&WaitBucket { le_ms: None, count: 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_large_batch_leaves_pages_for_single_requests() {
+        let permits = PriorityPermits::new(4);
+        let admission = Arc::new(BatchAdmission::new(2));
+        let gate = Arc::new(tokio::sync::RwLock::new(()));
+        let closed = gate.clone().write_owned().await;
+
+        let batch: Vec<_> = (0..20)
+            .map(|_| {
+                let permits = permits.clone();
+                let admission = admission.clone();
+                let gate = gate.clone();
+                tokio::spawn(async move {
+                    let _slot = admission.admit().await;
+                    let _page = permits.acquire(Priority::Low).await;
+                    let _open = gate.read().await;
+                })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(permits.available(), 2);
+        let single = tokio::time::timeout(Duration::from_millis(100), async {
+            (permits.acquire(Priority::Normal).await, permits.acquire(Priority::Normal).await)
+        })
+        .await;
+        assert!(single.is_ok(), "single requests waited behind the batch");
+        drop(single);
+
+        drop(closed);
+        for task in batch {
+            task.await.unwrap();
+        }
+        assert_eq!(permits.available(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_does_not_leak_permit() {
+        let permits = PriorityPermits::new(1);
+        let held = permits.acquire(Priority::Normal).await;
+
+        let cancelled = {
+            let permits = permits.clone();
+            tokio::spawn(async move { permits.acquire(Priority::High).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        drop(held);
+        assert_eq!(permits.available(), 1);
+        let _again = permits.acquire(Priority::Low).await;
+        assert_eq!(permits.available(), 0);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::models::LoadEvent;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Reports pipeline stages to a streaming client. A disabled reporter is a
+/// no-op, so the regular request paths pay nothing for it.
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    sender: Option<UnboundedSender<LoadEvent>>,
+}
+
+impl ProgressReporter {
+    pub fn new(sender: UnboundedSender<LoadEvent>) -> Self {
+        Self { sender: Some(sender) }
+    }
+
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn emit(&self, event: LoadEvent) {
+        if let Some(ref sender) = self.sender {
+            let _ = sender.send(event);
+        }
+    }
+}
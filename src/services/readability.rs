@@ -1,11 +1,170 @@
 use crate::error::{AppError, Result};
 use crate::models::{ExtractedContent, PageSnapshot};
-use readability::extractor;
-use scraper::{Html, Selector};
+use crate::services::markdown::PARAGRAPH_ELLIPSIS;
+use crate::services::ScraperService;
+use lazy_static::lazy_static;
+use readability::extractor::{self, Product};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::any::Any;
 use std::io::Cursor;
-use tracing::debug;
+use tracing::{debug, warn};
 use url::Url;
 
+lazy_static! {
+    static ref PREFORMATTED: Regex =
+        Regex::new(r"(?is)<pre\b[^>]*>.*?</pre>|<textarea\b[^>]*>.*?</textarea>").unwrap();
+    static ref INLINE_DISPLAY_NONE: Regex = Regex::new(r"(?i)display\s*:\s*none").unwrap();
+    static ref INLINE_VISIBILITY_HIDDEN: Regex = Regex::new(r"(?i)visibility\s*:\s*hidden").unwrap();
+    static ref INLINE_VISIBILITY_VISIBLE: Regex = Regex::new(r"(?i)visibility\s*:\s*visible").unwrap();
+}
+
+/// Elements that start and end a block of text for `x-max-paragraphs`, so
+/// plain text is cut at the same places as its markdown.
+const TEXT_BLOCK_ELEMENTS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "blockquote", "ul", "ol", "dl", "table",
+    "figure", "div", "section", "article", "header", "footer", "main", "aside", "nav", "hr", "br",
+];
+
+/// Never rendered as text, so not a block of their own.
+const TEXT_SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template"];
+
+/// Placeholder for a protected block; it contains no whitespace or markup
+/// so whitespace and tag rewrites leave it alone.
+const PREFORMATTED_PLACEHOLDER: &str = "\u{1A}PRE";
+
+/// Runs `transform` over `html` with every `<pre>` and `<textarea>` block
+/// swapped for a placeholder, then puts the blocks back untouched.
+pub fn protect_preformatted(html: &str, transform: impl FnOnce(&str) -> String) -> String {
+    let mut blocks = Vec::new();
+    let masked = PREFORMATTED.replace_all(html, |caps: &regex::Captures| {
+        blocks.push(caps[0].to_string());
+        format!("{}{}\u{1A}", PREFORMATTED_PLACEHOLDER, blocks.len() - 1)
+    });
+    if blocks.is_empty() {
+        return transform(html);
+    }
+
+    let mut result = transform(&masked);
+    for (i, block) in blocks.iter().enumerate() {
+        result = result.replacen(&format!("{}{}\u{1A}", PREFORMATTED_PLACEHOLDER, i), block, 1);
+    }
+    result
+}
+
+/// Elements kept by `sanitize_html`. Anything else is unwrapped to its
+/// children, or dropped with them when listed in `SANITIZE_DROP_TAGS`.
+const SANITIZE_ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "article", "b", "blockquote", "br", "caption", "cite", "code", "dd", "del",
+    "div", "dl", "dt", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr",
+    "i", "img", "ins", "kbd", "li", "mark", "ol", "p", "pre", "q", "s", "samp", "section",
+    "small", "span", "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th", "thead",
+    "time", "tr", "u", "ul",
+];
+
+/// Elements removed together with their content rather than unwrapped.
+const SANITIZE_DROP_TAGS: &[&str] = &[
+    "base", "button", "canvas", "embed", "form", "frame", "frameset", "head", "iframe", "input",
+    "link", "math", "meta", "noscript", "object", "script", "select", "style", "svg",
+    "template", "textarea", "title",
+];
+
+const SANITIZE_VOID_TAGS: &[&str] = &["br", "hr", "img"];
+
+/// Attributes kept on any allowed element.
+const SANITIZE_GLOBAL_ATTRS: &[&str] = &["title", "lang", "dir"];
+
+/// Attributes holding a URL; only relative, `http(s)` and `mailto` values
+/// are kept.
+const SANITIZE_URL_ATTRS: &[&str] = &["href", "src", "cite"];
+
+fn sanitize_attr_allowed(tag: &str, attr: &str) -> bool {
+    SANITIZE_GLOBAL_ATTRS.contains(&attr)
+        || matches!(
+            (tag, attr),
+            ("a", "href")
+                | ("img", "src" | "alt" | "width" | "height")
+                | ("td" | "th", "colspan" | "rowspan")
+                | ("th", "scope")
+                | ("ol", "start" | "reversed")
+                | ("blockquote" | "q" | "del" | "ins", "cite")
+                | ("time" | "del" | "ins", "datetime")
+        )
+}
+
+fn is_safe_url(value: &str) -> bool {
+    let value: String = value.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    match value.find([':', '/', '?', '#']) {
+        Some(i) if value[i..].starts_with(':') => {
+            matches!(value[..i].to_ascii_lowercase().as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn write_sanitized(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => escape_html(text, out),
+            Node::Element(el) => {
+                let tag = el.name();
+                if SANITIZE_DROP_TAGS.contains(&tag) {
+                    continue;
+                }
+                let child = ElementRef::wrap(child).expect("element node");
+                if !SANITIZE_ALLOWED_TAGS.contains(&tag) {
+                    write_sanitized(child, out);
+                    continue;
+                }
+
+                out.push('<');
+                out.push_str(tag);
+                for (name, value) in el.attrs() {
+                    let name = name.to_ascii_lowercase();
+                    if !sanitize_attr_allowed(tag, &name)
+                        || (SANITIZE_URL_ATTRS.contains(&name.as_str()) && !is_safe_url(value))
+                    {
+                        continue;
+                    }
+                    out.push(' ');
+                    out.push_str(&name);
+                    out.push_str("=\"");
+                    escape_html(value, out);
+                    out.push('"');
+                }
+                out.push('>');
+
+                if !SANITIZE_VOID_TAGS.contains(&tag) {
+                    write_sanitized(child, out);
+                    out.push_str("</");
+                    out.push_str(tag);
+                    out.push('>');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The message a panic was raised with, when it is a plain string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
+
 pub struct ReadabilityService;
 
 impl ReadabilityService {
@@ -13,18 +172,48 @@ impl ReadabilityService {
         Self
     }
 
-    pub fn extract_content(&self, snapshot: &PageSnapshot) -> Result<ExtractedContent> {
+    pub async fn extract_content(&self, snapshot: &PageSnapshot) -> Result<ExtractedContent> {
+        self.extract_content_with(snapshot, |html, url| {
+            extractor::extract(&mut Cursor::new(html.as_bytes()), &url)
+        })
+        .await
+    }
+
+    /// `extract_content` with the readability call passed in. The call runs
+    /// on the blocking pool: the crate can panic on malformed markup, and a
+    /// panic comes back in the join error, where it is treated like any
+    /// other extraction failure, falling back to the raw HTML with the
+    /// reason recorded, rather than unwinding through the request.
+    async fn extract_content_with<F>(&self, snapshot: &PageSnapshot, extract: F) -> Result<ExtractedContent>
+    where
+        F: FnOnce(String, Url) -> std::result::Result<Product, readability::error::Error> + Send + 'static,
+    {
         let url = Url::parse(&snapshot.url)
             .map_err(|e| AppError::ExtractionError(format!("Invalid URL: {}", e)))?;
 
-        let mut cursor = Cursor::new(snapshot.html.as_bytes());
+        let html = snapshot.html.clone();
+        let task = tokio::task::spawn_blocking(move || extract(html, url).map_err(|e| e.to_string()));
+        let extracted = match task.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => {
+                let reason = format!("readability panicked: {}", panic_message(e.into_panic().as_ref()));
+                warn!("{} on {}, using raw HTML", reason, snapshot.url);
+                Err(reason)
+            }
+            Err(e) => Err(format!("readability task failed: {}", e)),
+        };
 
-        match extractor::extract(&mut cursor, &url) {
+        match extracted {
             Ok(product) => {
                 debug!("Readability extraction successful");
 
                 let text_content = self.extract_text(&product.content);
 
+                // `Product` carries no byline or excerpt of its own, so both
+                // come from the page metadata, as readability.js does.
+                let excerpt = snapshot.description.clone()
+                    .or_else(|| self.first_paragraph(&product.content, snapshot.byline.as_deref()));
+
                 Ok(ExtractedContent {
                     url: snapshot.url.clone(),
                     title: if product.title.is_empty() {
@@ -35,8 +224,11 @@ impl ReadabilityService {
                     content: product.content,
                     text_content,
                     published_time: snapshot.published_time.clone(),
+                    byline: snapshot.byline.clone(),
+                    excerpt,
                     images: snapshot.images.clone(),
                     links: snapshot.links.clone(),
+                    fallback_reason: None,
                 })
             }
             Err(e) => {
@@ -49,8 +241,11 @@ impl ReadabilityService {
                     content: snapshot.html.clone(),
                     text_content,
                     published_time: snapshot.published_time.clone(),
+                    byline: snapshot.byline.clone(),
+                    excerpt: snapshot.description.clone(),
                     images: snapshot.images.clone(),
                     links: snapshot.links.clone(),
+                    fallback_reason: Some(e),
                 })
             }
         }
@@ -65,11 +260,27 @@ impl ReadabilityService {
             content: snapshot.html.clone(),
             text_content,
             published_time: snapshot.published_time.clone(),
+            byline: snapshot.byline.clone(),
+            excerpt: snapshot.description.clone(),
             images: snapshot.images.clone(),
             links: snapshot.links.clone(),
+            fallback_reason: None,
         }
     }
 
+    /// First non-empty paragraph, skipping one that is just the byline.
+    fn first_paragraph(&self, html: &str, byline: Option<&str>) -> Option<String> {
+        let selector = Selector::parse("p").ok()?;
+        let is_byline = |text: &str| {
+            byline.is_some_and(|b| text.ends_with(b) && text.len() <= b.len() + "By ".len())
+        };
+        Html::parse_fragment(html)
+            .select(&selector)
+            .map(|p| p.text().collect::<Vec<_>>().join(" "))
+            .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find(|text| !text.is_empty() && !is_byline(text))
+    }
+
     fn extract_text(&self, html: &str) -> String {
         let document = Html::parse_document(html);
 
@@ -84,39 +295,113 @@ impl ReadabilityService {
             .join(" ")
     }
 
-    pub fn clean_html(&self, html: &str) -> String {
-        use regex::Regex;
-
-        let body_html = self.extract_body(html);
-
-        let script_re = Regex::new(r"(?is)<script[^>]*>.*?</script>").unwrap();
-        let cleaned = script_re.replace_all(&body_html, "").to_string();
-
-        let style_re = Regex::new(r"(?is)<style[^>]*>.*?</style>").unwrap();
-        let cleaned = style_re.replace_all(&cleaned, "").to_string();
-
-        let noscript_re = Regex::new(r"(?is)<noscript[^>]*>.*?</noscript>").unwrap();
-        let cleaned = noscript_re.replace_all(&cleaned, "").to_string();
+    /// [`Self::extract_text`], keeping only the first `max` blocks of text
+    /// and ending with `[…]` when anything was cut, the way
+    /// `x-max-paragraphs` cuts markdown. Headings are kept but not counted.
+    pub fn extract_text_capped(&self, html: &str, max: usize) -> String {
+        fn collect(element: ElementRef, blocks: &mut Vec<(String, bool)>, current: &mut String) {
+            for child in element.children() {
+                if let Some(text) = child.value().as_text() {
+                    current.push(' ');
+                    current.push_str(text);
+                    continue;
+                }
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                let name = child.value().name();
+                if TEXT_SKIPPED_ELEMENTS.contains(&name) {
+                    continue;
+                }
+                if !TEXT_BLOCK_ELEMENTS.contains(&name) {
+                    collect(child, blocks, current);
+                    continue;
+                }
+                flush(blocks, current, false);
+                collect(child, blocks, current);
+                flush(blocks, current, matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6"));
+            }
+        }
 
-        let svg_re = Regex::new(r"(?is)<svg[^>]*>.*?</svg>").unwrap();
-        let cleaned = svg_re.replace_all(&cleaned, "").to_string();
+        fn flush(blocks: &mut Vec<(String, bool)>, current: &mut String, heading: bool) {
+            let text = current.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                blocks.push((text, heading));
+            }
+            current.clear();
+        }
 
-        let canvas_re = Regex::new(r"(?is)<canvas[^>]*>.*?</canvas>").unwrap();
-        let cleaned = canvas_re.replace_all(&cleaned, "").to_string();
+        let document = Html::parse_document(html);
+        let mut blocks = Vec::new();
+        let mut current = String::new();
+        collect(document.root_element(), &mut blocks, &mut current);
+        flush(&mut blocks, &mut current, false);
+
+        let mut kept = Vec::new();
+        let mut counted = 0;
+        for (i, (text, heading)) in blocks.iter().enumerate() {
+            if counted == max {
+                if blocks[i..].iter().any(|(_, heading)| !heading) {
+                    kept.push(PARAGRAPH_ELLIPSIS);
+                }
+                break;
+            }
+            if !heading {
+                counted += 1;
+            }
+            kept.push(text.as_str());
+        }
+        kept.join(" ")
+    }
 
-        let comment_re = Regex::new(r"(?is)<!--.*?-->").unwrap();
-        let cleaned = comment_re.replace_all(&cleaned, "").to_string();
+    /// Drop elements that never carry readable content (scripts, styles and
+    /// the like) but leave everything else, including navigation, in place.
+    pub fn strip_non_content(&self, html: &str) -> String {
+        let mut document = Html::parse_document(html);
+        if let Ok(selector) = Selector::parse("script, style, noscript, template") {
+            ScraperService::detach_matching(&mut document, &selector);
+        }
+        document.root_element().html()
+    }
 
-        let data_attr_re = Regex::new(r#"\s+data-[a-z0-9-]+="[^"]*""#).unwrap();
-        let cleaned = data_attr_re.replace_all(&cleaned, "").to_string();
+    /// `strip_hidden` also drops elements hidden by the `hidden` attribute
+    /// or an inline style; stylesheet rules are only visible to the browser.
+    /// An inline `visibility:hidden` element is kept when a descendant sets
+    /// `visibility:visible`, since that descendant still renders.
+    pub fn clean_html(&self, html: &str, strip_hidden: bool) -> String {
+        let body_html = self.extract_body(html);
 
-        let event_re = Regex::new(r#"\s+on[a-z]+="[^"]*""#).unwrap();
-        let cleaned = event_re.replace_all(&cleaned, "").to_string();
+        let mut fragment = Html::parse_fragment(&body_html);
 
-        let document = Html::parse_document(&cleaned);
-        let mut result = cleaned.clone();
+        if strip_hidden {
+            if let Ok(selector) = Selector::parse("[hidden]") {
+                ScraperService::detach_matching(&mut fragment, &selector);
+            }
+            fn style(node: &Node) -> Option<&str> {
+                node.as_element().and_then(|el| el.attr("style"))
+            }
+            let inline_hidden: Vec<_> = fragment
+                .tree
+                .nodes()
+                .filter(|node| match style(node.value()) {
+                    Some(s) if INLINE_DISPLAY_NONE.is_match(s) => true,
+                    Some(s) if INLINE_VISIBILITY_HIDDEN.is_match(s) => !node
+                        .descendants()
+                        .skip(1)
+                        .any(|d| style(d.value()).is_some_and(|s| INLINE_VISIBILITY_VISIBLE.is_match(s))),
+                    _ => false,
+                })
+                .map(|node| node.id())
+                .collect();
+            for id in inline_hidden {
+                if let Some(mut node) = fragment.tree.get_mut(id) {
+                    node.detach();
+                }
+            }
+        }
 
         let selectors_to_remove = [
+            "script", "style", "noscript", "svg", "canvas",
             "nav",
             "footer",
             "header:not(article header)",
@@ -133,21 +418,49 @@ impl ReadabilityService {
 
         for selector_str in &selectors_to_remove {
             if let Ok(selector) = Selector::parse(selector_str) {
-                for element in document.select(&selector) {
-                    result = result.replace(&element.html(), "");
-                }
+                ScraperService::detach_matching(&mut fragment, &selector);
+            }
+        }
+
+        let comment_ids: Vec<_> = fragment
+            .tree
+            .nodes()
+            .filter(|node| node.value().is_comment())
+            .map(|node| node.id())
+            .collect();
+        for id in comment_ids {
+            if let Some(mut node) = fragment.tree.get_mut(id) {
+                node.detach();
             }
         }
 
+        let cleaned = fragment.root_element().inner_html();
+
+        let data_attr_re = Regex::new(r#"\s+data-[a-z0-9-]+="[^"]*""#).unwrap();
+        let cleaned = data_attr_re.replace_all(&cleaned, "").to_string();
+
+        let event_re = Regex::new(r#"\s+on[a-z]+="[^"]*""#).unwrap();
+        let cleaned = event_re.replace_all(&cleaned, "").to_string();
+
         let whitespace_re = Regex::new(r"\s+").unwrap();
-        let cleaned = whitespace_re.replace_all(&result, " ").to_string();
+        let cleaned = protect_preformatted(&cleaned, |html| whitespace_re.replace_all(html, " ").to_string());
 
         cleaned.trim().to_string()
     }
 
-    fn extract_body(&self, html: &str) -> String {
-        use regex::Regex;
+    /// `clean_html`, then rebuilt from an allowlist of tags and attributes so
+    /// it is safe to embed: no scripts, frames, forms, event handlers, inline
+    /// styles or `javascript:` URLs survive.
+    pub fn sanitize_html(&self, html: &str, strip_hidden: bool) -> String {
+        let cleaned = self.clean_html(html, strip_hidden);
+        let fragment = Html::parse_fragment(&cleaned);
+
+        let mut sanitized = String::with_capacity(cleaned.len());
+        write_sanitized(fragment.root_element(), &mut sanitized);
+        sanitized
+    }
 
+    fn extract_body(&self, html: &str) -> String {
         let body_re = Regex::new(r"(?is)<body[^>]*>(.*)</body>").unwrap();
         if let Some(captures) = body_re.captures(html) {
             if let Some(body_content) = captures.get(1) {
@@ -176,3 +489,102 @@ impl Default for ReadabilityService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CrawlerOptions;
+
+    #[test]
+    fn test_clean_html_removes_identical_asides_without_touching_siblings() {
+        let html = r#"<html><body>
+            <p>First paragraph</p>
+            <aside><p>Related</p></aside>
+            <p>Second paragraph</p>
+            <aside><p>Related</p></aside>
+        </body></html>"#;
+
+        let cleaned = ReadabilityService::new().clean_html(html, false);
+
+        assert!(!cleaned.contains("<aside"));
+        assert!(!cleaned.contains("Related"));
+        assert!(cleaned.contains("<p>First paragraph</p>"));
+        assert!(cleaned.contains("<p>Second paragraph</p>"));
+    }
+
+    #[test]
+    fn test_extract_text_capped_cuts_after_blocks() {
+        let html = "<html><head><title>Page</title></head><body><h2>Title</h2><p>One</p>\
+            <div>Two <em>words</em></div><pre>a\n\nb</pre><h2>Next</h2><p>Three</p></body></html>";
+        let service = ReadabilityService::new();
+
+        assert_eq!(service.extract_text_capped(html, 2), "Title One Two words […]");
+        assert_eq!(service.extract_text_capped(html, 3), "Title One Two words a b […]");
+        assert_eq!(service.extract_text_capped(html, 4), "Title One Two words a b Next Three");
+    }
+
+    #[test]
+    fn test_clean_html_strips_scripts_styles_and_comments() {
+        let html = r#"<html><body>
+            <script>var x = "<p>not content</p>";</script>
+            <style>p { color: red; }</style>
+            <!-- tracking -->
+            <p data-id="1" onclick="go()">Body</p>
+        </body></html>"#;
+
+        let cleaned = ReadabilityService::new().clean_html(html, false);
+
+        assert_eq!(cleaned, "<p>Body</p>");
+    }
+
+    #[test]
+    fn test_sanitize_html_keeps_only_allowlisted_markup() {
+        let html = r#"<html><body>
+            <p class="lead" style="color:red" onclick="steal()">Hello <b>world</b></p>
+            <script>alert(1)</script>
+            <a href="/about" onmouseover="x()" target="_blank">About</a>
+            <a href="JavaScript:alert(1)">Bad link</a>
+            <iframe src="https://evil.example/"></iframe>
+            <custom-card><p>Inside <em>custom</em></p></custom-card>
+            <img src="data:image/svg+xml,<svg onload=alert(1)>" alt="x"><img src="https://example.com/a.png" alt="A &quot;quote&quot;">
+            <form action="/login"><input name="user"><p>Form text</p></form>
+        </body></html>"#;
+
+        let sanitized = ReadabilityService::new().sanitize_html(html, false);
+
+        assert!(!sanitized.contains("<script") && !sanitized.contains("alert(1)"));
+        assert!(!sanitized.contains("onclick") && !sanitized.contains("onmouseover"));
+        assert!(!sanitized.contains("style=") && !sanitized.contains("class=") && !sanitized.contains("target="));
+        assert!(!sanitized.contains("iframe") && !sanitized.contains("Form text"));
+        assert!(sanitized.contains("<p>Hello <b>world</b></p>"), "{}", sanitized);
+        assert!(sanitized.contains(r#"<a href="/about">About</a>"#));
+        assert!(sanitized.contains("<a>Bad link</a>"));
+        assert!(sanitized.contains("<p>Inside <em>custom</em></p>"));
+        assert!(sanitized.contains(r#"<img alt="x">"#));
+        assert!(sanitized.contains(r#"<img src="https://example.com/a.png" alt="A &quot;quote&quot;">"#));
+    }
+
+    #[tokio::test]
+    async fn test_readability_panic_falls_back_to_raw_html() {
+        // Unclosed, misnested tags and a stray end tag: html5ever repairs
+        // them, and whatever readability makes of the result must not panic.
+        let html = "<html><body><table><p>Broken <b>row<td></p></table></div>\
+            <article><h1>Title<p>Malformed body text</article></b><select><svg><p>End";
+        let options = CrawlerOptions::new("https://example.com/broken".to_string());
+        let snapshot = ScraperService::new().parse_html(html, &options).unwrap();
+        let service = ReadabilityService::new();
+
+        assert!(service.extract_content(&snapshot).await.is_ok());
+
+        let extracted = service
+            .extract_content_with(&snapshot, |_, _| panic!("index out of bounds"))
+            .await
+            .unwrap();
+        assert_eq!(extracted.content, snapshot.html);
+        assert!(extracted.text_content.contains("Malformed body text"));
+        assert_eq!(
+            extracted.fallback_reason.as_deref(),
+            Some("readability panicked: index out of bounds"),
+        );
+    }
+}
@@ -1,74 +1,258 @@
 use crate::error::{AppError, Result};
-use crate::models::{ComplexityMetrics, CrawlerOptions, ImageData, LinkData, PageSnapshot};
-use scraper::{Html, Selector};
+use crate::models::{
+    ComplexityMetrics, CrawlerOptions, ImageData, LinkData, MetaRobots, OpenGraph, PageSnapshot,
+    ResponseFormat, TargetMode,
+};
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{HashMap, HashSet};
 use tracing::debug;
 
-pub struct ScraperService;
+/// Nesting depth at which `calculate_list_depth` stops counting.
+const MAX_LIST_DEPTH: usize = 32;
+
+/// Longer "author" elements are usually bios, not bylines.
+const MAX_BYLINE_CHARS: usize = 100;
+
+/// A page with a subscribe prompt and fewer words than this is treated as a
+/// paywall teaser; full articles that merely link to a subscription are longer.
+const PAYWALL_TEASER_MAX_WORDS: usize = 400;
+
+/// Pages this short that carry a not-found phrase are treated as soft 404s.
+const SOFT_404_MAX_WORDS: usize = 500;
+
+/// Non-whitespace characters a `<p>` needs to count as a prose paragraph.
+const PARAGRAPH_MIN_CHARS: usize = 80;
+
+const FEED_TYPE_RSS: &str = "application/rss+xml";
+const FEED_TYPE_ATOM: &str = "application/atom+xml";
+
+const SUBSCRIBE_PROMPTS: &[&str] = &[
+    "subscribe to continue",
+    "subscribe to read",
+    "subscribe to unlock",
+    "subscribers only",
+    "already a subscriber",
+    "to continue reading",
+    "this article is for subscribers",
+];
+
+/// Non-whitespace characters of text under `element`, leaving out scripts,
+/// styles and templates.
+fn visible_text_chars(element: ElementRef) -> usize {
+    element.descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node.ancestors().any(|a| a.value().as_element().is_some_and(|el| {
+                matches!(el.name(), "script" | "style" | "noscript" | "template")
+            }));
+            (!hidden).then(|| text.chars().filter(|c| !c.is_whitespace()).count())
+        })
+        .sum()
+}
+
+fn strip_by_prefix(byline: &str) -> String {
+    let trimmed = byline.trim();
+    match trimmed.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("by ") => trimmed[3..].trim().to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Image and link attributes as they appear in the document, before they are
+/// turned into [`ImageData`] and [`LinkData`]. Plain owned data, so the
+/// conversion can carry on from the parsed page while another thread
+/// resolves them with [`ScraperService::resolve_assets`].
+#[derive(Debug, Default)]
+pub struct RawAssets {
+    images: Vec<RawImage>,
+    links: Vec<RawLink>,
+}
+
+#[derive(Debug)]
+struct RawImage {
+    src: String,
+    alt: Option<String>,
+    width: Option<String>,
+    height: Option<String>,
+}
+
+#[derive(Debug)]
+struct RawLink {
+    href: String,
+    text: String,
+    rel: Option<String>,
+}
+
+pub struct ScraperService {
+    paywall_selector: Option<Selector>,
+    soft_404_phrases: Vec<String>,
+}
 
 impl ScraperService {
     pub fn new() -> Self {
-        Self
+        Self { paywall_selector: None, soft_404_phrases: Vec::new() }
+    }
+
+    /// Mark pages matching the CSS selector list `selectors` as paywalled.
+    /// An unparsable list leaves only the subscribe-prompt heuristic.
+    pub fn with_paywall_selectors(mut self, selectors: &str) -> Self {
+        self.paywall_selector = Selector::parse(selectors).ok();
+        self
+    }
+
+    /// Mark short pages whose title or body contains one of `phrases` as
+    /// soft 404s. Matching is case-insensitive.
+    pub fn with_soft_404_phrases(mut self, phrases: &[String]) -> Self {
+        self.soft_404_phrases = phrases.iter().map(|p| p.to_lowercase()).collect();
+        self
     }
 
     pub fn parse_html(&self, html: &str, options: &CrawlerOptions) -> Result<PageSnapshot> {
+        let (mut snapshot, assets) = self.parse_page(html, options)?;
+        (snapshot.images, snapshot.links) = self.resolve_assets(assets, options);
+        Ok(snapshot)
+    }
+
+    /// [`Self::parse_html`] with images and links left as [`RawAssets`],
+    /// read from the same parse. The snapshot's `images` and `links` are
+    /// empty until the assets are resolved.
+    pub fn parse_page(&self, html: &str, options: &CrawlerOptions) -> Result<(PageSnapshot, RawAssets)> {
         let document = Html::parse_document(html);
 
         let title = self.extract_title(&document);
 
         let published_time = self.extract_published_time(&document);
 
+        let mut warnings = Vec::new();
+
         let content_html = if let Some(ref selector_str) = options.target_selector {
-            self.extract_targeted_content(&document, selector_str)?
+            match options.target_mode {
+                TargetMode::First => self.extract_targeted_content(&document, selector_str, &mut warnings)?,
+                TargetMode::All => self.extract_all_targets(&document, selector_str)?,
+            }
         } else {
             html.to_string()
         };
 
         let final_html = if let Some(ref selector_str) = options.remove_selector {
-            self.remove_elements(&content_html, selector_str)?
+            self.remove_elements(&content_html, selector_str, options.target_selector.is_some())?
         } else {
             content_html
         };
 
-        let images = self.extract_images(&document, options.keep_img_data_url);
+        let robots = self.extract_meta_robots(&document);
 
         let base_url = &options.url;
-        let links = self.extract_links(&document, base_url);
+        let assets = RawAssets {
+            images: self.collect_images(&document),
+            links: if options.respect_meta_robots && robots.nofollow {
+                debug!("Dropping links for {}: meta robots nofollow", options.url);
+                Vec::new()
+            } else {
+                self.collect_links(&document)
+            },
+        };
 
         let has_pdf = self.detect_pdf(&document);
 
-        Ok(PageSnapshot {
+        let open_graph = self.extract_open_graph(&document, base_url);
+
+        let feeds = self.extract_feeds(&document, base_url);
+
+        let byline = self.extract_byline(&document);
+
+        let description = self.extract_meta_content(&document, "meta[name='description']")
+            .or_else(|| open_graph.description.clone());
+
+        let (body_text, body_words) = Self::body_text(&document);
+
+        let paywalled = self.detect_paywall(&document, &body_text, body_words);
+        if paywalled {
+            debug!("Paywall detected on {}", options.url);
+        }
+
+        let soft_404 = self.detect_soft_404(title.as_deref(), &body_text, body_words);
+        if soft_404 {
+            debug!("{} looks like a not-found page", options.url);
+        }
+
+        // An explicit `x-raw-extract` or `markdown` is taken as given.
+        let extraction = (options.respond_with == ResponseFormat::Default && !options.raw_extract)
+            .then(|| self.complexity_of(&document).extraction_strategy());
+
+        let snapshot = PageSnapshot {
             url: options.url.clone(),
             html: final_html,
             title,
             published_time,
-            images,
-            links,
+            byline,
+            description,
+            images: Vec::new(),
+            links: Vec::new(),
             has_pdf,
-        })
+            robots,
+            open_graph,
+            feeds,
+            paywalled,
+            soft_404,
+            extraction,
+            warnings,
+        };
+        Ok((snapshot, assets))
+    }
+
+    /// Turn the assets read by [`Self::parse_page`] into the snapshot's
+    /// images and links. Needs no document, so it can run alongside the
+    /// content conversion.
+    pub fn resolve_assets(&self, assets: RawAssets, options: &CrawlerOptions) -> (Vec<ImageData>, Vec<LinkData>) {
+        let images = self.resolve_images(assets.images, options.keep_img_data_url, options.max_data_url_bytes);
+        let links = self.resolve_links(assets.links, &options.url);
+        (images, links)
     }
 
     pub fn calculate_complexity(&self, html: &str) -> ComplexityMetrics {
-        let document = Html::parse_document(html);
+        self.complexity_of(&Html::parse_document(html))
+    }
+
+    fn complexity_of(&self, document: &Html) -> ComplexityMetrics {
         let mut metrics = ComplexityMetrics::default();
 
         if let Ok(selector) = Selector::parse("table") {
             metrics.table_count = document.select(&selector).count();
         }
 
-        metrics.max_list_depth = self.calculate_list_depth(&document);
+        metrics.max_list_depth = self.calculate_list_depth(document);
 
         if let Ok(selector) = Selector::parse("pre, code") {
             metrics.code_block_count = document.select(&selector).count();
         }
 
-        metrics.has_math = self.detect_math(&document);
+        metrics.has_math = self.detect_math(document);
 
-        metrics.is_non_english = self.detect_non_english(&document);
+        metrics.is_non_english = self.detect_non_english(document);
 
         if let Ok(selector) = Selector::parse("*") {
             metrics.total_elements = document.select(&selector).count();
         }
 
+        if let Ok(selector) = Selector::parse("body") {
+            metrics.text_chars = document.select(&selector).map(visible_text_chars).sum();
+        }
+        if let Ok(selector) = Selector::parse("a[href]") {
+            metrics.link_text_chars = document.select(&selector).map(visible_text_chars).sum();
+        }
+        if let Ok(selector) = Selector::parse("table") {
+            metrics.table_text_chars = document.select(&selector)
+                .filter(|table| !table.ancestors().any(|a| a.value().as_element().is_some_and(|el| el.name() == "table")))
+                .map(visible_text_chars)
+                .sum();
+        }
+        if let Ok(selector) = Selector::parse("p") {
+            metrics.paragraph_count = document.select(&selector)
+                .filter(|p| visible_text_chars(*p) >= PARAGRAPH_MIN_CHARS)
+                .count();
+        }
+
         debug!("Complexity metrics: {:?}", metrics);
         metrics
     }
@@ -96,6 +280,115 @@ impl ScraperService {
         None
     }
 
+    /// `<link rel="alternate">` targets typed as RSS or Atom, resolved
+    /// against the page URL, in document order and without duplicates.
+    fn extract_feeds(&self, document: &Html, base_url: &str) -> Vec<String> {
+        let selector = match Selector::parse("link[rel][type][href]") {
+            Ok(s) => s,
+            Err(_) => return Vec::new(),
+        };
+        let base = url::Url::parse(base_url).ok();
+
+        let mut feeds: Vec<String> = Vec::new();
+        for link in document.select(&selector) {
+            let el = link.value();
+            let alternate = el.attr("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("alternate")));
+            let feed_type = el.attr("type").map(|t| t.trim().to_ascii_lowercase());
+            if !alternate || !matches!(feed_type.as_deref(), Some(FEED_TYPE_RSS | FEED_TYPE_ATOM)) {
+                continue;
+            }
+
+            let href = el.attr("href").unwrap_or_default().trim();
+            if href.is_empty() {
+                continue;
+            }
+            let resolved = match &base {
+                Some(base) => match base.join(href) {
+                    Ok(url) => url.to_string(),
+                    Err(_) => continue,
+                },
+                None => href.to_string(),
+            };
+            if !feeds.contains(&resolved) {
+                feeds.push(resolved);
+            }
+        }
+        feeds
+    }
+
+    /// `og:*` tags use `property` and `twitter:*` tags usually `name`, but
+    /// both spellings are common in the wild, so either attribute matches.
+    fn extract_open_graph(&self, document: &Html, base_url: &str) -> OpenGraph {
+        let mut tags: HashMap<String, String> = HashMap::new();
+
+        if let Ok(selector) = Selector::parse("meta[content]") {
+            for meta in document.select(&selector) {
+                let key = meta.value().attr("property").or_else(|| meta.value().attr("name"));
+                let content = meta.value().attr("content").map(str::trim).unwrap_or_default();
+                if let Some(key) = key {
+                    let key = key.trim().to_lowercase();
+                    if (key.starts_with("og:") || key.starts_with("twitter:")) && !content.is_empty() {
+                        tags.entry(key).or_insert_with(|| content.to_string());
+                    }
+                }
+            }
+        }
+
+        let base = url::Url::parse(base_url).ok();
+        let absolute = |raw: &String| match &base {
+            Some(base) => base.join(raw).map(|u| u.to_string()).unwrap_or_else(|_| raw.clone()),
+            None => raw.clone(),
+        };
+
+        let image = ["og:image:secure_url", "og:image", "og:image:url", "twitter:image"]
+            .iter()
+            .find_map(|key| tags.get(*key))
+            .map(absolute);
+        let tag = |key: &str| tags.get(key).cloned();
+
+        OpenGraph {
+            title: tag("og:title").or_else(|| tag("twitter:title")),
+            description: tag("og:description").or_else(|| tag("twitter:description")),
+            og_type: tag("og:type"),
+            url: tag("og:url").map(|u| absolute(&u)),
+            site_name: tag("og:site_name"),
+            image,
+            image_alt: tag("og:image:alt").or_else(|| tag("twitter:image:alt")),
+            twitter_card: tag("twitter:card"),
+            twitter_site: tag("twitter:site"),
+        }
+    }
+
+    fn extract_meta_content(&self, document: &Html, selector: &str) -> Option<String> {
+        let selector = Selector::parse(selector).ok()?;
+        document
+            .select(&selector)
+            .filter_map(|el| el.value().attr("content"))
+            .map(|content| content.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find(|content| !content.is_empty())
+    }
+
+    /// Meta tags first, then the first short element marked as the author.
+    /// `article:author` is often a profile URL, which is skipped.
+    fn extract_byline(&self, document: &Html) -> Option<String> {
+        let from_meta = ["meta[name='author']", "meta[property='article:author']", "meta[name='byl']"]
+            .iter()
+            .filter_map(|selector| self.extract_meta_content(document, selector))
+            .find(|author| !author.starts_with("http://") && !author.starts_with("https://"));
+        if from_meta.is_some() {
+            return from_meta.map(|author| strip_by_prefix(&author));
+        }
+
+        let selector = Selector::parse("[rel='author'], [itemprop='author'], .byline, .author").ok()?;
+        document
+            .select(&selector)
+            .map(|el| el.text().collect::<Vec<_>>().join(" "))
+            .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .find(|text| !text.is_empty() && text.chars().count() <= MAX_BYLINE_CHARS)
+            .map(|text| strip_by_prefix(&text))
+    }
+
     fn extract_published_time(&self, document: &Html) -> Option<String> {
         let selectors = [
             "meta[property='article:published_time']",
@@ -122,75 +415,162 @@ impl ScraperService {
         None
     }
 
-    fn extract_targeted_content(&self, document: &Html, selector_str: &str) -> Result<String> {
+    fn extract_meta_robots(&self, document: &Html) -> MetaRobots {
+        let mut robots = MetaRobots::default();
+
+        if let Ok(selector) = Selector::parse("meta[name][content]") {
+            for element in document.select(&selector) {
+                let is_robots = element.value().attr("name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("robots"));
+                if !is_robots {
+                    continue;
+                }
+
+                let content = element.value().attr("content").unwrap_or_default().to_lowercase();
+                for directive in content.split(',').map(str::trim) {
+                    match directive {
+                        "noindex" => robots.noindex = true,
+                        "nofollow" => robots.nofollow = true,
+                        "none" => {
+                            robots.noindex = true;
+                            robots.nofollow = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        robots
+    }
+
+    /// `selector_str` is a fallback chain: comma-separated selectors are
+    /// tried in order and the first one that matches anything wins.
+    fn extract_targeted_content(
+        &self,
+        document: &Html,
+        selector_str: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<String> {
+        for (position, candidate) in Self::split_selector_list(selector_str).into_iter().enumerate() {
+            let selector = Selector::parse(candidate)
+                .map_err(|_| AppError::ScrapingError(format!("Invalid selector: {}", candidate)))?;
+
+            let mut content = String::new();
+            for element in document.select(&selector) {
+                content.push_str(&element.html());
+            }
+
+            if !content.is_empty() {
+                debug!("Target selector matched: {}", candidate);
+                if position > 0 {
+                    warnings.push(format!(
+                        "Target selector fell back to '{}'; earlier selectors in '{}' matched nothing",
+                        candidate, selector_str
+                    ));
+                }
+                return Ok(content);
+            }
+        }
+
+        Err(AppError::ScrapingError(format!(
+            "No content found for selector: {}",
+            selector_str
+        )))
+    }
+
+    /// Every element matched by any selector in the list, in document order.
+    /// An element inside another match is already part of it and is skipped.
+    fn extract_all_targets(&self, document: &Html, selector_str: &str) -> Result<String> {
         let selector = Selector::parse(selector_str)
             .map_err(|_| AppError::ScrapingError(format!("Invalid selector: {}", selector_str)))?;
 
-        let mut content = String::new();
-        for element in document.select(&selector) {
-            content.push_str(&element.html());
-        }
+        let matched: HashSet<_> = document.select(&selector).map(|e| e.id()).collect();
+        let content: String = document.select(&selector)
+            .filter(|element| !element.ancestors().any(|a| matched.contains(&a.id())))
+            .map(|element| element.html())
+            .collect();
 
         if content.is_empty() {
-            Err(AppError::ScrapingError(format!(
+            return Err(AppError::ScrapingError(format!(
                 "No content found for selector: {}",
                 selector_str
-            )))
-        } else {
-            Ok(content)
+            )));
         }
+        Ok(content)
     }
 
-    fn remove_elements(&self, html: &str, selector_str: &str) -> Result<String> {
-        let document = Html::parse_document(html);
+    /// Split a selector list on top-level commas, leaving commas inside
+    /// `:is(...)`, attribute brackets or quoted strings alone.
+    pub fn split_selector_list(selector_str: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+        let mut start = 0;
+
+        for (i, c) in selector_str.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(' | '[') => depth += 1,
+                (None, ')' | ']') => depth = depth.saturating_sub(1),
+                (None, ',') if depth == 0 => {
+                    parts.push(&selector_str[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&selector_str[start..]);
+
+        parts.into_iter()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    fn remove_elements(&self, html: &str, selector_str: &str, is_fragment: bool) -> Result<String> {
         let selector = Selector::parse(selector_str)
             .map_err(|_| AppError::ScrapingError(format!("Invalid selector: {}", selector_str)))?;
 
-        let elements_to_remove: Vec<String> = document
-            .select(&selector)
-            .map(|el| el.html())
-            .collect();
+        if is_fragment {
+            let mut fragment = Html::parse_fragment(html);
+            Self::detach_matching(&mut fragment, &selector);
+            Ok(fragment.root_element().inner_html())
+        } else {
+            let mut document = Html::parse_document(html);
+            Self::detach_matching(&mut document, &selector);
+            Ok(document.html())
+        }
+    }
 
-        let mut result = html.to_string();
-        for element in elements_to_remove {
-            result = result.replace(&element, "");
+    /// Detach every element matching `selector` from the parsed tree so that a
+    /// later serialization omits exactly those nodes. Returns how many matched.
+    pub fn detach_matching(document: &mut Html, selector: &Selector) -> usize {
+        let ids: Vec<_> = document.select(selector).map(|el| el.id()).collect();
+
+        for id in &ids {
+            if let Some(mut node) = document.tree.get_mut(*id) {
+                node.detach();
+            }
         }
 
-        Ok(result)
+        ids.len()
     }
 
-    fn extract_images(&self, document: &Html, keep_data_url: bool) -> Vec<ImageData> {
+    fn collect_images(&self, document: &Html) -> Vec<RawImage> {
         let mut images = Vec::new();
 
         if let Ok(selector) = Selector::parse("img") {
             for element in document.select(&selector) {
-                let src = element.value().attr("src")
-                    .or_else(|| element.value().attr("data-src"))
-                    .map(|s| s.to_string());
-
-                if let Some(src) = src {
-                    if src.starts_with("data:") && !keep_data_url {
-                        continue;
-                    }
-
-                    let alt = element.value().attr("alt").map(|s| s.to_string());
-                    let width = element.value().attr("width")
-                        .and_then(|w| w.parse().ok());
-                    let height = element.value().attr("height")
-                        .and_then(|h| h.parse().ok());
-
-                    let data_url = if src.starts_with("data:") && keep_data_url {
-                        Some(src.clone())
-                    } else {
-                        None
-                    };
-
-                    images.push(ImageData {
-                        src,
-                        alt,
-                        width,
-                        height,
-                        data_url,
+                let el = element.value();
+                if let Some(src) = el.attr("src").or_else(|| el.attr("data-src")) {
+                    images.push(RawImage {
+                        src: src.to_string(),
+                        alt: el.attr("alt").map(str::to_string),
+                        width: el.attr("width").map(str::to_string),
+                        height: el.attr("height").map(str::to_string),
                     });
                 }
             }
@@ -199,28 +579,61 @@ impl ScraperService {
         images
     }
 
-    fn extract_links(&self, document: &Html, base_url: &str) -> Vec<LinkData> {
+    fn resolve_images(
+        &self,
+        raw: Vec<RawImage>,
+        keep_data_url: bool,
+        max_data_url_bytes: Option<usize>,
+    ) -> Vec<ImageData> {
+        let mut images = Vec::new();
+
+        for RawImage { mut src, alt, width, height } in raw {
+            let is_data_url = src.starts_with("data:");
+            if is_data_url && !keep_data_url {
+                continue;
+            }
+
+            let width = width.and_then(|w| w.parse().ok());
+            let height = height.and_then(|h| h.parse().ok());
+
+            let oversized = is_data_url
+                && max_data_url_bytes.is_some_and(|max| src.len() > max);
+
+            let data_url = if is_data_url && !oversized {
+                Some(src.clone())
+            } else {
+                None
+            };
+
+            // Keep only the media type prefix (e.g. `data:image/png;base64,`)
+            // so the entry still says what the image was.
+            if oversized {
+                src.truncate(src.find(',').map_or(5, |i| i + 1));
+            }
+
+            images.push(ImageData {
+                src,
+                alt,
+                width,
+                height,
+                data_url,
+                data_url_dropped: oversized,
+            });
+        }
+
+        images
+    }
+
+    fn collect_links(&self, document: &Html) -> Vec<RawLink> {
         let mut links = Vec::new();
-        let base_domain = url::Url::parse(base_url)
-            .ok()
-            .and_then(|u| u.host_str().map(|s| s.to_string()));
 
         if let Ok(selector) = Selector::parse("a[href]") {
             for element in document.select(&selector) {
                 if let Some(href) = element.value().attr("href") {
-                    let text: String = element.text().collect();
-                    let text = text.trim().to_string();
-
-                    let is_internal = if let Some(ref domain) = base_domain {
-                        href.starts_with('/') || href.contains(domain)
-                    } else {
-                        href.starts_with('/')
-                    };
-
-                    links.push(LinkData {
+                    links.push(RawLink {
                         href: href.to_string(),
-                        text: if text.is_empty() { None } else { Some(text) },
-                        is_internal,
+                        text: element.text().collect(),
+                        rel: element.value().attr("rel").map(str::to_string),
                     });
                 }
             }
@@ -229,6 +642,149 @@ impl ScraperService {
         links
     }
 
+    fn resolve_links(&self, raw: Vec<RawLink>, base_url: &str) -> Vec<LinkData> {
+        let base_domain = url::Url::parse(base_url)
+            .ok()
+            .and_then(|u| u.host_str().map(|s| s.to_string()));
+
+        raw.into_iter()
+            .map(|RawLink { href, text, rel }| {
+                let text = text.trim().to_string();
+
+                let is_internal = if let Some(ref domain) = base_domain {
+                    href.starts_with('/') || href.contains(domain)
+                } else {
+                    href.starts_with('/')
+                };
+
+                let rel = rel
+                    .map(|rel| rel.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+                    .filter(|rel| !rel.is_empty());
+
+                LinkData {
+                    href,
+                    text: if text.is_empty() { None } else { Some(text) },
+                    rel,
+                    is_internal,
+                }
+            })
+            .collect()
+    }
+
+    /// Heuristic for Cloudflare-style "checking your browser" interstitials,
+    /// based on their well-known titles and challenge element ids.
+    pub fn is_challenge_page(html: &str) -> bool {
+        const TITLES: &[&str] = &[
+            "just a moment...",
+            "just a moment…",
+            "checking your browser",
+            "attention required! | cloudflare",
+            "ddos-guard",
+        ];
+        const SELECTORS: &str = "#cf-browser-verification, .cf-browser-verification, \
+            #challenge-form, #challenge-running, #cf-challenge-running, #challenge-stage";
+
+        let document = Html::parse_document(html);
+
+        if let Ok(selector) = Selector::parse("title") {
+            if let Some(title) = document.select(&selector).next() {
+                let title = title.text().collect::<String>().trim().to_lowercase();
+                if TITLES.iter().any(|t| title.starts_with(t)) {
+                    return true;
+                }
+            }
+        }
+
+        Selector::parse(SELECTORS)
+            .map(|selector| document.select(&selector).next().is_some())
+            .unwrap_or(false)
+    }
+
+    /// A configured paywall marker, `isAccessibleForFree: false` in the
+    /// page's structured data, or a short body carrying a subscribe prompt.
+    fn detect_paywall(&self, document: &Html, body_text: &str, body_words: usize) -> bool {
+        if let Some(selector) = &self.paywall_selector {
+            if document.select(selector).next().is_some() {
+                return true;
+            }
+        }
+
+        if let Ok(selector) = Selector::parse(r#"script[type="application/ld+json"]"#) {
+            let restricted = document.select(&selector).any(|script| {
+                let compact = script.text()
+                    .flat_map(str::chars)
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .to_lowercase();
+                compact.contains(r#""isaccessibleforfree":false"#)
+                    || compact.contains(r#""isaccessibleforfree":"false""#)
+            });
+            if restricted {
+                return true;
+            }
+        }
+
+        body_words < PAYWALL_TEASER_MAX_WORDS && SUBSCRIBE_PROMPTS.iter().any(|p| body_text.contains(p))
+    }
+
+    /// A page that answered 200 but reads like an error page: a configured
+    /// not-found phrase in the title or body, and little else on it.
+    fn detect_soft_404(&self, title: Option<&str>, body_text: &str, body_words: usize) -> bool {
+        if body_words >= SOFT_404_MAX_WORDS {
+            return false;
+        }
+        let title = title.unwrap_or_default().to_lowercase();
+        self.soft_404_phrases.iter()
+            .any(|phrase| title.contains(phrase.as_str()) || body_text.contains(phrase.as_str()))
+    }
+
+    /// Lowercased `<body>` text with whitespace collapsed, and its word count.
+    fn body_text(document: &Html) -> (String, usize) {
+        let Ok(selector) = Selector::parse("body") else { return (String::new(), 0) };
+        let Some(body) = document.select(&selector).next() else { return (String::new(), 0) };
+        let words: Vec<&str> = body.text().flat_map(str::split_whitespace).collect();
+        (words.join(" ").to_lowercase(), words.len())
+    }
+
+    /// Target of a `<meta http-equiv="refresh" content="N;url=...">` in
+    /// `html`, resolved against `base`. Refreshes without a URL, to the same
+    /// page, or to a non-http(s) scheme return `None`.
+    pub fn meta_refresh_target(html: &str, base: &url::Url) -> Option<url::Url> {
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("meta[http-equiv][content]").ok()?;
+
+        let content = document.select(&selector)
+            .find(|meta| {
+                meta.value().attr("http-equiv")
+                    .is_some_and(|v| v.trim().eq_ignore_ascii_case("refresh"))
+            })?
+            .value()
+            .attr("content")?;
+
+        let (_, rest) = content.split_once([';', ','])?;
+        let rest = rest.trim_start();
+        let target = match rest.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+                rest[3..].trim_start().strip_prefix('=').unwrap_or(rest)
+            }
+            _ => rest,
+        };
+        let target = target.trim().trim_matches(|c| c == '\'' || c == '"').trim();
+        if target.is_empty() {
+            return None;
+        }
+
+        let mut resolved = base.join(target).ok()?;
+        if !matches!(resolved.scheme(), "http" | "https") {
+            return None;
+        }
+        resolved.set_fragment(None);
+        let mut current = base.clone();
+        current.set_fragment(None);
+
+        (resolved != current).then_some(resolved)
+    }
+
     fn detect_pdf(&self, document: &Html) -> bool {
         if let Ok(selector) = Selector::parse("embed[type='application/pdf'], object[type='application/pdf'], iframe[src*='.pdf']") {
             if document.select(&selector).next().is_some() {
@@ -245,36 +801,43 @@ impl ScraperService {
         false
     }
 
+    /// Deepest `ul`/`ol` nesting, found in one iterative walk of the tree so
+    /// hostile markup cannot blow the stack. Saturates at `MAX_LIST_DEPTH`.
     fn calculate_list_depth(&self, document: &Html) -> usize {
-        let mut max_depth = 0;
+        let is_list = |node: &scraper::node::Node| {
+            node.as_element().is_some_and(|el| matches!(el.name(), "ul" | "ol"))
+        };
 
-        if let Ok(selector) = Selector::parse("ul, ol") {
-            for element in document.select(&selector) {
-                let depth = self.count_list_depth(element.html().as_str(), 0);
-                if depth > max_depth {
-                    max_depth = depth;
+        let mut node = document.tree.root();
+        let mut depth = 0;
+        let mut max_depth = 0;
+        loop {
+            if is_list(node.value()) {
+                depth += 1;
+                if depth >= MAX_LIST_DEPTH {
+                    debug!("List nesting reaches {}; not counting deeper", MAX_LIST_DEPTH);
+                    return MAX_LIST_DEPTH;
                 }
+                max_depth = max_depth.max(depth);
             }
-        }
-
-        max_depth
-    }
-
-    fn count_list_depth(&self, html: &str, current_depth: usize) -> usize {
-        let doc = Html::parse_fragment(html);
-        let mut max_depth = current_depth;
-
-        if let Ok(selector) = Selector::parse("ul, ol") {
-            for element in doc.select(&selector) {
-                let inner_html = element.inner_html();
-                let depth = self.count_list_depth(&inner_html, current_depth + 1);
-                if depth > max_depth {
-                    max_depth = depth;
+            if let Some(child) = node.first_child() {
+                node = child;
+                continue;
+            }
+            loop {
+                if is_list(node.value()) {
+                    depth -= 1;
+                }
+                if let Some(sibling) = node.next_sibling() {
+                    node = sibling;
+                    break;
+                }
+                match node.parent() {
+                    Some(parent) => node = parent,
+                    None => return max_depth,
                 }
             }
         }
-
-        max_depth
     }
 
     fn detect_math(&self, document: &Html) -> bool {
@@ -327,3 +890,307 @@ impl Default for ScraperService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(url: &str) -> CrawlerOptions {
+        CrawlerOptions::new(url.to_string())
+    }
+
+    #[test]
+    fn test_target_selector_falls_back_in_order() {
+        let scraper = ScraperService::new();
+        let html = r#"<html><body><main><p>Main body</p></main><div id="content"><p>Other</p></div></body></html>"#;
+
+        let mut opts = options("https://example.com");
+        opts.target_selector = Some("article, main, #content".to_string());
+        let snapshot = scraper.parse_html(html, &opts).unwrap();
+        assert!(snapshot.html.contains("Main body"));
+        assert!(!snapshot.html.contains("Other"));
+
+        opts.target_selector = Some("article, aside".to_string());
+        let err = scraper.parse_html(html, &opts).unwrap_err();
+        assert!(err.to_string().contains("No content found for selector: article, aside"));
+    }
+
+    #[test]
+    fn test_target_mode_all_concatenates_in_document_order() {
+        let scraper = ScraperService::new();
+        let html = r#"<html><body>
+            <p class="byline">By Ann</p>
+            <h1>Headline</h1>
+            <aside>Ads</aside>
+            <article><p>Body</p><p class="byline">Inner byline</p></article>
+            </body></html>"#;
+
+        let mut opts = options("https://example.com");
+        opts.target_selector = Some("article, h1, .byline".to_string());
+        opts.target_mode = TargetMode::All;
+        let snapshot = scraper.parse_html(html, &opts).unwrap();
+
+        let order: Vec<usize> = ["By Ann", "Headline", "Body"]
+            .iter()
+            .map(|text| snapshot.html.find(text).unwrap())
+            .collect();
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]), "{}", snapshot.html);
+        assert_eq!(snapshot.html.matches("Inner byline").count(), 1);
+        assert!(!snapshot.html.contains("Ads"));
+
+        opts.target_selector = Some("nav, footer".to_string());
+        assert!(scraper.parse_html(html, &opts).is_err());
+    }
+
+    #[test]
+    fn test_open_graph_tags_prefer_secure_image() {
+        let scraper = ScraperService::new();
+        let html = r#"<html><head>
+            <meta property="og:title" content="Launch day">
+            <meta property="og:type" content="article">
+            <meta property="og:site_name" content="Example News">
+            <meta property="og:image" content="http://cdn.example.com/card.png">
+            <meta property="og:image:secure_url" content="/img/card.png">
+            <meta name="twitter:card" content="summary_large_image">
+            <meta name="twitter:image" content="https://example.com/twitter.png">
+            <meta name="description" content="Not an og tag">
+        </head><body><p>Body</p></body></html>"#;
+
+        let og = scraper.parse_html(html, &options("https://example.com/posts/1")).unwrap().open_graph;
+        assert_eq!(og.title.as_deref(), Some("Launch day"));
+        assert_eq!(og.og_type.as_deref(), Some("article"));
+        assert_eq!(og.site_name.as_deref(), Some("Example News"));
+        assert_eq!(og.image.as_deref(), Some("https://example.com/img/card.png"));
+        assert_eq!(og.twitter_card.as_deref(), Some("summary_large_image"));
+        assert_eq!(og.description, None);
+
+        let bare = scraper.parse_html("<html><body><p>Body</p></body></html>", &options("https://example.com")).unwrap();
+        assert!(bare.open_graph.is_empty());
+    }
+
+    #[test]
+    fn test_list_depth_is_counted_and_capped() {
+        let scraper = ScraperService::new();
+        let nested = "<ul><li>a<ol><li>b</li></ol></li></ul><ul><li>c</li></ul>";
+        assert_eq!(scraper.calculate_complexity(nested).max_list_depth, 2);
+        assert_eq!(scraper.calculate_complexity("<p>no lists</p>").max_list_depth, 0);
+
+        let hostile = format!("{}x{}", "<ul><li>".repeat(1000), "</li></ul>".repeat(1000));
+        let start = std::time::Instant::now();
+        let metrics = scraper.calculate_complexity(&hostile);
+        assert_eq!(metrics.max_list_depth, MAX_LIST_DEPTH);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_split_selector_list_respects_nesting() {
+        assert_eq!(
+            ScraperService::split_selector_list("article, :is(main, .body) > p,[data-x=\"a,b\"]"),
+            vec!["article", ":is(main, .body) > p", "[data-x=\"a,b\"]"]
+        );
+        assert!(ScraperService::split_selector_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_oversized_data_url_is_dropped() {
+        let scraper = ScraperService::new();
+        let small = "data:image/gif;base64,R0lGODlhAQABAAAAACw=";
+        let large = format!("data:image/png;base64,{}", "A".repeat(4096));
+        let html = format!(
+            r#"<html><body><img src="{}" alt="dot"><img src="{}" alt="photo" width="800" height="600"></body></html>"#,
+            small, large
+        );
+
+        let mut opts = options("https://example.com");
+        opts.keep_img_data_url = true;
+        opts.max_data_url_bytes = Some(1024);
+        let images = scraper.parse_html(&html, &opts).unwrap().images;
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].data_url.as_deref(), Some(small));
+        assert!(!images[0].data_url_dropped);
+
+        assert!(images[1].data_url.is_none());
+        assert!(images[1].data_url_dropped);
+        assert_eq!(images[1].src, "data:image/png;base64,");
+        assert_eq!((images[1].width, images[1].height), (Some(800), Some(600)));
+        assert_eq!(images[1].alt.as_deref(), Some("photo"));
+
+        opts.max_data_url_bytes = None;
+        let images = scraper.parse_html(&html, &opts).unwrap().images;
+        assert_eq!(images[1].data_url.as_deref(), Some(large.as_str()));
+    }
+
+    #[test]
+    fn test_challenge_page_detection() {
+        assert!(ScraperService::is_challenge_page(
+            "<html><head><title>Just a moment...</title></head><body></body></html>"
+        ));
+        assert!(ScraperService::is_challenge_page(
+            r#"<html><body><div id="cf-browser-verification">Checking</div></body></html>"#
+        ));
+        assert!(!ScraperService::is_challenge_page(
+            "<html><head><title>Just a moment in history</title></head><body><p>Article</p></body></html>"
+        ));
+        assert!(!ScraperService::is_challenge_page(
+            "<html><head><title>Release notes</title></head><body><p>Article</p></body></html>"
+        ));
+    }
+
+    #[test]
+    fn test_paywall_detection() {
+        let selectors = crate::config::Config::default().paywall_selectors;
+        let scraper = ScraperService::new().with_paywall_selectors(&selectors);
+        let opts = options("https://news.example.com/story");
+
+        let marked = r#"<html><body><article><p>Opening paragraph of the story.</p>
+            <div class="paywall"><p>Keep reading with a subscription.</p></div></article></body></html>"#;
+        assert!(scraper.parse_html(marked, &opts).unwrap().paywalled);
+
+        let teaser = r#"<html><body><article><p>Opening paragraph of the story.</p>
+            <p>Subscribe to continue reading.</p></article></body></html>"#;
+        assert!(scraper.parse_html(teaser, &opts).unwrap().paywalled);
+
+        let structured = r#"<html><head><script type="application/ld+json">
+            {"@type": "NewsArticle", "isAccessibleForFree": false}</script></head>
+            <body><p>Opening paragraph of the story.</p></body></html>"#;
+        assert!(scraper.parse_html(structured, &opts).unwrap().paywalled);
+
+        // Only structured data counts, not a page that merely quotes it.
+        let quoted = r#"<html><body><p>Set <code>"isAccessibleForFree": false</code> on paywalled articles.</p>
+            <pre>{"isAccessibleForFree": false}</pre></body></html>"#;
+        assert!(!scraper.parse_html(quoted, &opts).unwrap().paywalled);
+
+        let long_read = format!(
+            "<html><body><article>{}</article><footer>Already a subscriber? Sign in</footer></body></html>",
+            "<p>Plenty of freely readable article text here.</p>".repeat(80)
+        );
+        assert!(!scraper.parse_html(&long_read, &opts).unwrap().paywalled);
+
+        let open = r#"<html><body><article><p>Opening paragraph of the story.</p></article></body></html>"#;
+        assert!(!scraper.parse_html(open, &opts).unwrap().paywalled);
+    }
+
+    #[test]
+    fn test_meta_robots_directives() {
+        let scraper = ScraperService::new();
+        let opts = options("https://example.com");
+
+        let html = r#"<html><head><meta name="robots" content="noindex, follow"></head><body></body></html>"#;
+        let robots = scraper.parse_html(html, &opts).unwrap().robots;
+        assert!(robots.noindex);
+        assert!(!robots.nofollow);
+
+        let html = r#"<html><head><meta name="ROBOTS" content="NONE"></head><body></body></html>"#;
+        let robots = scraper.parse_html(html, &opts).unwrap().robots;
+        assert!(robots.noindex && robots.nofollow);
+
+        let html = "<html><head></head><body></body></html>";
+        assert_eq!(scraper.parse_html(html, &opts).unwrap().robots, MetaRobots::default());
+    }
+
+    #[test]
+    fn test_meta_robots_nofollow_drops_links_when_respected() {
+        let scraper = ScraperService::new();
+        let html = r#"<html><head><meta name="robots" content="nofollow"></head>
+            <body><a href="/a">A</a><a href="/b">B</a></body></html>"#;
+
+        let opts = options("https://example.com");
+        assert_eq!(scraper.parse_html(html, &opts).unwrap().links.len(), 2);
+
+        let mut opts = options("https://example.com");
+        opts.respect_meta_robots = true;
+        assert!(scraper.parse_html(html, &opts).unwrap().links.is_empty());
+    }
+
+    #[test]
+    fn test_rss_and_atom_feeds_are_found_and_resolved() {
+        let html = r#"<html><head>
+            <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.xml">
+            <link rel="Alternate" type="Application/Atom+XML" href="atom.xml">
+            <link rel="alternate" type="application/rss+xml" href="https://example.com/feed.xml">
+            <link rel="alternate" hreflang="de" type="text/html" href="/de/">
+            <link rel="stylesheet" type="text/css" href="/site.css">
+        </head><body><p>Body</p></body></html>"#;
+        let snapshot = ScraperService::new().parse_html(html, &options("https://example.com/blog/post")).unwrap();
+
+        assert_eq!(snapshot.feeds, vec![
+            "https://example.com/feed.xml".to_string(),
+            "https://example.com/blog/atom.xml".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_link_rel_is_captured() {
+        let html = r#"<html><body><a href="/ad" rel="NoFollow  noopener">Ad</a><a href="/plain">Plain</a></body></html>"#;
+        let links = ScraperService::new().parse_html(html, &options("https://example.com")).unwrap().links;
+
+        assert_eq!(links[0].rel.as_deref(), Some("nofollow noopener"));
+        assert_eq!(links[1].rel, None);
+    }
+
+    #[test]
+    fn test_remove_selector_drops_identical_blocks_and_keeps_siblings() {
+        let html = r#"<html><body>
+            <p>Intro</p>
+            <aside class="promo">Buy now</aside>
+            <p>Middle</p>
+            <aside class="promo">Buy now</aside>
+            <p>Outro</p>
+        </body></html>"#;
+        let mut opts = options("https://example.com");
+        opts.remove_selector = Some("aside".to_string());
+
+        let snapshot = ScraperService::new().parse_html(html, &opts).unwrap();
+
+        assert!(!snapshot.html.contains("<aside"));
+        assert!(!snapshot.html.contains("Buy now"));
+        assert!(snapshot.html.contains("<p>Intro</p>"));
+        assert!(snapshot.html.contains("<p>Middle</p>"));
+        assert!(snapshot.html.contains("<p>Outro</p>"));
+    }
+
+    #[test]
+    fn test_remove_selector_does_not_remove_identical_markup_elsewhere() {
+        let html = r#"<html><body>
+            <div class="promo"><p>Sale</p></div>
+            <article><p>Sale</p></article>
+        </body></html>"#;
+        let mut opts = options("https://example.com");
+        opts.remove_selector = Some(".promo > p".to_string());
+
+        let snapshot = ScraperService::new().parse_html(html, &opts).unwrap();
+
+        assert!(snapshot.html.contains(r#"<div class="promo"></div>"#));
+        assert!(snapshot.html.contains("<article><p>Sale</p></article>"));
+    }
+
+    #[test]
+    fn test_remove_selector_on_targeted_fragment() {
+        let html = r#"<html><body><article><p>Keep</p><aside>Drop</aside></article></body></html>"#;
+        let mut opts = options("https://example.com");
+        opts.target_selector = Some("article".to_string());
+        opts.remove_selector = Some("aside".to_string());
+
+        let snapshot = ScraperService::new().parse_html(html, &opts).unwrap();
+
+        assert_eq!(snapshot.html, "<article><p>Keep</p></article>");
+    }
+
+    #[test]
+    fn test_meta_refresh_target() {
+        let base = url::Url::parse("https://example.com/old/page").unwrap();
+        let target = |content: &str| {
+            let html = format!(r#"<html><head><meta http-equiv="Refresh" content="{}"></head></html>"#, content);
+            ScraperService::meta_refresh_target(&html, &base).map(|u| u.to_string())
+        };
+
+        assert_eq!(target("0;url=/new"), Some("https://example.com/new".to_string()));
+        assert_eq!(target("0; URL='next.html'"), Some("https://example.com/old/next.html".to_string()));
+        assert_eq!(target("3, https://other.com/"), Some("https://other.com/".to_string()));
+        assert_eq!(target("30"), None);
+        assert_eq!(target("0;url=https://example.com/old/page#top"), None);
+        assert_eq!(target("0;url=javascript:alert(1)"), None);
+        assert_eq!(ScraperService::meta_refresh_target("<p>No refresh</p>", &base), None);
+    }
+}
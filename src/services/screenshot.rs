@@ -1,23 +1,104 @@
 use crate::config::Config;
 use crate::error::{AppError, Result};
+use crate::models::CrawlerOptions;
+use crate::services::browser::VIEWPORT;
+use crate::services::screenshot_store::{LocalScreenshotStore, S3ScreenshotStore, ScreenshotStore};
+use sha2::{Digest, Sha256};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use uuid::Uuid;
-use tracing::debug;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotNaming {
+    /// Sanitized URL prefix plus a random UUID; every capture gets a new file.
+    Uuid,
+    /// Sanitized URL prefix plus a hash of the URL, capture mode, viewport
+    /// and what the page was loaded as; identical requests map to the same
+    /// file.
+    Hash,
+}
+
+impl ScreenshotNaming {
+    /// `Config::validate` rejects anything but `uuid` and `hash`.
+    fn parse(raw: &str) -> Self {
+        match raw.trim().to_lowercase().as_str() {
+            "hash" => Self::Hash,
+            _ => Self::Uuid,
+        }
+    }
+}
+
+/// The parts of a request, besides the URL, that change what the origin
+/// serves: cookies, identity, proxy and body. Captures that differ in any of
+/// them get different files, so one client's logged-in page is never reused
+/// for another.
+fn client_inputs(options: &CrawlerOptions) -> String {
+    let mut input = String::new();
+    if let Some(cookies) = &options.cookies {
+        input.push_str(&format!("\ncookies={}", cookies));
+    }
+    if !options.cookies_json.is_empty() {
+        input.push_str(&format!("\ncookies_json={}", serde_json::to_string(&options.cookies_json).unwrap_or_default()));
+    }
+    if let Some(user_agent) = &options.user_agent {
+        input.push_str(&format!("\nuser_agent={}", user_agent));
+    }
+    if let Some(stealth) = options.stealth {
+        input.push_str(&format!("\nstealth={}", stealth));
+    }
+    if let Some(proxy) = &options.proxy_url {
+        input.push_str(&format!("\nproxy={}", proxy));
+    }
+    if let Some(referer) = &options.referer {
+        input.push_str(&format!("\nreferer={}", referer));
+    }
+    if let Some(post) = &options.post_body {
+        input.push_str(&format!("\npost={}\n{}", post.content_type, post.body));
+    }
+    input
+}
 
 pub struct ScreenshotService {
     screenshot_dir: PathBuf,
+    naming: ScreenshotNaming,
+    store: Arc<dyn ScreenshotStore>,
+    /// Whether captures go to `screenshot_dir` rather than a remote store.
+    local: bool,
 }
 
 impl ScreenshotService {
     pub fn new(config: &Config) -> Self {
+        let (store, local): (Arc<dyn ScreenshotStore>, bool) = match config.screenshot_backend.as_str() {
+            "s3" => match S3ScreenshotStore::from_config(config) {
+                Some(store) => (Arc::new(store), false),
+                None => {
+                    warn!("SCREENSHOT_BACKEND=s3 is incomplete, keeping screenshots on local disk");
+                    (Arc::new(LocalScreenshotStore::new(config.screenshot_dir.clone())), true)
+                }
+            },
+            _ => (Arc::new(LocalScreenshotStore::new(config.screenshot_dir.clone())), true),
+        };
+
         Self {
             screenshot_dir: config.screenshot_dir.clone(),
+            naming: ScreenshotNaming::parse(&config.screenshot_naming),
+            store,
+            local,
         }
     }
 
+    pub fn with_store(mut self, store: Arc<dyn ScreenshotStore>) -> Self {
+        self.store = store;
+        self.local = false;
+        self
+    }
+
+    /// Create `SCREENSHOT_DIR` when captures are kept on local disk.
     pub async fn initialize(&self) -> Result<()> {
-        if !self.screenshot_dir.exists() {
+        if self.local && !self.screenshot_dir.exists() {
             fs::create_dir_all(&self.screenshot_dir)
                 .await
                 .map_err(|e| AppError::ScreenshotError(format!(
@@ -27,30 +108,48 @@ impl ScreenshotService {
         Ok(())
     }
 
-    pub async fn save_screenshot(&self, data: &[u8], url: &str) -> Result<String> {
-        let filename = self.generate_filename(url);
-        let filepath = self.screenshot_dir.join(&filename);
-
-        fs::write(&filepath, data)
-            .await
-            .map_err(|e| AppError::ScreenshotError(format!(
-                "Failed to save screenshot: {}", e
-            )))?;
+    pub async fn save_screenshot(&self, data: &[u8], options: &CrawlerOptions, full_page: bool) -> Result<String> {
+        let filename = self.generate_filename(options, full_page);
+        self.store.put(&filename, data).await
+    }
 
-        debug!("Screenshot saved: {:?}", filepath);
+    /// Return the URL of an earlier capture of the same request if it is
+    /// younger than `max_age`. Only hash naming maps requests to stable files,
+    /// so UUID mode never finds one.
+    pub async fn find_fresh(&self, options: &CrawlerOptions, full_page: bool, max_age: Duration) -> Option<String> {
+        if self.naming != ScreenshotNaming::Hash {
+            return None;
+        }
 
-        Ok(format!("/screenshots/{}", filename))
+        let filename = self.generate_filename(options, full_page);
+        self.store.find_fresh(&filename, max_age).await
     }
 
-    fn generate_filename(&self, url: &str) -> String {
-        let uuid = Uuid::new_v4();
+    fn generate_filename(&self, options: &CrawlerOptions, full_page: bool) -> String {
+        let url = options.url.as_str();
         let sanitized_url = url
             .chars()
             .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
             .take(50)
             .collect::<String>();
 
-        format!("{}_{}.png", sanitized_url, uuid)
+        match self.naming {
+            ScreenshotNaming::Uuid => format!("{}_{}.png", sanitized_url, Uuid::new_v4()),
+            ScreenshotNaming::Hash => {
+                let mode = if full_page { "pageshot" } else { "screenshot" };
+                let mut input = format!("{}\n{}\n{}x{}", url, mode, VIEWPORT.0, VIEWPORT.1);
+                if let Some(clip) = &options.screenshot_clip {
+                    input.push_str(&format!("\nclip={}", clip));
+                }
+                if let Some(device) = &options.device {
+                    input.push_str(&format!("\ndevice={}", device));
+                }
+                input.push_str(&client_inputs(options));
+                let digest = Sha256::digest(input.as_bytes());
+                let hash: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+                format!("{}_{}.png", sanitized_url, hash)
+            }
+        }
     }
 
     pub async fn get_screenshot(&self, filename: &str) -> Result<Vec<u8>> {
@@ -108,3 +207,179 @@ impl ScreenshotService {
         Ok(deleted)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PostBody, ScreenshotClip};
+
+    fn options(url: &str) -> CrawlerOptions {
+        CrawlerOptions::new(url.to_string())
+    }
+
+    fn service(naming: &str) -> ScreenshotService {
+        let config = Config {
+            screenshot_naming: naming.to_string(),
+            ..Config::default()
+        };
+        ScreenshotService::new(&config)
+    }
+
+    #[test]
+    fn test_hash_naming_is_deterministic() {
+        let service = service("hash");
+        let first = service.generate_filename(&options("https://example.com/page"), false);
+        let second = service.generate_filename(&options("https://example.com/page"), false);
+
+        assert_eq!(first, second);
+        assert!(first.starts_with("httpsexamplecompage_"));
+        assert!(first.ends_with(".png"));
+    }
+
+    #[test]
+    fn test_hash_naming_separates_mode_and_shared_prefix() {
+        let service = service("hash");
+        let long = "https://example.com/a/very/long/path/that/goes/past/fifty/characters";
+        let screenshot = service.generate_filename(&options(long), false);
+
+        assert_ne!(screenshot, service.generate_filename(&options(long), true));
+        assert_ne!(screenshot, service.generate_filename(&options(&format!("{}?page=2", long)), false));
+
+        let mut clipped = options(long);
+        clipped.screenshot_clip = Some(ScreenshotClip { x: 0.0, y: 0.0, width: 200.0, height: 100.0 });
+        assert_ne!(screenshot, service.generate_filename(&clipped, false));
+        let mut device = options(long);
+        device.device = Some("iphone-13".to_string());
+        assert_ne!(screenshot, service.generate_filename(&device, false));
+    }
+
+    #[test]
+    fn test_hash_naming_separates_client_inputs() {
+        let service = service("hash");
+        let url = "https://example.com/account";
+        let anonymous = service.generate_filename(&options(url), false);
+
+        let variants: Vec<fn(&mut CrawlerOptions)> = vec![
+            |o| o.cookies = Some("session=abc".to_string()),
+            |o| o.cookies_json = vec![serde_json::from_str(r#"{"name":"session","value":"abc"}"#).unwrap()],
+            |o| o.user_agent = Some("Custom/1.0".to_string()),
+            |o| o.stealth = Some(true),
+            |o| o.proxy_url = Some("http://proxy.example.com:8080".to_string()),
+            |o| o.referer = Some("https://www.google.com/".to_string()),
+            |o| o.post_body = Some(PostBody { body: "q=1".to_string(), content_type: "text/plain".to_string() }),
+        ];
+        let mut names = vec![anonymous];
+        for apply in variants {
+            let mut variant = options(url);
+            apply(&mut variant);
+            names.push(service.generate_filename(&variant, false));
+        }
+
+        let unique: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(unique.len(), names.len(), "{:?}", names);
+
+        let mut other_session = options(url);
+        other_session.cookies = Some("session=xyz".to_string());
+        assert_ne!(service.generate_filename(&other_session, false), names[1]);
+        let mut other_cookie = options(url);
+        other_cookie.cookies_json = vec![serde_json::from_str(r#"{"name":"session","value":"xyz"}"#).unwrap()];
+        assert_ne!(service.generate_filename(&other_cookie, false), names[2]);
+    }
+
+    #[test]
+    fn test_uuid_naming_is_unique() {
+        let service = service("uuid");
+        assert_ne!(
+            service.generate_filename(&options("https://example.com"), false),
+            service.generate_filename(&options("https://example.com"), false)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_fresh_respects_naming_and_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            screenshot_dir: dir.path().to_path_buf(),
+            screenshot_naming: "hash".to_string(),
+            ..Config::default()
+        };
+        let service = ScreenshotService::new(&config);
+        let request = options("https://example.com/fresh");
+
+        assert!(service.find_fresh(&request, false, Duration::from_secs(60)).await.is_none());
+
+        let saved = service.save_screenshot(b"png", &request, false).await.unwrap();
+        assert_eq!(service.find_fresh(&request, false, Duration::from_secs(60)).await, Some(saved));
+        assert!(service.find_fresh(&request, true, Duration::from_secs(60)).await.is_none());
+        assert!(service.find_fresh(&request, false, Duration::ZERO).await.is_none());
+
+        let mut logged_in = request.clone();
+        logged_in.cookies = Some("session=abc".to_string());
+        assert!(service.find_fresh(&logged_in, false, Duration::from_secs(60)).await.is_none());
+
+        let uuid_service = ScreenshotService::new(&Config {
+            screenshot_naming: "uuid".to_string(),
+            ..config
+        });
+        assert!(uuid_service.find_fresh(&request, false, Duration::from_secs(60)).await.is_none());
+    }
+
+    #[derive(Default)]
+    struct RecordingStore {
+        puts: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ScreenshotStore for RecordingStore {
+        async fn put(&self, filename: &str, _data: &[u8]) -> Result<String> {
+            self.puts.lock().push(filename.to_string());
+            Ok(format!("https://bucket.example.com/{}", filename))
+        }
+
+        async fn find_fresh(&self, filename: &str, _max_age: Duration) -> Option<String> {
+            self.puts.lock().iter()
+                .any(|p| p == filename)
+                .then(|| format!("https://bucket.example.com/{}", filename))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_initialize_creates_the_directory_only_for_local_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = Config {
+            screenshot_dir: dir.path().join("local"),
+            ..Config::default()
+        };
+        ScreenshotService::new(&local).initialize().await.unwrap();
+        assert!(local.screenshot_dir.is_dir());
+
+        let s3 = Config {
+            screenshot_dir: dir.path().join("s3"),
+            screenshot_backend: "s3".to_string(),
+            screenshot_s3_bucket: Some("shots".to_string()),
+            screenshot_s3_access_key_id: Some("key".to_string()),
+            screenshot_s3_secret_access_key: Some("secret".to_string()),
+            ..Config::default()
+        };
+        ScreenshotService::new(&s3).initialize().await.unwrap();
+        assert!(!s3.screenshot_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_custom_store_receives_captures() {
+        let store = Arc::new(RecordingStore::default());
+        let service = service("hash").with_store(store.clone());
+        let request = options("https://example.com/remote");
+
+        let saved = service.save_screenshot(b"png", &request, false).await.unwrap();
+        assert!(saved.starts_with("https://bucket.example.com/httpsexamplecomremote_"));
+        assert_eq!(store.puts.lock().len(), 1);
+        assert_eq!(service.find_fresh(&request, false, Duration::from_secs(60)).await, Some(saved));
+    }
+
+    #[test]
+    fn test_naming_is_parsed() {
+        assert_eq!(ScreenshotNaming::parse("uuid"), ScreenshotNaming::Uuid);
+        assert_eq!(ScreenshotNaming::parse(" HASH "), ScreenshotNaming::Hash);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::config::Config;
 use crate::error::{AppError, Result};
 use dashmap::DashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::{Duration, Instant};
 use tracing::warn;
 use url::Url;
@@ -86,17 +86,11 @@ impl SecurityService {
     }
 
     fn is_localhost_ip(&self, host: &str) -> bool {
-        if let Ok(ip) = host.parse::<IpAddr>() {
+        let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = bare_host.parse::<IpAddr>() {
             return match ip {
-                IpAddr::V4(ipv4) => {
-                    ipv4.is_loopback() ||
-                    ipv4.is_private() ||
-                    ipv4.is_link_local() ||
-                    ipv4.octets()[0] == 127
-                }
-                IpAddr::V6(ipv6) => {
-                    ipv6.is_loopback()
-                }
+                IpAddr::V4(ipv4) => Self::is_internal_ipv4(&ipv4),
+                IpAddr::V6(ipv6) => Self::is_internal_ipv6(&ipv6),
             };
         }
 
@@ -104,6 +98,25 @@ impl SecurityService {
         patterns.iter().any(|p| host.starts_with(p))
     }
 
+    fn is_internal_ipv4(ipv4: &Ipv4Addr) -> bool {
+        ipv4.is_loopback() ||
+        ipv4.is_private() ||
+        ipv4.is_link_local() ||
+        ipv4.octets()[0] == 127
+    }
+
+    fn is_internal_ipv6(ipv6: &Ipv6Addr) -> bool {
+        if let Some(mapped) = ipv6.to_ipv4_mapped() {
+            return Self::is_internal_ipv4(&mapped);
+        }
+
+        let first_segment = ipv6.segments()[0];
+        let is_unique_local = (first_segment & 0xfe00) == 0xfc00;
+        let is_link_local = (first_segment & 0xffc0) == 0xfe80;
+
+        ipv6.is_loopback() || is_unique_local || is_link_local
+    }
+
     pub fn check_circuit_breaker(&self, domain: &str) -> Result<()> {
         if let Some(state) = self.circuit_breakers.get(domain) {
             if let Some(open_until) = state.open_until {
@@ -160,12 +173,19 @@ impl SecurityService {
 
         if entry.requests > max_requests {
             warn!("Rate limit exceeded for domain: {}", domain);
-            return Err(AppError::RateLimitExceeded(domain.to_string()));
+            return Err(AppError::RateLimitExceeded { domain: domain.to_string(), retry_after: None });
         }
 
         Ok(())
     }
 
+    /// Whether `domain` or a parent of it is listed in `PRIORITY_DOMAINS`.
+    pub fn is_priority_domain(&self, domain: &str) -> bool {
+        let domain = domain.to_lowercase();
+        self.config.priority_domains.iter()
+            .any(|listed| domain == *listed || domain.ends_with(&format!(".{}", listed)))
+    }
+
     pub fn check_domain_count(&self, domains: &[String]) -> Result<()> {
         if domains.len() > self.config.max_domains_per_page {
             return Err(AppError::TooManyDomains(domains.len()));
@@ -173,6 +193,13 @@ impl SecurityService {
         Ok(())
     }
 
+    pub fn check_batch_size(&self, count: usize) -> Result<()> {
+        if count > self.config.max_batch_urls {
+            return Err(AppError::TooManyUrls { count, limit: self.config.max_batch_urls });
+        }
+        Ok(())
+    }
+
     pub fn extract_domain(url: &Url) -> String {
         url.host_str().unwrap_or("unknown").to_string()
     }
@@ -183,3 +210,51 @@ impl Default for SecurityService {
         Self::new(Config::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_ipv6_unique_local() {
+        let security = SecurityService::default();
+        assert!(security.is_localhost_ip("fc00::1"));
+        assert!(security.is_localhost_ip("fd12:3456:789a::1"));
+        assert!(matches!(
+            security.validate_url("http://[fc00::1]/"),
+            Err(AppError::BlockedUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_blocks_ipv6_link_local() {
+        let security = SecurityService::default();
+        assert!(security.is_localhost_ip("fe80::1"));
+        assert!(security.is_localhost_ip("[fe80::1]"));
+        assert!(matches!(
+            security.validate_url("http://[fe80::1]:8080/"),
+            Err(AppError::BlockedUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_blocks_ipv4_mapped_private_addresses() {
+        let security = SecurityService::default();
+        assert!(security.is_localhost_ip("::ffff:10.0.0.1"));
+        assert!(security.is_localhost_ip("::ffff:127.0.0.1"));
+        assert!(security.is_localhost_ip("::ffff:192.168.1.1"));
+        assert!(!security.is_localhost_ip("::ffff:8.8.8.8"));
+        assert!(matches!(
+            security.validate_url("http://[::ffff:10.0.0.1]/"),
+            Err(AppError::BlockedUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_allows_public_addresses() {
+        let security = SecurityService::default();
+        assert!(!security.is_localhost_ip("2001:4860:4860::8888"));
+        assert!(!security.is_localhost_ip("8.8.8.8"));
+        assert!(security.validate_url("https://example.com/").is_ok());
+    }
+}
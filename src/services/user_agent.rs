@@ -128,6 +128,12 @@ mod tests {
         assert_eq!(svc.resolve(None), "DEFAULT");
     }
 
+    #[test]
+    fn blank_override_uses_configured_default() {
+        let svc = UserAgentService::new(&cfg("Operator/2.0", vec![], "off"));
+        assert_eq!(svc.resolve(Some("   ")), "Operator/2.0");
+    }
+
     #[test]
     fn round_robin_cycles_through_pool() {
         let svc = UserAgentService::new(&cfg("D", vec!["A", "B", "C"], "round_robin"));